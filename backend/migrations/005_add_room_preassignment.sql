@@ -0,0 +1,4 @@
+-- Allow rooms to be pre-assigned to confirmed bookings, before check-in
+-- A pinned pre-assignment is never moved when re-packing rooms to fit new bookings
+
+ALTER TABLE bookings ADD COLUMN room_pinned BOOLEAN NOT NULL DEFAULT FALSE;
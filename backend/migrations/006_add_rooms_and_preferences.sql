@@ -0,0 +1,16 @@
+-- Create rooms table, describing the features of rooms in a hotel
+-- Rooms which are not listed here (but are within the hotel's room_count) have default features
+
+CREATE TABLE rooms (
+    hotel_id        BIGINT NOT NULL REFERENCES hotels(id),
+    room_number     INTEGER NOT NULL CHECK (room_number > 0),
+    floor           INTEGER NOT NULL DEFAULT 1,
+    quiet           BOOLEAN NOT NULL DEFAULT FALSE,
+    near_elevator   BOOLEAN NOT NULL DEFAULT FALSE,
+    accessible      BOOLEAN NOT NULL DEFAULT FALSE,
+
+    PRIMARY KEY (hotel_id, room_number)
+);
+
+-- Room preferences of the guest, used when assigning a room at check-in
+ALTER TABLE bookings ADD COLUMN preferences JSONB NOT NULL DEFAULT '{}';
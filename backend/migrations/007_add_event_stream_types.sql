@@ -0,0 +1,7 @@
+-- Events can belong to different kinds of streams (bookings, hotel housekeeping, ...)
+-- Stream IDs are only unique within a stream type
+
+ALTER TABLE events ADD COLUMN stream_type TEXT NOT NULL DEFAULT 'booking';
+
+ALTER TABLE events DROP CONSTRAINT unique_stream_version;
+ALTER TABLE events ADD CONSTRAINT unique_stream_version UNIQUE (stream_type, stream_id, version);
@@ -0,0 +1,5 @@
+-- Track the housekeeping (cleaning) status of rooms
+-- Rooms start out inspected, i.e. ready for the next guest
+
+ALTER TABLE rooms ADD COLUMN housekeeping_status TEXT NOT NULL DEFAULT 'inspected'
+    CHECK (housekeeping_status IN ('dirty', 'cleaning', 'clean', 'inspected'));
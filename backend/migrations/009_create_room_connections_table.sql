@@ -0,0 +1,13 @@
+-- Create room connections table, describing which rooms are next to each other
+-- 'adjacent' rooms are next to each other, 'connecting' rooms also have a door between them
+-- Each pair of rooms is stored once, with the lower room number first
+
+CREATE TABLE room_connections (
+    hotel_id    BIGINT NOT NULL REFERENCES hotels(id),
+    room_a      INTEGER NOT NULL CHECK (room_a > 0),
+    room_b      INTEGER NOT NULL,
+    kind        TEXT NOT NULL CHECK (kind IN ('adjacent', 'connecting')),
+
+    PRIMARY KEY (hotel_id, room_a, room_b),
+    CHECK (room_a < room_b)
+);
@@ -0,0 +1,8 @@
+-- Add the no-show booking status, for guests who didn't arrive
+-- Bookings are marked as no-shows once the hotel's cutoff time on the arrival date has passed
+
+ALTER TABLE bookings DROP CONSTRAINT bookings_status_check;
+ALTER TABLE bookings ADD CONSTRAINT bookings_status_check
+    CHECK (status IN ('confirmed', 'checked_in', 'checked_out', 'cancelled', 'no_show'));
+
+ALTER TABLE hotels ADD COLUMN no_show_cutoff TIME NOT NULL DEFAULT '23:59';
@@ -0,0 +1,10 @@
+-- Add tentative holds: a held booking takes up a room until it's confirmed or the hold expires
+
+ALTER TABLE bookings DROP CONSTRAINT bookings_status_check;
+ALTER TABLE bookings ADD CONSTRAINT bookings_status_check
+    CHECK (status IN ('held', 'confirmed', 'checked_in', 'checked_out', 'cancelled', 'no_show', 'expired'));
+
+ALTER TABLE bookings ADD COLUMN hold_expires_at TIMESTAMPTZ NULL;
+
+-- Index for finding expired holds
+CREATE INDEX idx_bookings_hold_expiry ON bookings (hold_expires_at) WHERE status = 'held';
@@ -0,0 +1,7 @@
+-- Link the bookings of a multi-room group booking, so that they can be managed together
+
+CREATE SEQUENCE IF NOT EXISTS booking_group_id_seq START 1;
+
+ALTER TABLE bookings ADD COLUMN group_id BIGINT NULL;
+
+CREATE INDEX idx_bookings_group_id ON bookings (group_id) WHERE group_id IS NOT NULL;
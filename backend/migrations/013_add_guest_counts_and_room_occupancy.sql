@@ -0,0 +1,6 @@
+-- Track the size of the party on each booking, and how many guests each room can fit
+
+ALTER TABLE bookings ADD COLUMN adults INTEGER NOT NULL DEFAULT 1 CHECK (adults >= 1);
+ALTER TABLE bookings ADD COLUMN children INTEGER NOT NULL DEFAULT 0 CHECK (children >= 0);
+
+ALTER TABLE rooms ADD COLUMN max_occupancy INTEGER NOT NULL DEFAULT 2 CHECK (max_occupancy >= 1);
@@ -0,0 +1,16 @@
+-- Guest profiles, projected from the guest event streams
+
+CREATE SEQUENCE IF NOT EXISTS guest_id_seq START 1;
+
+CREATE TABLE guests (
+    id BIGINT PRIMARY KEY,
+    name TEXT NOT NULL,
+    email TEXT NULL,
+    phone TEXT NULL,
+    preferences JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+ALTER TABLE bookings ADD COLUMN guest_id BIGINT NULL REFERENCES guests (id);
+
+CREATE INDEX idx_bookings_guest_id ON bookings (guest_id) WHERE guest_id IS NOT NULL;
@@ -0,0 +1,20 @@
+-- Waitlist for fully-booked dates, projected from the waitlist event streams
+-- Once a room frees up, the entry is offered a held booking
+
+CREATE SEQUENCE IF NOT EXISTS waitlist_entry_id_seq START 1;
+
+CREATE TABLE waitlist_entries (
+    id BIGINT PRIMARY KEY,
+    hotel_id BIGINT NOT NULL REFERENCES hotels (id),
+    guest_name TEXT NOT NULL,
+    email TEXT NULL,
+    start_time DATE NOT NULL,
+    end_time DATE NOT NULL,
+    adults INTEGER NOT NULL DEFAULT 1,
+    children INTEGER NOT NULL DEFAULT 0,
+    status TEXT NOT NULL DEFAULT 'waiting' CHECK (status IN ('waiting', 'offered', 'cancelled')),
+    booking_id BIGINT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_waitlist_entries_hotel_status ON waitlist_entries (hotel_id, status, id);
@@ -0,0 +1,13 @@
+-- Allow selling more rooms than a hotel physically has, to absorb expected no-shows
+-- The limit is either an absolute number of rooms or a percentage of the hotel's rooms
+
+ALTER TABLE hotels ADD COLUMN overbooking_rooms INTEGER NULL CHECK (overbooking_rooms >= 0);
+ALTER TABLE hotels ADD COLUMN overbooking_percentage INTEGER NULL
+    CHECK (overbooking_percentage BETWEEN 0 AND 100);
+ALTER TABLE hotels ADD CONSTRAINT hotels_single_overbooking_limit
+    CHECK (overbooking_rooms IS NULL OR overbooking_percentage IS NULL);
+
+-- Guests who couldn't get a room because the hotel was overbooked are "walked" to another hotel
+ALTER TABLE bookings DROP CONSTRAINT bookings_status_check;
+ALTER TABLE bookings ADD CONSTRAINT bookings_status_check
+    CHECK (status IN ('held', 'confirmed', 'checked_in', 'checked_out', 'cancelled', 'no_show', 'expired', 'walked'));
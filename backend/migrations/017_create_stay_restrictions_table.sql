@@ -0,0 +1,17 @@
+-- Stay restrictions: rules such as a minimum stay over New Year, or no arrivals on Saturdays
+-- A restriction applies to the dates from start_date (inclusive) to end_date (exclusive), optionally
+-- only on some days of the week (ISO numbers, Monday = 1)
+
+CREATE TABLE stay_restrictions (
+    id BIGSERIAL PRIMARY KEY,
+    hotel_id BIGINT NOT NULL REFERENCES hotels (id),
+    start_date DATE NOT NULL,
+    end_date DATE NOT NULL,
+    days_of_week INTEGER[] NOT NULL DEFAULT '{}',
+    min_stay INTEGER NULL CHECK (min_stay >= 1),
+    closed_to_arrival BOOLEAN NOT NULL DEFAULT FALSE,
+    closed_to_departure BOOLEAN NOT NULL DEFAULT FALSE,
+    CHECK (start_date < end_date)
+);
+
+CREATE INDEX idx_stay_restrictions_hotel_dates ON stay_restrictions (hotel_id, start_date, end_date);
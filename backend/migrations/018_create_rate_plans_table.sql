@@ -0,0 +1,23 @@
+-- Room types, and the rate plans which price stays in each room type of a hotel
+-- All amounts are in minor units of the plan's currency (e.g. cents), so that prices are exact
+
+ALTER TABLE rooms ADD COLUMN room_type TEXT NOT NULL DEFAULT 'standard';
+
+CREATE TABLE rate_plans (
+    id BIGSERIAL PRIMARY KEY,
+    hotel_id BIGINT NOT NULL REFERENCES hotels (id),
+    room_type TEXT NOT NULL,
+    name TEXT NOT NULL,
+    currency TEXT NOT NULL CHECK (currency ~ '^[A-Z]{3}$'),
+    base_rate BIGINT NOT NULL CHECK (base_rate >= 0),
+    -- Applied to the rates of Friday and Saturday nights; may be negative
+    weekend_surcharge_percent INTEGER NOT NULL DEFAULT 0 CHECK (weekend_surcharge_percent >= -100),
+    -- Nightly rates overriding the base rate in some periods: [{start_date, end_date, nightly_rate}]
+    seasons JSONB NOT NULL DEFAULT '[]',
+    -- Discounts on the whole stay from a number of nights: [{min_nights, percent}]
+    length_of_stay_discounts JSONB NOT NULL DEFAULT '[]',
+    UNIQUE (hotel_id, room_type)
+);
+
+-- The price quoted when the booking was made, which later rate changes don't alter
+ALTER TABLE bookings ADD COLUMN quote JSONB NULL;
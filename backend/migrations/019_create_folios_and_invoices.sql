@@ -0,0 +1,34 @@
+-- Folios: the charges, payments and adjustments posted to each booking
+-- Amounts are in minor units of the booking's currency; charges are positive, credits negative
+
+CREATE SEQUENCE folio_entry_id_seq;
+
+CREATE TABLE folio_entries (
+    id BIGINT PRIMARY KEY,
+    booking_id BIGINT NOT NULL REFERENCES bookings (id),
+    kind TEXT NOT NULL CHECK (kind IN ('room_charge', 'extra', 'payment', 'adjustment')),
+    description TEXT NOT NULL,
+    amount BIGINT NOT NULL,
+    -- The date the charge is for (the night, for room charges)
+    date DATE NOT NULL,
+    posted_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_folio_entries_booking_id ON folio_entries (booking_id);
+
+-- Each night is charged only once
+CREATE UNIQUE INDEX idx_folio_entries_room_charge_night ON folio_entries (booking_id, date)
+    WHERE kind = 'room_charge';
+
+-- Invoices are numbered sequentially per hotel, without gaps
+ALTER TABLE hotels ADD COLUMN last_invoice_number BIGINT NOT NULL DEFAULT 0;
+
+CREATE TABLE invoices (
+    hotel_id BIGINT NOT NULL REFERENCES hotels (id),
+    invoice_number BIGINT NOT NULL,
+    booking_id BIGINT NOT NULL UNIQUE REFERENCES bookings (id),
+    issued_at TIMESTAMPTZ NOT NULL,
+    -- The invoice as issued, which doesn't change if the folio does
+    document JSONB NOT NULL,
+    PRIMARY KEY (hotel_id, invoice_number)
+);
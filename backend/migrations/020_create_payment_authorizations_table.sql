@@ -0,0 +1,14 @@
+-- Deposits authorized on the guests' cards through the payment provider
+-- An authorization is closed by capturing (part of) it, or by voiding it
+
+CREATE TABLE payment_authorizations (
+    authorization_id TEXT PRIMARY KEY,
+    booking_id BIGINT NOT NULL REFERENCES bookings (id),
+    amount BIGINT NOT NULL CHECK (amount > 0),
+    currency TEXT NOT NULL,
+    status TEXT NOT NULL DEFAULT 'authorized' CHECK (status IN ('authorized', 'captured', 'voided')),
+    captured_amount BIGINT NOT NULL DEFAULT 0,
+    authorized_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_payment_authorizations_booking_id ON payment_authorizations (booking_id);
@@ -0,0 +1,7 @@
+-- Cancellation policies of rate plans: {free_until_days_before, penalty: {type, value}}
+-- The policy is copied into the quote of each booking, so that later changes don't alter it
+ALTER TABLE rate_plans ADD COLUMN cancellation_policy JSONB NULL;
+
+ALTER TABLE folio_entries DROP CONSTRAINT folio_entries_kind_check;
+ALTER TABLE folio_entries ADD CONSTRAINT folio_entries_kind_check
+    CHECK (kind IN ('room_charge', 'extra', 'payment', 'adjustment', 'cancellation_fee'));
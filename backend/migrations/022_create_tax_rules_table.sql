@@ -0,0 +1,17 @@
+-- Taxes and fees added to the price of each night, such as VAT, city tax or a service fee
+-- Rates are in basis points (hundredths of a percent) of the night's room price, so that they are
+-- exact; per person amounts are in minor units of the rate plan's currency
+
+CREATE TABLE tax_rules (
+    id BIGSERIAL PRIMARY KEY,
+    hotel_id BIGINT NOT NULL REFERENCES hotels (id),
+    name TEXT NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('rate', 'per_person_per_night')),
+    amount BIGINT NOT NULL CHECK (amount >= 0)
+);
+
+CREATE INDEX idx_tax_rules_hotel_id ON tax_rules (hotel_id);
+
+ALTER TABLE folio_entries DROP CONSTRAINT folio_entries_kind_check;
+ALTER TABLE folio_entries ADD CONSTRAINT folio_entries_kind_check
+    CHECK (kind IN ('room_charge', 'extra', 'payment', 'adjustment', 'cancellation_fee', 'tax'));
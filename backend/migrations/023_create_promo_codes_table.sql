@@ -0,0 +1,20 @@
+-- Promo codes for discounts on stays, which marketing hands out
+-- Codes are stored in upper case and matched case-insensitively
+
+CREATE TABLE promo_codes (
+    code TEXT PRIMARY KEY CHECK (code = UPPER(code)),
+    -- Only valid at this hotel, or at every hotel if NULL
+    hotel_id BIGINT NULL REFERENCES hotels (id),
+    discount_type TEXT NOT NULL CHECK (discount_type IN ('percentage', 'fixed')),
+    -- A percentage, or an amount in minor units of the currency
+    discount_value BIGINT NOT NULL CHECK (discount_value >= 0),
+    currency TEXT NULL CHECK (currency ~ '^[A-Z]{3}$'),
+    -- Stays arriving in this window (inclusive) can use the code
+    valid_from DATE NULL,
+    valid_until DATE NULL,
+    -- Unlimited if NULL
+    max_uses INTEGER NULL CHECK (max_uses >= 0),
+    used_count INTEGER NOT NULL DEFAULT 0,
+    CHECK ((discount_type = 'fixed') = (currency IS NOT NULL)),
+    CHECK (discount_type = 'fixed' OR discount_value <= 100)
+);
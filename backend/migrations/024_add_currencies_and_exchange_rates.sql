@@ -0,0 +1,18 @@
+-- Each hotel prices its stays, and keeps its folios, in its base currency
+ALTER TABLE hotels ADD COLUMN base_currency TEXT NOT NULL DEFAULT 'EUR' CHECK (base_currency ~ '^[A-Z]{3}$');
+
+-- Hotels which already have rate plans keep their currency
+UPDATE hotels SET base_currency = plans.currency
+FROM (SELECT DISTINCT ON (hotel_id) hotel_id, currency FROM rate_plans ORDER BY hotel_id, id) plans
+WHERE plans.hotel_id = hotels.id;
+
+-- Exchange rates for showing quotes in the guests' currencies
+-- Rates are in millionths of to_currency per unit of from_currency, so that they are exact
+CREATE TABLE exchange_rates (
+    from_currency TEXT NOT NULL CHECK (from_currency ~ '^[A-Z]{3}$'),
+    to_currency TEXT NOT NULL CHECK (to_currency ~ '^[A-Z]{3}$'),
+    rate BIGINT NOT NULL CHECK (rate > 0),
+    updated_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (from_currency, to_currency),
+    CHECK (from_currency <> to_currency)
+);
@@ -0,0 +1,19 @@
+-- Loyalty points of guests, projected from the loyalty event streams (one per guest)
+-- Points are earned on completed stays and redeemed against bookings
+
+ALTER TABLE guests ADD COLUMN loyalty_points BIGINT NOT NULL DEFAULT 0 CHECK (loyalty_points >= 0);
+
+CREATE TABLE loyalty_transactions (
+    id BIGSERIAL PRIMARY KEY,
+    guest_id BIGINT NOT NULL REFERENCES guests (id),
+    booking_id BIGINT NOT NULL REFERENCES bookings (id),
+    kind TEXT NOT NULL CHECK (kind IN ('earned', 'redeemed', 'reversed')),
+    -- The change to the guest's balance, negative for redemptions
+    points BIGINT NOT NULL,
+    -- The amount credited on the booking's folio, in minor units of the hotel's base currency
+    amount BIGINT NOT NULL DEFAULT 0,
+    created_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_loyalty_transactions_guest_id ON loyalty_transactions (guest_id);
+CREATE INDEX idx_loyalty_transactions_booking_id ON loyalty_transactions (booking_id);
@@ -0,0 +1,4 @@
+-- Hotels are archived rather than deleted, so that their bookings, folios and invoices are kept
+-- Archived hotels are hidden from guests and don't take new bookings
+
+ALTER TABLE hotels ADD COLUMN archived_at TIMESTAMPTZ NULL;
@@ -0,0 +1,9 @@
+-- The time zone each hotel is in. Booking dates, the no-show cutoff and the times below are all
+-- in the hotel's local time.
+
+ALTER TABLE hotels ADD COLUMN time_zone TEXT NOT NULL DEFAULT 'UTC';
+
+-- Guests may move in from check_in_time on the arrival date, and have to leave by check_out_time
+-- on the departure date, unless they're let in early or leave late
+ALTER TABLE hotels ADD COLUMN check_in_time TIME NOT NULL DEFAULT '15:00';
+ALTER TABLE hotels ADD COLUMN check_out_time TIME NOT NULL DEFAULT '11:00';
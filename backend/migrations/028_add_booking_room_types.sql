@@ -0,0 +1,10 @@
+-- The room type each booking was sold as; bookings only get rooms of their type
+-- Bookings without one (made before room types were tracked, and not priced) may get any room
+
+ALTER TABLE bookings ADD COLUMN room_type TEXT NULL;
+
+UPDATE bookings SET room_type = COALESCE(
+    quote->>'room_type',
+    (SELECT rooms.room_type FROM rooms
+     WHERE rooms.hotel_id = bookings.hotel_id AND rooms.room_number = bookings.room_number)
+);
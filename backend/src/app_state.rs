@@ -1,5 +1,7 @@
 use crate::db::DbPool;
 use crate::event_processor::EventProcessor;
+use crate::payments::PaymentProvider;
+use crate::room_assignment::RoomAssignmentStrategy;
 use reqwest::Client;
 use std::sync::Arc;
 
@@ -8,4 +10,6 @@ pub struct AppState {
     pub db_pool: DbPool,
     pub event_processor: Arc<EventProcessor>,
     pub http_client: Client,
+    pub room_assignment_strategy: Arc<dyn RoomAssignmentStrategy>,
+    pub payment_provider: Arc<dyn PaymentProvider>,
 }
\ No newline at end of file
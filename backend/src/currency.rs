@@ -0,0 +1,119 @@
+use crate::db::get_exchange_rate;
+use crate::models::{ConvertedPrice, PriceQuote};
+use anyhow::Result;
+use sqlx::PgConnection;
+
+/// Exchange rates are in millionths, e.g. 1085000 converts 1.00 EUR into 1.085 USD
+pub const RATE_SCALE: i64 = 1_000_000;
+
+/// Whether a string is an ISO 4217 currency code, e.g. "EUR"
+pub fn is_currency_code(code: &str) -> bool {
+    code.len() == 3 && code.chars().all(|c| c.is_ascii_uppercase())
+}
+
+/// Divides, rounding half away from zero. The divisor must be positive.
+fn div_round(dividend: i128, divisor: i128) -> i128 {
+    (dividend + divisor / 2 * dividend.signum()) / divisor
+}
+
+/// Converts an amount in minor units at an exchange rate in millionths, rounded to whole minor
+/// units. Both currencies are assumed to have two decimal places.
+pub fn convert_amount(amount: i64, rate: i64) -> i64 {
+    div_round(
+        i128::from(amount) * i128::from(rate),
+        i128::from(RATE_SCALE),
+    ) as i64
+}
+
+/// The rate for converting back the other way, to the nearest millionth.
+pub fn invert_rate(rate: i64) -> i64 {
+    div_round(
+        i128::from(RATE_SCALE) * i128::from(RATE_SCALE),
+        i128::from(rate),
+    ) as i64
+}
+
+/// Adds the price of a quote in another currency, at the given exchange rate.
+pub fn convert_quote(quote: &mut PriceQuote, currency: &str, rate: i64) {
+    quote.converted = Some(ConvertedPrice {
+        currency: currency.to_string(),
+        exchange_rate: rate,
+        total: convert_amount(quote.total, rate),
+        total_with_taxes: convert_amount(quote.total_with_taxes(), rate),
+    });
+}
+
+/// The current rate for converting amounts from one currency into another. The rate the other way
+/// round is inverted if there's no direct one. Returns `None` if there's neither.
+pub async fn find_exchange_rate(
+    conn: &mut PgConnection,
+    from: &str,
+    to: &str,
+) -> Result<Option<i64>> {
+    if from == to {
+        return Ok(Some(RATE_SCALE));
+    }
+    if let Some(rate) = get_exchange_rate(&mut *conn, from, to).await? {
+        return Ok(Some(rate.rate));
+    }
+
+    let inverse = get_exchange_rate(&mut *conn, to, from).await?;
+    Ok(inverse.map(|rate| invert_rate(rate.rate)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::NightlyPrice;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_convert_amount_rounds_to_minor_units() {
+        // 100.00 EUR at 1.085432 is 108.5432 USD
+        assert_eq!(convert_amount(10000, 1_085_432), 10854);
+        // 0.05 at 1.1 is 0.055
+        assert_eq!(convert_amount(5, 1_100_000), 6);
+        assert_eq!(convert_amount(-5, 1_100_000), -6);
+        // Large amounts don't overflow
+        assert_eq!(
+            convert_amount(1_000_000_000_000, 150_000_000),
+            150_000_000_000_000
+        );
+    }
+
+    #[test]
+    fn test_invert_rate() {
+        assert_eq!(invert_rate(2_000_000), 500_000);
+        // 1 / 1.085432 = 0.921292...
+        assert_eq!(invert_rate(1_085_432), 921_292);
+    }
+
+    #[test]
+    fn test_convert_quote_keeps_rate() {
+        let mut quote = PriceQuote {
+            rate_plan_id: 1,
+            room_type: "standard".to_string(),
+            currency: "CHF".to_string(),
+            nights: vec![NightlyPrice {
+                date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                amount: 20000,
+            }],
+            subtotal: 20000,
+            discount: 0,
+            total: 20000,
+            cancellation_policy: None,
+            taxes: vec![],
+            promo: None,
+            converted: None,
+        };
+
+        convert_quote(&mut quote, "EUR", 1_050_000);
+
+        let converted = quote.converted.unwrap();
+        assert_eq!(converted.currency, "EUR");
+        assert_eq!(converted.exchange_rate, 1_050_000);
+        assert_eq!(converted.total, 21000);
+        // The quote itself stays in the hotel's currency
+        assert_eq!(quote.total, 20000);
+    }
+}
@@ -1,32 +1,263 @@
-use crate::models::{Booking, BookingStatus, Hotel};
+use crate::models::{
+    AuthorizationStatus, Booking, BookingStatus, ExchangeRate, FolioEntry, FolioEntryKind, Guest,
+    Hotel, HousekeepingStatus, Invoice, LoyaltyTransaction, LoyaltyTransactionKind,
+    OverbookingLimit, PaymentAuthorization, PriceQuote, PromoCode, PromoDiscount, RatePlan, Room,
+    RoomConnection, RoomConnectionKind, StayRestriction, TaxCharge, TaxRule, WaitlistEntry,
+    WaitlistStatus,
+};
 use anyhow::{Context, Result, anyhow};
-use chrono::NaiveDate;
-use sqlx::{Executor, PgPool, Pool, Postgres, Row, Transaction, migrate::MigrateError};
+use chrono::{DateTime, NaiveDate, Utc, Weekday};
+use chrono_tz::Tz;
+use sqlx::{
+    Executor, PgConnection, PgPool, Pool, Postgres, Row, Transaction, migrate::MigrateError,
+    types::Json,
+};
 use std::str::FromStr;
 
-const SELECT_HOTEL_QUERY: &str = "SELECT id, name, room_count FROM hotels WHERE id = $1";
-const SELECT_ALL_HOTELS_QUERY: &str = "SELECT id, name, room_count FROM hotels ORDER BY name";
+const SELECT_HOTEL_QUERY: &str = "SELECT id, name, room_count, no_show_cutoff, overbooking_rooms, overbooking_percentage, base_currency, time_zone, check_in_time, check_out_time, archived_at FROM hotels WHERE id = $1";
+const SELECT_HOTEL_FOR_UPDATE_QUERY: &str = "SELECT id, name, room_count, no_show_cutoff, overbooking_rooms, overbooking_percentage, base_currency, time_zone, check_in_time, check_out_time, archived_at FROM hotels WHERE id = $1 FOR UPDATE";
+const SELECT_HOTEL_FOR_SHARE_QUERY: &str = "SELECT id, name, room_count, no_show_cutoff, overbooking_rooms, overbooking_percentage, base_currency, time_zone, check_in_time, check_out_time, archived_at FROM hotels WHERE id = $1 FOR SHARE";
+const SELECT_ALL_HOTELS_QUERY: &str = "SELECT id, name, room_count, no_show_cutoff, overbooking_rooms, overbooking_percentage, base_currency, time_zone, check_in_time, check_out_time, archived_at FROM hotels ORDER BY name";
+const SELECT_ACTIVE_HOTELS_QUERY: &str = "SELECT id, name, room_count, no_show_cutoff, overbooking_rooms, overbooking_percentage, base_currency, time_zone, check_in_time, check_out_time, archived_at FROM hotels WHERE archived_at IS NULL ORDER BY name";
+const INSERT_HOTEL_QUERY: &str = "INSERT INTO hotels (name, room_count, no_show_cutoff, base_currency, time_zone, check_in_time, check_out_time) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING id";
+const UPDATE_HOTEL_QUERY: &str = "UPDATE hotels SET name = $2, room_count = $3, time_zone = $4, check_in_time = $5, check_out_time = $6 WHERE id = $1";
+const ARCHIVE_HOTEL_QUERY: &str = "UPDATE hotels SET archived_at = COALESCE(archived_at, NOW()) WHERE id = $1 RETURNING archived_at";
+const UPDATE_HOTEL_OVERBOOKING_LIMIT_QUERY: &str =
+    "UPDATE hotels SET overbooking_rooms = $2, overbooking_percentage = $3 WHERE id = $1";
+const SELECT_CHECKED_IN_PRICED_BOOKINGS_QUERY: &str =
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type
+     FROM bookings
+     WHERE status = 'checked_in'
+     AND quote IS NOT NULL
+     ORDER BY id
+     FOR UPDATE SKIP LOCKED";
+const SELECT_FOLIO_ENTRIES_QUERY: &str =
+    "SELECT id, booking_id, kind, description, amount, date, posted_at
+     FROM folio_entries
+     WHERE booking_id = $1
+     ORDER BY date, id";
+const SELECT_NEXT_FOLIO_ENTRY_ID_QUERY: &str = "SELECT nextval('folio_entry_id_seq') as next_id";
+const UPDATE_NEXT_INVOICE_NUMBER_QUERY: &str = "UPDATE hotels SET last_invoice_number = last_invoice_number + 1 WHERE id = $1 RETURNING last_invoice_number";
+const SELECT_INVOICE_BY_BOOKING_QUERY: &str = "SELECT document FROM invoices WHERE booking_id = $1";
+const SELECT_PAYMENT_AUTHORIZATIONS_QUERY: &str =
+    "SELECT authorization_id, booking_id, amount, currency, status, captured_amount, authorized_at
+     FROM payment_authorizations
+     WHERE booking_id = $1
+     ORDER BY authorized_at, authorization_id";
+const SELECT_OPEN_AUTHORIZATIONS_QUERY: &str =
+    "SELECT authorization_id, booking_id, amount, currency, status, captured_amount, authorized_at
+     FROM payment_authorizations
+     WHERE booking_id = $1
+     AND status = 'authorized'
+     ORDER BY authorized_at, authorization_id
+     FOR UPDATE";
+const SELECT_RATE_PLAN_QUERY: &str =
+    "SELECT id, hotel_id, room_type, name, currency, base_rate, weekend_surcharge_percent, seasons, length_of_stay_discounts, cancellation_policy
+     FROM rate_plans
+     WHERE hotel_id = $1 AND room_type = $2";
+const SELECT_RATE_PLANS_BY_HOTEL_QUERY: &str =
+    "SELECT id, hotel_id, room_type, name, currency, base_rate, weekend_surcharge_percent, seasons, length_of_stay_discounts, cancellation_policy
+     FROM rate_plans
+     WHERE hotel_id = $1
+     ORDER BY room_type";
+const UPSERT_RATE_PLAN_QUERY: &str =
+    "INSERT INTO rate_plans (hotel_id, room_type, name, currency, base_rate, weekend_surcharge_percent, seasons, length_of_stay_discounts, cancellation_policy)
+     VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)
+     ON CONFLICT (hotel_id, room_type) DO UPDATE SET
+         name = EXCLUDED.name,
+         currency = EXCLUDED.currency,
+         base_rate = EXCLUDED.base_rate,
+         weekend_surcharge_percent = EXCLUDED.weekend_surcharge_percent,
+         seasons = EXCLUDED.seasons,
+         length_of_stay_discounts = EXCLUDED.length_of_stay_discounts,
+         cancellation_policy = EXCLUDED.cancellation_policy
+     RETURNING id";
+const SELECT_PROMO_CODE_QUERY: &str =
+    "SELECT code, hotel_id, discount_type, discount_value, currency, valid_from, valid_until, max_uses, used_count
+     FROM promo_codes
+     WHERE code = $1";
+const SELECT_ALL_PROMO_CODES_QUERY: &str =
+    "SELECT code, hotel_id, discount_type, discount_value, currency, valid_from, valid_until, max_uses, used_count
+     FROM promo_codes
+     ORDER BY code";
+const INSERT_PROMO_CODE_QUERY: &str =
+    "INSERT INTO promo_codes (code, hotel_id, discount_type, discount_value, currency, valid_from, valid_until, max_uses)
+     VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+     ON CONFLICT (code) DO NOTHING";
+const REDEEM_PROMO_CODE_QUERY: &str =
+    "UPDATE promo_codes SET used_count = used_count + 1
+     WHERE code = $1
+     AND (hotel_id IS NULL OR hotel_id = $2)
+     AND (valid_from IS NULL OR valid_from <= $3)
+     AND (valid_until IS NULL OR valid_until >= $3)
+     AND (max_uses IS NULL OR used_count < max_uses)
+     RETURNING code, hotel_id, discount_type, discount_value, currency, valid_from, valid_until, max_uses, used_count";
+const SELECT_EXCHANGE_RATE_QUERY: &str = "SELECT from_currency, to_currency, rate, updated_at
+     FROM exchange_rates
+     WHERE from_currency = $1 AND to_currency = $2";
+const SELECT_ALL_EXCHANGE_RATES_QUERY: &str = "SELECT from_currency, to_currency, rate, updated_at
+     FROM exchange_rates
+     ORDER BY from_currency, to_currency";
+const UPSERT_EXCHANGE_RATE_QUERY: &str =
+    "INSERT INTO exchange_rates (from_currency, to_currency, rate, updated_at)
+     VALUES ($1, $2, $3, $4)
+     ON CONFLICT (from_currency, to_currency) DO UPDATE SET
+         rate = EXCLUDED.rate,
+         updated_at = EXCLUDED.updated_at";
+const SELECT_TAX_RULES_QUERY: &str =
+    "SELECT id, hotel_id, name, kind, amount FROM tax_rules WHERE hotel_id = $1 ORDER BY id";
+const INSERT_TAX_RULE_QUERY: &str =
+    "INSERT INTO tax_rules (hotel_id, name, kind, amount) VALUES ($1, $2, $3, $4) RETURNING id";
+const DELETE_TAX_RULE_QUERY: &str = "DELETE FROM tax_rules WHERE hotel_id = $1 AND id = $2";
+const SELECT_STAY_RESTRICTIONS_QUERY: &str =
+    "SELECT id, start_date, end_date, days_of_week, min_stay, closed_to_arrival, closed_to_departure
+     FROM stay_restrictions
+     WHERE hotel_id = $1
+     AND start_date <= $3
+     AND end_date > $2
+     ORDER BY start_date, id";
+const INSERT_STAY_RESTRICTION_QUERY: &str =
+    "INSERT INTO stay_restrictions (hotel_id, start_date, end_date, days_of_week, min_stay, closed_to_arrival, closed_to_departure)
+     VALUES ($1, $2, $3, $4, $5, $6, $7)
+     RETURNING id";
+const DELETE_STAY_RESTRICTION_QUERY: &str =
+    "DELETE FROM stay_restrictions WHERE hotel_id = $1 AND id = $2";
 const SELECT_NEXT_BOOKING_ID_QUERY: &str = "SELECT nextval('booking_id_seq') as next_id";
+const SELECT_NEXT_GROUP_ID_QUERY: &str = "SELECT nextval('booking_group_id_seq') as next_id";
 const SELECT_OVERLAPPING_BOOKINGS_QUERY: &str =
-    "SELECT id, hotel_id, room_number, guest_name, start_time, end_time, status 
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type 
      FROM bookings 
      WHERE hotel_id = $1 
-     AND status IN ('confirmed', 'checked_in')
+     AND status IN ('confirmed', 'checked_in', 'held')
      AND start_time < $3 
      AND end_time > $2
      ORDER BY start_time
      FOR UPDATE";
+const SELECT_CURRENT_AND_FUTURE_BOOKINGS_QUERY: &str =
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type
+     FROM bookings
+     WHERE hotel_id = $1
+     AND status IN ('confirmed', 'checked_in', 'held')
+     AND end_time > $2
+     ORDER BY start_time
+     FOR UPDATE";
+const SELECT_OVERLAPPING_BOOKINGS_FOR_READ_QUERY: &str =
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type 
+     FROM bookings 
+     WHERE hotel_id = $1 
+     AND status IN ('confirmed', 'checked_in', 'held')
+     AND start_time < $3 
+     AND end_time > $2
+     ORDER BY start_time";
 const SELECT_BOOKINGS_BY_HOTEL_AND_DATE_QUERY: &str =
-    "SELECT id, hotel_id, room_number, guest_name, start_time, end_time, status 
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type 
      FROM bookings 
      WHERE hotel_id = $1 
      AND start_time <= $2 
      AND end_time >= $2
      ORDER BY start_time DESC";
+const SELECT_ROOMS_BY_HOTEL_QUERY: &str =
+    "SELECT room_number, floor, quiet, near_elevator, accessible, housekeeping_status, max_occupancy, room_type
+     FROM rooms
+     WHERE hotel_id = $1
+     ORDER BY room_number";
+const SELECT_ROOM_CONNECTIONS_BY_HOTEL_QUERY: &str =
+    "SELECT room_a, room_b, kind FROM room_connections WHERE hotel_id = $1";
+const UPSERT_ROOM_QUERY: &str =
+    "INSERT INTO rooms (hotel_id, room_number, max_occupancy, room_type) VALUES ($1, $2, $3, $4)
+     ON CONFLICT (hotel_id, room_number) DO UPDATE SET max_occupancy = EXCLUDED.max_occupancy, room_type = EXCLUDED.room_type";
+const UPSERT_ROOM_CONNECTION_QUERY: &str =
+    "INSERT INTO room_connections (hotel_id, room_a, room_b, kind) VALUES ($1, $2, $3, $4)
+     ON CONFLICT (hotel_id, room_a, room_b) DO UPDATE SET kind = EXCLUDED.kind";
+const DELETE_ROOM_CONNECTION_QUERY: &str =
+    "DELETE FROM room_connections WHERE hotel_id = $1 AND room_a = $2 AND room_b = $3";
+const SELECT_DUE_NO_SHOW_BOOKINGS_QUERY: &str =
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type
+     FROM bookings
+     WHERE hotel_id = $1
+     AND status = 'confirmed'
+     AND start_time <= $2
+     ORDER BY start_time
+     FOR UPDATE SKIP LOCKED";
+const SELECT_OVERSTAYING_BOOKINGS_QUERY: &str =
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type
+     FROM bookings
+     WHERE hotel_id = $1
+     AND status = 'checked_in'
+     AND end_time < $2
+     ORDER BY end_time, room_number";
+const SELECT_HOTELS_WITH_EXPIRED_HOLDS_QUERY: &str = "SELECT DISTINCT hotel_id
+     FROM bookings
+     WHERE status = 'held'
+     AND hold_expires_at <= NOW()
+     ORDER BY hotel_id";
+const SELECT_EXPIRED_HOLDS_QUERY: &str =
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type
+     FROM bookings
+     WHERE hotel_id = $1
+     AND status = 'held'
+     AND hold_expires_at <= NOW()
+     ORDER BY hold_expires_at
+     FOR UPDATE SKIP LOCKED";
+const SELECT_GROUP_HOTEL_QUERY: &str = "SELECT hotel_id FROM bookings WHERE group_id = $1 LIMIT 1";
+const SELECT_GROUP_BOOKINGS_QUERY: &str =
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type
+     FROM bookings
+     WHERE group_id = $1
+     ORDER BY id
+     FOR UPDATE";
+const SELECT_BOOKINGS_BY_GUEST_QUERY: &str =
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type
+     FROM bookings
+     WHERE guest_id = $1
+     ORDER BY start_time DESC";
+const SELECT_NEXT_GUEST_ID_QUERY: &str = "SELECT nextval('guest_id_seq') as next_id";
+const SELECT_GUEST_QUERY: &str =
+    "SELECT id, name, email, phone, preferences FROM guests WHERE id = $1";
+const SELECT_ALL_GUESTS_QUERY: &str =
+    "SELECT id, name, email, phone, preferences FROM guests ORDER BY id";
+const SELECT_LOYALTY_BALANCE_QUERY: &str = "SELECT loyalty_points FROM guests WHERE id = $1";
+const SELECT_LOYALTY_BALANCE_FOR_UPDATE_QUERY: &str =
+    "SELECT loyalty_points FROM guests WHERE id = $1 FOR UPDATE";
+const SELECT_LOYALTY_TRANSACTIONS_BY_GUEST_QUERY: &str =
+    "SELECT id, guest_id, booking_id, kind, points, amount, created_at
+     FROM loyalty_transactions
+     WHERE guest_id = $1
+     ORDER BY created_at DESC, id DESC";
+const SELECT_LOYALTY_TRANSACTIONS_BY_BOOKING_QUERY: &str =
+    "SELECT id, guest_id, booking_id, kind, points, amount, created_at
+     FROM loyalty_transactions
+     WHERE booking_id = $1
+     ORDER BY created_at, id";
+const SELECT_NEXT_WAITLIST_ENTRY_ID_QUERY: &str =
+    "SELECT nextval('waitlist_entry_id_seq') as next_id";
+const SELECT_WAITLIST_ENTRY_QUERY: &str =
+    "SELECT id, hotel_id, guest_name, email, start_time, end_time, adults, children, status, booking_id
+     FROM waitlist_entries
+     WHERE id = $1";
+const SELECT_WAITLIST_BY_HOTEL_QUERY: &str =
+    "SELECT id, hotel_id, guest_name, email, start_time, end_time, adults, children, status, booking_id
+     FROM waitlist_entries
+     WHERE hotel_id = $1
+     AND status IN ('waiting', 'offered')
+     ORDER BY id";
+const SELECT_WAITING_ENTRIES_QUERY: &str =
+    "SELECT id, hotel_id, guest_name, email, start_time, end_time, adults, children, status, booking_id
+     FROM waitlist_entries
+     WHERE hotel_id = $1
+     AND status = 'waiting'
+     AND start_time >= $2
+     ORDER BY id
+     FOR UPDATE";
 const SELECT_BOOKING_BY_ID_QUERY: &str =
-    "SELECT id, hotel_id, room_number, guest_name, start_time, end_time, status 
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type 
      FROM bookings 
      WHERE id = $1";
+const SELECT_BOOKING_BY_ID_FOR_UPDATE_QUERY: &str =
+    "SELECT id, hotel_id, room_number, room_pinned, guest_name, start_time, end_time, status, preferences, hold_expires_at, group_id, adults, children, guest_id, quote, room_type
+     FROM bookings
+     WHERE id = $1
+     FOR UPDATE";
 
 pub type DbPool = Pool<Postgres>;
 
@@ -38,12 +269,29 @@ pub async fn run_migrations(pool: &DbPool) -> Result<(), MigrateError> {
     sqlx::migrate!("./migrations").run(pool).await
 }
 
-fn row_to_hotel(row: &sqlx::postgres::PgRow) -> Hotel {
-    Hotel {
+fn row_to_hotel(row: &sqlx::postgres::PgRow) -> Result<Hotel> {
+    let time_zone_str: String = row.get("time_zone");
+    let time_zone = Tz::from_str(&time_zone_str).map_err(|e| anyhow!(e))?;
+
+    Ok(Hotel {
         id: row.get("id"),
         name: row.get("name"),
         room_count: row.get("room_count"),
-    }
+        no_show_cutoff: row.get("no_show_cutoff"),
+        overbooking_limit: match (
+            row.get::<Option<i32>, _>("overbooking_rooms"),
+            row.get::<Option<i32>, _>("overbooking_percentage"),
+        ) {
+            (Some(rooms), _) => OverbookingLimit::Rooms(rooms),
+            (None, Some(percentage)) => OverbookingLimit::Percentage(percentage),
+            (None, None) => OverbookingLimit::None,
+        },
+        base_currency: row.get("base_currency"),
+        time_zone,
+        check_in_time: row.get("check_in_time"),
+        check_out_time: row.get("check_out_time"),
+        archived_at: row.get("archived_at"),
+    })
 }
 
 fn row_to_booking(row: &sqlx::postgres::PgRow) -> Result<Booking> {
@@ -54,10 +302,197 @@ fn row_to_booking(row: &sqlx::postgres::PgRow) -> Result<Booking> {
         id: row.get("id"),
         hotel_id: row.get("hotel_id"),
         room_number: row.get("room_number"),
+        room_pinned: row.get("room_pinned"),
+        guest_name: row.get("guest_name"),
+        start_time: row.get("start_time"),
+        end_time: row.get("end_time"),
+        status,
+        preferences: row.get::<Json<_>, _>("preferences").0,
+        hold_expires_at: row.get("hold_expires_at"),
+        group_id: row.get("group_id"),
+        adults: row.get("adults"),
+        children: row.get("children"),
+        guest_id: row.get("guest_id"),
+        quote: row
+            .get::<Option<Json<PriceQuote>>, _>("quote")
+            .map(|quote| quote.0),
+        room_type: row.get("room_type"),
+    })
+}
+
+fn row_to_guest(row: &sqlx::postgres::PgRow) -> Guest {
+    Guest {
+        id: row.get("id"),
+        name: row.get("name"),
+        email: row.get("email"),
+        phone: row.get("phone"),
+        preferences: row.get::<Json<_>, _>("preferences").0,
+    }
+}
+
+fn row_to_waitlist_entry(row: &sqlx::postgres::PgRow) -> Result<WaitlistEntry> {
+    let status_str: String = row.get("status");
+    let status = WaitlistStatus::from_str(&status_str).map_err(|e| anyhow!(e))?;
+
+    Ok(WaitlistEntry {
+        id: row.get("id"),
+        hotel_id: row.get("hotel_id"),
         guest_name: row.get("guest_name"),
+        email: row.get("email"),
         start_time: row.get("start_time"),
         end_time: row.get("end_time"),
+        adults: row.get("adults"),
+        children: row.get("children"),
+        status,
+        booking_id: row.get("booking_id"),
+    })
+}
+
+fn row_to_folio_entry(row: &sqlx::postgres::PgRow) -> Result<FolioEntry> {
+    let kind_str: String = row.get("kind");
+    let kind = FolioEntryKind::from_str(&kind_str).map_err(|e| anyhow!(e))?;
+
+    Ok(FolioEntry {
+        id: row.get("id"),
+        booking_id: row.get("booking_id"),
+        kind,
+        description: row.get("description"),
+        amount: row.get("amount"),
+        date: row.get("date"),
+        posted_at: row.get("posted_at"),
+    })
+}
+
+fn row_to_payment_authorization(row: &sqlx::postgres::PgRow) -> Result<PaymentAuthorization> {
+    let status_str: String = row.get("status");
+    let status = AuthorizationStatus::from_str(&status_str).map_err(|e| anyhow!(e))?;
+
+    Ok(PaymentAuthorization {
+        authorization_id: row.get("authorization_id"),
+        booking_id: row.get("booking_id"),
+        amount: row.get("amount"),
+        currency: row.get("currency"),
         status,
+        captured_amount: row.get("captured_amount"),
+        authorized_at: row.get("authorized_at"),
+    })
+}
+
+fn row_to_loyalty_transaction(row: &sqlx::postgres::PgRow) -> Result<LoyaltyTransaction> {
+    let kind_str: String = row.get("kind");
+    let kind = LoyaltyTransactionKind::from_str(&kind_str).map_err(|e| anyhow!(e))?;
+
+    Ok(LoyaltyTransaction {
+        id: row.get("id"),
+        guest_id: row.get("guest_id"),
+        booking_id: row.get("booking_id"),
+        kind,
+        points: row.get("points"),
+        amount: row.get("amount"),
+        created_at: row.get("created_at"),
+    })
+}
+
+fn row_to_rate_plan(row: &sqlx::postgres::PgRow) -> RatePlan {
+    RatePlan {
+        id: row.get("id"),
+        hotel_id: row.get("hotel_id"),
+        room_type: row.get("room_type"),
+        name: row.get("name"),
+        currency: row.get("currency"),
+        base_rate: row.get("base_rate"),
+        weekend_surcharge_percent: row.get("weekend_surcharge_percent"),
+        seasons: row.get::<Json<_>, _>("seasons").0,
+        length_of_stay_discounts: row.get::<Json<_>, _>("length_of_stay_discounts").0,
+        cancellation_policy: row
+            .get::<Option<Json<_>>, _>("cancellation_policy")
+            .map(|policy| policy.0),
+    }
+}
+
+fn row_to_promo_code(row: &sqlx::postgres::PgRow) -> Result<PromoCode> {
+    let value: i64 = row.get("discount_value");
+    let discount = match row.get::<&str, _>("discount_type") {
+        "percentage" => PromoDiscount::Percentage(i32::try_from(value)?),
+        "fixed" => PromoDiscount::Fixed {
+            amount: value,
+            currency: row.get("currency"),
+        },
+        discount_type => return Err(anyhow!("Invalid discount type: {}", discount_type)),
+    };
+
+    Ok(PromoCode {
+        code: row.get("code"),
+        hotel_id: row.get("hotel_id"),
+        discount,
+        valid_from: row.get("valid_from"),
+        valid_until: row.get("valid_until"),
+        max_uses: row.get("max_uses"),
+        used_count: row.get("used_count"),
+    })
+}
+
+fn row_to_exchange_rate(row: &sqlx::postgres::PgRow) -> ExchangeRate {
+    ExchangeRate {
+        from_currency: row.get("from_currency"),
+        to_currency: row.get("to_currency"),
+        rate: row.get("rate"),
+        updated_at: row.get("updated_at"),
+    }
+}
+
+fn row_to_tax_rule(row: &sqlx::postgres::PgRow) -> Result<TaxRule> {
+    let amount = row.get("amount");
+    let charge = match row.get::<&str, _>("kind") {
+        "rate" => TaxCharge::Rate(amount),
+        "per_person_per_night" => TaxCharge::PerPersonPerNight(amount),
+        kind => return Err(anyhow!("Invalid tax rule kind: {}", kind)),
+    };
+
+    Ok(TaxRule {
+        id: row.get("id"),
+        hotel_id: row.get("hotel_id"),
+        name: row.get("name"),
+        charge,
+    })
+}
+
+fn row_to_stay_restriction(row: &sqlx::postgres::PgRow) -> Result<StayRestriction> {
+    let days_of_week = row
+        .get::<Vec<i32>, _>("days_of_week")
+        .into_iter()
+        .map(|day| {
+            u8::try_from(day)
+                .ok()
+                .and_then(|day| Weekday::try_from(day.wrapping_sub(1)).ok())
+                .ok_or_else(|| anyhow!("Invalid day of week: {}", day))
+        })
+        .collect::<Result<_>>()?;
+
+    Ok(StayRestriction {
+        id: row.get("id"),
+        start_date: row.get("start_date"),
+        end_date: row.get("end_date"),
+        days_of_week,
+        min_stay: row.get("min_stay"),
+        closed_to_arrival: row.get("closed_to_arrival"),
+        closed_to_departure: row.get("closed_to_departure"),
+    })
+}
+
+fn row_to_room(row: &sqlx::postgres::PgRow) -> Result<Room> {
+    let status_str: String = row.get("housekeeping_status");
+    let housekeeping_status = HousekeepingStatus::from_str(&status_str).map_err(|e| anyhow!(e))?;
+
+    Ok(Room {
+        room_number: row.get("room_number"),
+        floor: row.get("floor"),
+        quiet: row.get("quiet"),
+        near_elevator: row.get("near_elevator"),
+        accessible: row.get("accessible"),
+        housekeeping_status,
+        max_occupancy: row.get("max_occupancy"),
+        room_type: row.get("room_type"),
     })
 }
 
@@ -72,17 +507,242 @@ where
         .await
         .with_context(|| format!("Failed to fetch hotel with ID {}", id))?;
 
-    Ok(row.map(|row| row_to_hotel(&row)))
+    row.map(|row| row_to_hotel(&row)).transpose()
+}
+
+/// Gets and locks a hotel exclusively, for changing it or releasing its rooms. Locks on a hotel
+/// are always taken before the ones on its bookings, so that concurrent changes can't deadlock.
+pub async fn get_and_lock_hotel_by_id(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+) -> Result<Option<Hotel>> {
+    let row = sqlx::query(SELECT_HOTEL_FOR_UPDATE_QUERY)
+        .bind(id)
+        .fetch_optional(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to lock hotel with ID {}", id))?;
+
+    row.map(|row| row_to_hotel(&row)).transpose()
+}
+
+/// Gets and locks a hotel in shared mode, for adding or assigning bookings, which may happen
+/// concurrently. See `get_and_lock_hotel_by_id`.
+pub async fn get_and_share_lock_hotel_by_id(
+    tx: &mut Transaction<'_, Postgres>,
+    id: i64,
+) -> Result<Option<Hotel>> {
+    let row = sqlx::query(SELECT_HOTEL_FOR_SHARE_QUERY)
+        .bind(id)
+        .fetch_optional(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to lock hotel with ID {}", id))?;
+
+    row.map(|row| row_to_hotel(&row)).transpose()
+}
+
+/// Sets how far a hotel may be overbooked.
+pub async fn update_hotel_overbooking_limit(
+    pool: &DbPool,
+    hotel_id: i64,
+    limit: OverbookingLimit,
+) -> Result<()> {
+    let (rooms, percentage) = match limit {
+        OverbookingLimit::None => (None, None),
+        OverbookingLimit::Rooms(rooms) => (Some(rooms), None),
+        OverbookingLimit::Percentage(percentage) => (None, Some(percentage)),
+    };
+
+    sqlx::query(UPDATE_HOTEL_OVERBOOKING_LIMIT_QUERY)
+        .bind(hotel_id)
+        .bind(rooms)
+        .bind(percentage)
+        .execute(pool)
+        .await
+        .with_context(|| format!("Failed to update overbooking limit of hotel {}", hotel_id))?;
+
+    Ok(())
 }
 
-/// Gets all hotels from the database pool.
+/// Gets all hotels from the database pool, including archived ones.
 pub async fn get_all_hotels(pool: &DbPool) -> Result<Vec<Hotel>> {
     let rows = sqlx::query(SELECT_ALL_HOTELS_QUERY)
         .fetch_all(pool)
         .await
         .context("Failed to fetch all hotels")?;
 
-    Ok(rows.into_iter().map(|row| row_to_hotel(&row)).collect())
+    rows.into_iter().map(|row| row_to_hotel(&row)).collect()
+}
+
+/// Gets the hotels which haven't been archived.
+pub async fn get_active_hotels(pool: &DbPool) -> Result<Vec<Hotel>> {
+    let rows = sqlx::query(SELECT_ACTIVE_HOTELS_QUERY)
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch active hotels")?;
+
+    rows.into_iter().map(|row| row_to_hotel(&row)).collect()
+}
+
+/// Adds a hotel, returning its ID.
+pub async fn insert_hotel(pool: &DbPool, hotel: &Hotel) -> Result<i64> {
+    let row = sqlx::query(INSERT_HOTEL_QUERY)
+        .bind(&hotel.name)
+        .bind(hotel.room_count)
+        .bind(hotel.no_show_cutoff)
+        .bind(&hotel.base_currency)
+        .bind(hotel.time_zone.name())
+        .bind(hotel.check_in_time)
+        .bind(hotel.check_out_time)
+        .fetch_one(pool)
+        .await
+        .with_context(|| format!("Failed to add hotel {}", hotel.name))?;
+
+    Ok(row.get("id"))
+}
+
+/// Updates the settings of a hotel which can be changed after it's created.
+pub async fn update_hotel(tx: &mut Transaction<'_, Postgres>, hotel: &Hotel) -> Result<()> {
+    sqlx::query(UPDATE_HOTEL_QUERY)
+        .bind(hotel.id)
+        .bind(&hotel.name)
+        .bind(hotel.room_count)
+        .bind(hotel.time_zone.name())
+        .bind(hotel.check_in_time)
+        .bind(hotel.check_out_time)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to update hotel {}", hotel.id))?;
+
+    Ok(())
+}
+
+/// Archives a hotel, unless it's archived already. Returns when the hotel was archived, or `None`
+/// if it doesn't exist.
+pub async fn archive_hotel(pool: &DbPool, hotel_id: i64) -> Result<Option<DateTime<Utc>>> {
+    let row = sqlx::query(ARCHIVE_HOTEL_QUERY)
+        .bind(hotel_id)
+        .fetch_optional(pool)
+        .await
+        .with_context(|| format!("Failed to archive hotel {}", hotel_id))?;
+
+    Ok(row.map(|row| row.get("archived_at")))
+}
+
+/// Gets all rooms of a hotel, numbered from 1 to the hotel's room count. Rooms which aren't
+/// configured in the `rooms` table get default features.
+pub async fn get_hotel_rooms<'a, E>(executor: E, hotel: &Hotel) -> Result<Vec<Room>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let rows = sqlx::query(SELECT_ROOMS_BY_HOTEL_QUERY)
+        .bind(hotel.id)
+        .fetch_all(executor)
+        .await
+        .with_context(|| format!("Failed to fetch rooms for hotel {}", hotel.id))?;
+
+    let mut rooms: Vec<Room> = (1..=hotel.room_count).map(Room::with_defaults).collect();
+    for row in rows {
+        let room = row_to_room(&row)?;
+        if let Some(slot) = rooms.get_mut((room.room_number - 1) as usize) {
+            *slot = room;
+        }
+    }
+
+    Ok(rooms)
+}
+
+/// Gets the adjacency and connecting-door relations between the rooms of a hotel.
+pub async fn get_room_connections<'a, E>(executor: E, hotel_id: i64) -> Result<Vec<RoomConnection>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let rows = sqlx::query(SELECT_ROOM_CONNECTIONS_BY_HOTEL_QUERY)
+        .bind(hotel_id)
+        .fetch_all(executor)
+        .await
+        .with_context(|| format!("Failed to fetch room connections for hotel {}", hotel_id))?;
+
+    rows.into_iter()
+        .map(|row| {
+            let kind_str: String = row.get("kind");
+            Ok(RoomConnection {
+                room_a: row.get("room_a"),
+                room_b: row.get("room_b"),
+                kind: RoomConnectionKind::from_str(&kind_str).map_err(|e| anyhow!(e))?,
+            })
+        })
+        .collect()
+}
+
+/// Saves the occupancy and type of a room. Rooms without a row yet get one, with default features.
+pub async fn upsert_room(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+    room: &Room,
+) -> Result<()> {
+    sqlx::query(UPSERT_ROOM_QUERY)
+        .bind(hotel_id)
+        .bind(room.room_number)
+        .bind(room.max_occupancy)
+        .bind(&room.room_type)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to update room {} of hotel {}",
+                room.room_number, hotel_id
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Adds a connection between two rooms, or changes its kind. The lower room number must come
+/// first.
+pub async fn upsert_room_connection(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+    connection: &RoomConnection,
+) -> Result<()> {
+    sqlx::query(UPSERT_ROOM_CONNECTION_QUERY)
+        .bind(hotel_id)
+        .bind(connection.room_a)
+        .bind(connection.room_b)
+        .bind(connection.kind.to_string())
+        .execute(&mut **tx)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to connect rooms {} and {} of hotel {}",
+                connection.room_a, connection.room_b, hotel_id
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Deletes the connection between two rooms, the lower room number first. Returns whether there
+/// was one.
+pub async fn delete_room_connection(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+    room_a: i32,
+    room_b: i32,
+) -> Result<bool> {
+    let result = sqlx::query(DELETE_ROOM_CONNECTION_QUERY)
+        .bind(hotel_id)
+        .bind(room_a)
+        .bind(room_b)
+        .execute(&mut **tx)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to disconnect rooms {} and {} of hotel {}",
+                room_a, room_b, hotel_id
+            )
+        })?;
+
+    Ok(result.rows_affected() > 0)
 }
 
 /// Generates the next booking ID using an existing database transaction.
@@ -95,6 +755,16 @@ pub async fn get_next_booking_id(tx: &mut Transaction<'_, Postgres>) -> Result<i
     Ok(row.get("next_id"))
 }
 
+/// Generates the ID of a new group booking.
+pub async fn get_next_group_id(tx: &mut Transaction<'_, Postgres>) -> Result<i64> {
+    let row = sqlx::query(SELECT_NEXT_GROUP_ID_QUERY)
+        .fetch_one(&mut **tx)
+        .await
+        .context("Failed to generate next group ID")?;
+
+    Ok(row.get("next_id"))
+}
+
 /// Gets overlapping bookings using an existing database transaction.
 /// This ensures consistent reads within a transaction context.
 pub async fn get_and_lock_overlapping_bookings(
@@ -119,16 +789,108 @@ pub async fn get_and_lock_overlapping_bookings(
     rows.into_iter().map(|row| row_to_booking(&row)).collect()
 }
 
+/// Gets and locks the bookings of a hotel which take up rooms on or after the given day.
+pub async fn get_and_lock_current_and_future_bookings(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+    from: NaiveDate,
+) -> Result<Vec<Booking>> {
+    let rows = sqlx::query(SELECT_CURRENT_AND_FUTURE_BOOKINGS_QUERY)
+        .bind(hotel_id)
+        .bind(from)
+        .fetch_all(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to fetch upcoming bookings for hotel {}", hotel_id))?;
+
+    rows.into_iter().map(|row| row_to_booking(&row)).collect()
+}
+
+/// Gets overlapping bookings without locking them, for read-only availability checks.
+pub async fn get_overlapping_bookings<'a, E>(
+    executor: E,
+    hotel_id: i64,
+    start_time: NaiveDate,
+    end_time: NaiveDate,
+) -> Result<Vec<Booking>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let rows = sqlx::query(SELECT_OVERLAPPING_BOOKINGS_FOR_READ_QUERY)
+        .bind(hotel_id)
+        .bind(start_time)
+        .bind(end_time)
+        .fetch_all(executor)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to fetch overlapping bookings for hotel {}",
+                hotel_id
+            )
+        })?;
+
+    rows.into_iter().map(|row| row_to_booking(&row)).collect()
+}
+
+/// Gets and locks the confirmed bookings of a hotel arriving on or before the given date, whose
+/// guests haven't checked in. Bookings locked by other transactions are skipped.
+pub async fn get_and_lock_due_no_show_bookings(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+    last_arrival: NaiveDate,
+) -> Result<Vec<Booking>> {
+    let rows = sqlx::query(SELECT_DUE_NO_SHOW_BOOKINGS_QUERY)
+        .bind(hotel_id)
+        .bind(last_arrival)
+        .fetch_all(&mut **tx)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to fetch bookings of hotel {} due to be marked as no-shows",
+                hotel_id
+            )
+        })?;
+
+    rows.into_iter().map(|row| row_to_booking(&row)).collect()
+}
+
+/// Gets the IDs of the hotels which have held bookings whose hold has expired.
+pub async fn get_hotels_with_expired_holds(pool: &DbPool) -> Result<Vec<i64>> {
+    let rows = sqlx::query(SELECT_HOTELS_WITH_EXPIRED_HOLDS_QUERY)
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch hotels with expired holds")?;
+
+    Ok(rows.iter().map(|row| row.get("hotel_id")).collect())
+}
+
+/// Gets and locks held bookings of a hotel whose hold has expired. Bookings locked by other
+/// transactions are skipped.
+pub async fn get_and_lock_expired_holds(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+) -> Result<Vec<Booking>> {
+    let rows = sqlx::query(SELECT_EXPIRED_HOLDS_QUERY)
+        .bind(hotel_id)
+        .fetch_all(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to fetch expired holds of hotel {}", hotel_id))?;
+
+    rows.into_iter().map(|row| row_to_booking(&row)).collect()
+}
+
 /// Gets bookings for a specific hotel that touch a specific date.
-pub async fn get_bookings_by_hotel_id_and_date(
-    pool: &DbPool,
+pub async fn get_bookings_by_hotel_id_and_date<'a, E>(
+    executor: E,
     hotel_id: i64,
     date: NaiveDate,
-) -> Result<Vec<Booking>> {
+) -> Result<Vec<Booking>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
     let rows = sqlx::query(SELECT_BOOKINGS_BY_HOTEL_AND_DATE_QUERY)
         .bind(hotel_id)
         .bind(date)
-        .fetch_all(pool)
+        .fetch_all(executor)
         .await
         .with_context(|| {
             format!(
@@ -140,16 +902,634 @@ pub async fn get_bookings_by_hotel_id_and_date(
     rows.into_iter().map(|row| row_to_booking(&row)).collect()
 }
 
-/// Gets a specific booking by ID using an existing database transaction.
-pub async fn get_booking_by_id<'a, E>(executor: E, booking_id: i64) -> Result<Option<Booking>>
-where
-    E: Executor<'a, Database = Postgres>,
-{
-    let row = sqlx::query(SELECT_BOOKING_BY_ID_QUERY)
-        .bind(booking_id)
-        .fetch_optional(executor)
+/// Gets checked-in bookings of a hotel whose departure date is before the given date.
+pub async fn get_overstaying_bookings(
+    pool: &DbPool,
+    hotel_id: i64,
+    date: NaiveDate,
+) -> Result<Vec<Booking>> {
+    let rows = sqlx::query(SELECT_OVERSTAYING_BOOKINGS_QUERY)
+        .bind(hotel_id)
+        .bind(date)
+        .fetch_all(pool)
         .await
-        .with_context(|| format!("Failed to fetch booking with ID {}", booking_id))?;
+        .with_context(|| {
+            format!(
+                "Failed to fetch overstaying bookings for hotel {}",
+                hotel_id
+            )
+        })?;
+
+    rows.into_iter().map(|row| row_to_booking(&row)).collect()
+}
+
+/// Gets the ID of the hotel a group booking is for.
+pub async fn get_group_hotel_id<'a, E>(executor: E, group_id: i64) -> Result<Option<i64>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let row = sqlx::query(SELECT_GROUP_HOTEL_QUERY)
+        .bind(group_id)
+        .fetch_optional(executor)
+        .await
+        .with_context(|| format!("Failed to fetch the hotel of group {}", group_id))?;
+
+    Ok(row.map(|row| row.get("hotel_id")))
+}
+
+/// Gets and locks all bookings of a group booking.
+pub async fn get_and_lock_group_bookings(
+    tx: &mut Transaction<'_, Postgres>,
+    group_id: i64,
+) -> Result<Vec<Booking>> {
+    let rows = sqlx::query(SELECT_GROUP_BOOKINGS_QUERY)
+        .bind(group_id)
+        .fetch_all(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to fetch bookings of group {}", group_id))?;
+
+    rows.into_iter().map(|row| row_to_booking(&row)).collect()
+}
+
+/// Gets a specific booking by ID using an existing database transaction.
+pub async fn get_booking_by_id<'a, E>(executor: E, booking_id: i64) -> Result<Option<Booking>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let row = sqlx::query(SELECT_BOOKING_BY_ID_QUERY)
+        .bind(booking_id)
+        .fetch_optional(executor)
+        .await
+        .with_context(|| format!("Failed to fetch booking with ID {}", booking_id))?;
 
     row.map(|row| row_to_booking(&row)).transpose()
 }
+
+/// Gets a specific booking by ID, locking it until the end of the transaction.
+pub async fn get_and_lock_booking_by_id(
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+) -> Result<Option<Booking>> {
+    let row = sqlx::query(SELECT_BOOKING_BY_ID_FOR_UPDATE_QUERY)
+        .bind(booking_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to fetch booking with ID {}", booking_id))?;
+
+    row.map(|row| row_to_booking(&row)).transpose()
+}
+
+/// Generates the ID of a new guest profile.
+pub async fn get_next_guest_id(tx: &mut Transaction<'_, Postgres>) -> Result<i64> {
+    let row = sqlx::query(SELECT_NEXT_GUEST_ID_QUERY)
+        .fetch_one(&mut **tx)
+        .await
+        .context("Failed to generate next guest ID")?;
+
+    Ok(row.get("next_id"))
+}
+
+/// Gets a guest profile by ID.
+pub async fn get_guest_by_id<'a, E>(executor: E, guest_id: i64) -> Result<Option<Guest>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let row = sqlx::query(SELECT_GUEST_QUERY)
+        .bind(guest_id)
+        .fetch_optional(executor)
+        .await
+        .with_context(|| format!("Failed to fetch guest with ID {}", guest_id))?;
+
+    Ok(row.map(|row| row_to_guest(&row)))
+}
+
+/// Gets all guest profiles.
+pub async fn get_all_guests(pool: &DbPool) -> Result<Vec<Guest>> {
+    let rows = sqlx::query(SELECT_ALL_GUESTS_QUERY)
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch guests")?;
+
+    Ok(rows.iter().map(row_to_guest).collect())
+}
+
+/// Gets the bookings of a guest, latest stays first.
+pub async fn get_bookings_by_guest_id(pool: &DbPool, guest_id: i64) -> Result<Vec<Booking>> {
+    let rows = sqlx::query(SELECT_BOOKINGS_BY_GUEST_QUERY)
+        .bind(guest_id)
+        .fetch_all(pool)
+        .await
+        .with_context(|| format!("Failed to fetch bookings of guest {}", guest_id))?;
+
+    rows.into_iter().map(|row| row_to_booking(&row)).collect()
+}
+
+/// Gets the loyalty points balance of a guest, if the guest exists.
+pub async fn get_loyalty_balance(pool: &DbPool, guest_id: i64) -> Result<Option<i64>> {
+    let row = sqlx::query(SELECT_LOYALTY_BALANCE_QUERY)
+        .bind(guest_id)
+        .fetch_optional(pool)
+        .await
+        .with_context(|| format!("Failed to fetch loyalty points of guest {}", guest_id))?;
+
+    Ok(row.map(|row| row.get("loyalty_points")))
+}
+
+/// Gets and locks the loyalty points balance of a guest, so that the same points can't be
+/// redeemed twice.
+pub async fn get_and_lock_loyalty_balance(
+    tx: &mut Transaction<'_, Postgres>,
+    guest_id: i64,
+) -> Result<Option<i64>> {
+    let row = sqlx::query(SELECT_LOYALTY_BALANCE_FOR_UPDATE_QUERY)
+        .bind(guest_id)
+        .fetch_optional(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to fetch loyalty points of guest {}", guest_id))?;
+
+    Ok(row.map(|row| row.get("loyalty_points")))
+}
+
+/// Gets the loyalty transactions of a guest, latest first.
+pub async fn get_loyalty_transactions_by_guest_id(
+    pool: &DbPool,
+    guest_id: i64,
+) -> Result<Vec<LoyaltyTransaction>> {
+    let rows = sqlx::query(SELECT_LOYALTY_TRANSACTIONS_BY_GUEST_QUERY)
+        .bind(guest_id)
+        .fetch_all(pool)
+        .await
+        .with_context(|| format!("Failed to fetch loyalty transactions of guest {}", guest_id))?;
+
+    rows.into_iter()
+        .map(|row| row_to_loyalty_transaction(&row))
+        .collect()
+}
+
+/// Gets the loyalty transactions for a booking, in the order they happened.
+pub async fn get_loyalty_transactions_by_booking_id(
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+) -> Result<Vec<LoyaltyTransaction>> {
+    let rows = sqlx::query(SELECT_LOYALTY_TRANSACTIONS_BY_BOOKING_QUERY)
+        .bind(booking_id)
+        .fetch_all(&mut **tx)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to fetch loyalty transactions of booking {}",
+                booking_id
+            )
+        })?;
+
+    rows.into_iter()
+        .map(|row| row_to_loyalty_transaction(&row))
+        .collect()
+}
+
+/// Generates the ID of a new waitlist entry.
+pub async fn get_next_waitlist_entry_id(tx: &mut Transaction<'_, Postgres>) -> Result<i64> {
+    let row = sqlx::query(SELECT_NEXT_WAITLIST_ENTRY_ID_QUERY)
+        .fetch_one(&mut **tx)
+        .await
+        .context("Failed to generate next waitlist entry ID")?;
+
+    Ok(row.get("next_id"))
+}
+
+/// Gets a waitlist entry by ID.
+pub async fn get_waitlist_entry_by_id<'a, E>(
+    executor: E,
+    entry_id: i64,
+) -> Result<Option<WaitlistEntry>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let row = sqlx::query(SELECT_WAITLIST_ENTRY_QUERY)
+        .bind(entry_id)
+        .fetch_optional(executor)
+        .await
+        .with_context(|| format!("Failed to fetch waitlist entry with ID {}", entry_id))?;
+
+    row.map(|row| row_to_waitlist_entry(&row)).transpose()
+}
+
+/// Gets the open (waiting or offered) waitlist entries of a hotel, in the order they were added.
+pub async fn get_waitlist_by_hotel_id(pool: &DbPool, hotel_id: i64) -> Result<Vec<WaitlistEntry>> {
+    let rows = sqlx::query(SELECT_WAITLIST_BY_HOTEL_QUERY)
+        .bind(hotel_id)
+        .fetch_all(pool)
+        .await
+        .with_context(|| format!("Failed to fetch waitlist of hotel {}", hotel_id))?;
+
+    rows.into_iter()
+        .map(|row| row_to_waitlist_entry(&row))
+        .collect()
+}
+
+/// Gets and locks the waitlist entries of a hotel which are still waiting for a room, in the order
+/// they were added. Entries for stays which should have started before `today` (at the hotel) are
+/// skipped.
+pub async fn get_and_lock_waiting_entries(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+    today: NaiveDate,
+) -> Result<Vec<WaitlistEntry>> {
+    let rows = sqlx::query(SELECT_WAITING_ENTRIES_QUERY)
+        .bind(hotel_id)
+        .bind(today)
+        .fetch_all(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to fetch waiting entries of hotel {}", hotel_id))?;
+
+    rows.into_iter()
+        .map(|row| row_to_waitlist_entry(&row))
+        .collect()
+}
+
+/// Gets the stay restrictions of a hotel which are in effect on any date from `from` to `to`
+/// (both inclusive, as departure dates are restricted too).
+pub async fn get_stay_restrictions<'a, E>(
+    executor: E,
+    hotel_id: i64,
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Result<Vec<StayRestriction>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let rows = sqlx::query(SELECT_STAY_RESTRICTIONS_QUERY)
+        .bind(hotel_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(executor)
+        .await
+        .with_context(|| format!("Failed to fetch stay restrictions of hotel {}", hotel_id))?;
+
+    rows.into_iter()
+        .map(|row| row_to_stay_restriction(&row))
+        .collect()
+}
+
+/// Adds a stay restriction to a hotel, returning its ID.
+pub async fn insert_stay_restriction(
+    pool: &DbPool,
+    hotel_id: i64,
+    restriction: &StayRestriction,
+) -> Result<i64> {
+    let days_of_week: Vec<i32> = restriction
+        .days_of_week
+        .iter()
+        .map(|day| day.number_from_monday() as i32)
+        .collect();
+
+    let row = sqlx::query(INSERT_STAY_RESTRICTION_QUERY)
+        .bind(hotel_id)
+        .bind(restriction.start_date)
+        .bind(restriction.end_date)
+        .bind(days_of_week)
+        .bind(restriction.min_stay)
+        .bind(restriction.closed_to_arrival)
+        .bind(restriction.closed_to_departure)
+        .fetch_one(pool)
+        .await
+        .with_context(|| format!("Failed to add stay restriction to hotel {}", hotel_id))?;
+
+    Ok(row.get("id"))
+}
+
+/// Removes a stay restriction from a hotel. Returns whether it existed.
+pub async fn delete_stay_restriction(
+    pool: &DbPool,
+    hotel_id: i64,
+    restriction_id: i64,
+) -> Result<bool> {
+    let result = sqlx::query(DELETE_STAY_RESTRICTION_QUERY)
+        .bind(hotel_id)
+        .bind(restriction_id)
+        .execute(pool)
+        .await
+        .with_context(|| format!("Failed to delete stay restriction {}", restriction_id))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Gets the rate plan of a hotel for a room type.
+pub async fn get_rate_plan<'a, E>(
+    executor: E,
+    hotel_id: i64,
+    room_type: &str,
+) -> Result<Option<RatePlan>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let row = sqlx::query(SELECT_RATE_PLAN_QUERY)
+        .bind(hotel_id)
+        .bind(room_type)
+        .fetch_optional(executor)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to fetch rate plan of hotel {} for room type {}",
+                hotel_id, room_type
+            )
+        })?;
+
+    Ok(row.map(|row| row_to_rate_plan(&row)))
+}
+
+/// Gets the rate plans of a hotel, for all of its room types.
+pub async fn get_rate_plans_by_hotel_id(pool: &DbPool, hotel_id: i64) -> Result<Vec<RatePlan>> {
+    let rows = sqlx::query(SELECT_RATE_PLANS_BY_HOTEL_QUERY)
+        .bind(hotel_id)
+        .fetch_all(pool)
+        .await
+        .with_context(|| format!("Failed to fetch rate plans of hotel {}", hotel_id))?;
+
+    Ok(rows.iter().map(row_to_rate_plan).collect())
+}
+
+pub async fn get_promo_code<'a, E>(executor: E, code: &str) -> Result<Option<PromoCode>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let row = sqlx::query(SELECT_PROMO_CODE_QUERY)
+        .bind(code)
+        .fetch_optional(executor)
+        .await
+        .with_context(|| format!("Failed to fetch promo code {}", code))?;
+
+    row.map(|row| row_to_promo_code(&row)).transpose()
+}
+
+pub async fn get_all_promo_codes(pool: &DbPool) -> Result<Vec<PromoCode>> {
+    let rows = sqlx::query(SELECT_ALL_PROMO_CODES_QUERY)
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch promo codes")?;
+
+    rows.iter().map(row_to_promo_code).collect()
+}
+
+/// Adds a promo code. Returns false if the code already exists.
+pub async fn insert_promo_code(pool: &DbPool, promo: &PromoCode) -> Result<bool> {
+    let (discount_type, value, currency) = match &promo.discount {
+        PromoDiscount::Percentage(percent) => ("percentage", i64::from(*percent), None),
+        PromoDiscount::Fixed { amount, currency } => ("fixed", *amount, Some(currency)),
+    };
+
+    let result = sqlx::query(INSERT_PROMO_CODE_QUERY)
+        .bind(&promo.code)
+        .bind(promo.hotel_id)
+        .bind(discount_type)
+        .bind(value)
+        .bind(currency)
+        .bind(promo.valid_from)
+        .bind(promo.valid_until)
+        .bind(promo.max_uses)
+        .execute(pool)
+        .await
+        .with_context(|| format!("Failed to add promo code {}", promo.code))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Counts one more use of a promo code, if it is still valid for a stay at the hotel arriving on
+/// the given date. The conditions are checked by the update itself, so that concurrent
+/// redemptions can't exceed the code's limit. Returns the code as updated, or `None` if it can't
+/// be used.
+pub async fn increment_promo_code_usage(
+    conn: &mut PgConnection,
+    code: &str,
+    hotel_id: i64,
+    arrival: NaiveDate,
+) -> Result<Option<PromoCode>> {
+    let row = sqlx::query(REDEEM_PROMO_CODE_QUERY)
+        .bind(code)
+        .bind(hotel_id)
+        .bind(arrival)
+        .fetch_optional(conn)
+        .await
+        .with_context(|| format!("Failed to redeem promo code {}", code))?;
+
+    row.map(|row| row_to_promo_code(&row)).transpose()
+}
+
+pub async fn get_exchange_rate<'a, E>(
+    executor: E,
+    from_currency: &str,
+    to_currency: &str,
+) -> Result<Option<ExchangeRate>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let row = sqlx::query(SELECT_EXCHANGE_RATE_QUERY)
+        .bind(from_currency)
+        .bind(to_currency)
+        .fetch_optional(executor)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to fetch exchange rate from {} to {}",
+                from_currency, to_currency
+            )
+        })?;
+
+    Ok(row.map(|row| row_to_exchange_rate(&row)))
+}
+
+pub async fn get_all_exchange_rates(pool: &DbPool) -> Result<Vec<ExchangeRate>> {
+    let rows = sqlx::query(SELECT_ALL_EXCHANGE_RATES_QUERY)
+        .fetch_all(pool)
+        .await
+        .context("Failed to fetch exchange rates")?;
+
+    Ok(rows.iter().map(row_to_exchange_rate).collect())
+}
+
+/// Sets the rate between two currencies, replacing the previous one. Bookings keep the rate they
+/// were quoted at.
+pub async fn upsert_exchange_rate(pool: &DbPool, rate: &ExchangeRate) -> Result<()> {
+    sqlx::query(UPSERT_EXCHANGE_RATE_QUERY)
+        .bind(&rate.from_currency)
+        .bind(&rate.to_currency)
+        .bind(rate.rate)
+        .bind(rate.updated_at)
+        .execute(pool)
+        .await
+        .with_context(|| {
+            format!(
+                "Failed to save exchange rate from {} to {}",
+                rate.from_currency, rate.to_currency
+            )
+        })?;
+
+    Ok(())
+}
+
+/// Gets the tax rules of a hotel, in the order they were added.
+pub async fn get_tax_rules<'a, E>(executor: E, hotel_id: i64) -> Result<Vec<TaxRule>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let rows = sqlx::query(SELECT_TAX_RULES_QUERY)
+        .bind(hotel_id)
+        .fetch_all(executor)
+        .await
+        .with_context(|| format!("Failed to fetch tax rules of hotel {}", hotel_id))?;
+
+    rows.iter().map(row_to_tax_rule).collect()
+}
+
+/// Adds a tax rule to the rule's hotel, returning its ID.
+pub async fn insert_tax_rule(pool: &DbPool, rule: &TaxRule) -> Result<i64> {
+    let (kind, amount) = match rule.charge {
+        TaxCharge::Rate(basis_points) => ("rate", basis_points),
+        TaxCharge::PerPersonPerNight(amount) => ("per_person_per_night", amount),
+    };
+
+    let row = sqlx::query(INSERT_TAX_RULE_QUERY)
+        .bind(rule.hotel_id)
+        .bind(&rule.name)
+        .bind(kind)
+        .bind(amount)
+        .fetch_one(pool)
+        .await
+        .with_context(|| format!("Failed to add tax rule to hotel {}", rule.hotel_id))?;
+
+    Ok(row.get("id"))
+}
+
+/// Deletes a tax rule of a hotel. Returns false if the hotel has no such rule.
+pub async fn delete_tax_rule(pool: &DbPool, hotel_id: i64, tax_rule_id: i64) -> Result<bool> {
+    let result = sqlx::query(DELETE_TAX_RULE_QUERY)
+        .bind(hotel_id)
+        .bind(tax_rule_id)
+        .execute(pool)
+        .await
+        .with_context(|| format!("Failed to delete tax rule {}", tax_rule_id))?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+/// Creates the rate plan for the plan's room type, or replaces the existing one. Returns the ID of
+/// the plan. Bookings keep the prices they were quoted.
+pub async fn upsert_rate_plan(pool: &DbPool, plan: &RatePlan) -> Result<i64> {
+    let row = sqlx::query(UPSERT_RATE_PLAN_QUERY)
+        .bind(plan.hotel_id)
+        .bind(&plan.room_type)
+        .bind(&plan.name)
+        .bind(&plan.currency)
+        .bind(plan.base_rate)
+        .bind(plan.weekend_surcharge_percent)
+        .bind(Json(&plan.seasons))
+        .bind(Json(&plan.length_of_stay_discounts))
+        .bind(plan.cancellation_policy.as_ref().map(Json))
+        .fetch_one(pool)
+        .await
+        .with_context(|| format!("Failed to save rate plan of hotel {}", plan.hotel_id))?;
+
+    Ok(row.get("id"))
+}
+
+/// Gets and locks the checked-in bookings which have a price, and so get room charges posted.
+pub async fn get_and_lock_checked_in_priced_bookings(
+    tx: &mut Transaction<'_, Postgres>,
+) -> Result<Vec<Booking>> {
+    let rows = sqlx::query(SELECT_CHECKED_IN_PRICED_BOOKINGS_QUERY)
+        .fetch_all(&mut **tx)
+        .await
+        .context("Failed to fetch checked-in bookings")?;
+
+    rows.into_iter().map(|row| row_to_booking(&row)).collect()
+}
+
+/// Gets the entries of a booking's folio, in date order.
+pub async fn get_folio_entries<'a, E>(executor: E, booking_id: i64) -> Result<Vec<FolioEntry>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let rows = sqlx::query(SELECT_FOLIO_ENTRIES_QUERY)
+        .bind(booking_id)
+        .fetch_all(executor)
+        .await
+        .with_context(|| format!("Failed to fetch folio of booking {}", booking_id))?;
+
+    rows.into_iter()
+        .map(|row| row_to_folio_entry(&row))
+        .collect()
+}
+
+pub async fn get_next_folio_entry_id(tx: &mut Transaction<'_, Postgres>) -> Result<i64> {
+    let row = sqlx::query(SELECT_NEXT_FOLIO_ENTRY_ID_QUERY)
+        .fetch_one(&mut **tx)
+        .await
+        .context("Failed to generate next folio entry ID")?;
+
+    Ok(row.get("next_id"))
+}
+
+/// Allocates the next invoice number of a hotel. The hotel row stays locked until the transaction
+/// ends, so that invoice numbers have no gaps.
+pub async fn get_next_invoice_number(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+) -> Result<i64> {
+    let row = sqlx::query(UPDATE_NEXT_INVOICE_NUMBER_QUERY)
+        .bind(hotel_id)
+        .fetch_one(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to get next invoice number of hotel {}", hotel_id))?;
+
+    Ok(row.get("last_invoice_number"))
+}
+
+/// Gets the invoice issued for a booking, if any.
+pub async fn get_invoice_by_booking_id<'a, E>(
+    executor: E,
+    booking_id: i64,
+) -> Result<Option<Invoice>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let row = sqlx::query(SELECT_INVOICE_BY_BOOKING_QUERY)
+        .bind(booking_id)
+        .fetch_optional(executor)
+        .await
+        .with_context(|| format!("Failed to fetch invoice of booking {}", booking_id))?;
+
+    Ok(row.map(|row| row.get::<Json<Invoice>, _>("document").0))
+}
+
+/// Gets the deposits authorized for a booking, whether they're still open or not.
+pub async fn get_payment_authorizations(
+    pool: &DbPool,
+    booking_id: i64,
+) -> Result<Vec<PaymentAuthorization>> {
+    let rows = sqlx::query(SELECT_PAYMENT_AUTHORIZATIONS_QUERY)
+        .bind(booking_id)
+        .fetch_all(pool)
+        .await
+        .with_context(|| format!("Failed to fetch payments of booking {}", booking_id))?;
+
+    rows.into_iter()
+        .map(|row| row_to_payment_authorization(&row))
+        .collect()
+}
+
+/// Gets and locks the deposits of a booking which haven't been captured or voided yet, so that
+/// they can't be captured twice.
+pub async fn get_and_lock_open_authorizations(
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+) -> Result<Vec<PaymentAuthorization>> {
+    let rows = sqlx::query(SELECT_OPEN_AUTHORIZATIONS_QUERY)
+        .bind(booking_id)
+        .fetch_all(&mut **tx)
+        .await
+        .with_context(|| format!("Failed to fetch open payments of booking {}", booking_id))?;
+
+    rows.into_iter()
+        .map(|row| row_to_payment_authorization(&row))
+        .collect()
+}
@@ -1,4 +1,6 @@
 use crate::app_state::AppState;
+use crate::db::get_hotel_by_id;
+use crate::hotel_time::hotel_today;
 use axum::{
     body::Body,
     extract::{Path, Query, State},
@@ -11,7 +13,6 @@ use std::env;
 
 #[derive(Deserialize)]
 pub struct BookingShapeParams {
-    date: String,
     offset: Option<String>,
     handle: Option<String>,
     live: Option<bool>,
@@ -29,10 +30,20 @@ pub async fn get_hotel_bookings_shape(
     // Build Electric API URL for bookings table
     let url = format!("{}/v1/shape", electric_url);
 
+    // Only today's bookings are synced, as of the hotel's local date
+    let hotel = get_hotel_by_id(&app_state.db_pool, hotel_id)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to fetch hotel {}: {:?}", hotel_id, e);
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let today = hotel_today(&hotel);
+
     // Build query parameters - fix table to bookings and hotel_id with mandatory date filtering
     let where_clause = format!(
         "hotel_id = {} AND start_time <= '{}' AND end_time >= '{}'",
-        hotel_id, params.date, params.date
+        hotel_id, today, today
     );
 
     let mut query_params = vec![("table", "bookings".to_string()), ("where", where_clause)];
@@ -91,7 +102,7 @@ pub async fn get_hotel_bookings_shape(
     let stream = response.bytes_stream().map(|result| {
         result.map_err(|e| {
             tracing::error!("Error streaming from Electric: {}", e);
-            std::io::Error::new(std::io::ErrorKind::Other, e)
+            std::io::Error::other(e)
         })
     });
 
@@ -114,11 +125,9 @@ pub async fn get_hotel_bookings_shape(
             {
                 // Convert reqwest headers to axum headers by creating new ones
                 if let Ok(header_name) = axum::http::HeaderName::from_bytes(key.as_str().as_bytes())
+                    && let Ok(header_value) = axum::http::HeaderValue::from_bytes(value.as_bytes())
                 {
-                    if let Ok(header_value) = axum::http::HeaderValue::from_bytes(value.as_bytes())
-                    {
-                        response_headers.insert(header_name, header_value);
-                    }
+                    response_headers.insert(header_name, header_value);
                 }
             }
         }
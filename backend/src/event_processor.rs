@@ -15,21 +15,50 @@ impl EventProcessor {
         tx: &mut Transaction<'_, Postgres>,
         stream_id: i64,
         event: Event,
+    ) -> Result<()> {
+        self.append_event(tx, stream_id, &event).await?;
+
+        // Offer rooms that were released to guests on the waitlist, within the same transaction
+        if let Some(booking_id) = event.released_booking_id() {
+            crate::waitlist::offer_released_rooms(self, tx, booking_id).await?;
+        }
+
+        // Keep the guest's loyalty points in step with their stays
+        if let Event::BookingCheckedOut(e) = &event {
+            crate::loyalty::accrue_points(self, tx, e.booking_id).await?;
+        }
+        if let Some(booking_id) = crate::loyalty::forfeited_booking_id(&event) {
+            crate::loyalty::reverse_points(self, tx, booking_id).await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stores the event and applies it to the projections, without triggering any reactions.
+    pub async fn append_event(
+        &self,
+        tx: &mut Transaction<'_, Postgres>,
+        stream_id: i64,
+        event: &Event,
     ) -> Result<()> {
         // Get next version for this stream
-        let version = self.get_next_version(tx, stream_id).await?;
+        let stream_type = event.stream_type();
+        let version = self.get_next_version(tx, stream_type, stream_id).await?;
 
         // Insert event into events table
-        let event_data = serde_json::to_value(&event)?;
-        sqlx::query("INSERT INTO events (stream_id, version, data) VALUES ($1, $2, $3)")
-            .bind(stream_id)
-            .bind(version)
-            .bind(event_data)
-            .execute(&mut **tx)
-            .await?;
+        let event_data = serde_json::to_value(event)?;
+        sqlx::query(
+            "INSERT INTO events (stream_type, stream_id, version, data) VALUES ($1, $2, $3, $4)",
+        )
+        .bind(stream_type)
+        .bind(stream_id)
+        .bind(version)
+        .bind(event_data)
+        .execute(&mut **tx)
+        .await?;
 
         // Apply projection updates for all events
-        crate::projections::handle_booking_event(tx, &event).await?;
+        crate::projections::handle_event(tx, event).await?;
 
         Ok(())
     }
@@ -37,11 +66,13 @@ impl EventProcessor {
     async fn get_next_version(
         &self,
         tx: &mut Transaction<'_, Postgres>,
+        stream_type: &str,
         stream_id: i64,
     ) -> Result<i32> {
         let row = sqlx::query(
-            "SELECT COALESCE(MAX(version), 0) + 1 as next_version FROM events WHERE stream_id = $1",
+            "SELECT COALESCE(MAX(version), 0) + 1 as next_version FROM events WHERE stream_type = $1 AND stream_id = $2",
         )
+        .bind(stream_type)
         .bind(stream_id)
         .fetch_one(&mut **tx)
         .await?;
@@ -0,0 +1,512 @@
+use crate::db::{get_folio_entries, get_next_folio_entry_id};
+use crate::event_processor::EventProcessor;
+use crate::models::{
+    Booking, FolioEntry, FolioEntryKind, Hotel, Invoice, InvoiceLine, NightlyPrice,
+};
+use crate::models_events::{Event, FolioEntryPostedEvent};
+use crate::pricing::{cancellation_fee, discounted_nights, no_show_fee};
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{Postgres, Transaction};
+use std::fmt::Write;
+
+/// What the guest still owes, given the entries of their folio
+pub fn folio_balance(entries: &[FolioEntry]) -> i64 {
+    entries.iter().map(|entry| entry.amount).sum()
+}
+
+/// The nights of a booking's quote before `until` which haven't been charged yet. Unpriced
+/// bookings have no room charges.
+pub fn unposted_room_charges(
+    booking: &Booking,
+    entries: &[FolioEntry],
+    until: NaiveDate,
+) -> Vec<NightlyPrice> {
+    let Some(quote) = &booking.quote else {
+        return vec![];
+    };
+
+    discounted_nights(quote)
+        .into_iter()
+        .filter(|night| night.date < until && night.date < booking.end_time)
+        .filter(|night| {
+            !entries
+                .iter()
+                .any(|e| e.kind == FolioEntryKind::RoomCharge && e.date == night.date)
+        })
+        .collect()
+}
+
+async fn post_entry(
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+    kind: FolioEntryKind,
+    description: &str,
+    amount: i64,
+    date: NaiveDate,
+) -> Result<()> {
+    let entry_id = get_next_folio_entry_id(tx).await?;
+    let event = Event::FolioEntryPosted(FolioEntryPostedEvent {
+        entry_id,
+        booking_id,
+        kind,
+        description: description.to_string(),
+        amount,
+        date,
+        posted_at: Utc::now(),
+    });
+    event_processor
+        .process_event_with_tx(tx, booking_id, event)
+        .await
+}
+
+/// Posts the room charges of a booking for the nights before `until` which haven't been charged
+/// yet, each with the taxes and fees on the night. Returns the number of nights charged.
+pub async fn post_room_charges(
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    booking: &Booking,
+    until: NaiveDate,
+) -> Result<usize> {
+    let entries = get_folio_entries(&mut **tx, booking.id).await?;
+    let nights = unposted_room_charges(booking, &entries, until);
+    let taxes = booking.quote.as_ref().map_or(&[][..], |quote| &quote.taxes);
+
+    for night in &nights {
+        post_entry(
+            event_processor,
+            tx,
+            booking.id,
+            FolioEntryKind::RoomCharge,
+            "Room charge",
+            night.amount,
+            night.date,
+        )
+        .await?;
+
+        for tax in taxes.iter().filter(|tax| tax.date == night.date) {
+            post_entry(
+                event_processor,
+                tx,
+                booking.id,
+                FolioEntryKind::Tax,
+                &tax.name,
+                tax.amount,
+                tax.date,
+            )
+            .await?;
+        }
+    }
+
+    Ok(nights.len())
+}
+
+/// Posts the penalty for cancelling a booking on the given day, if there is one. Returns the fee.
+pub async fn post_cancellation_fee(
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    booking: &Booking,
+    today: NaiveDate,
+) -> Result<i64> {
+    let fee = cancellation_fee(booking, today);
+    if fee == 0 {
+        return Ok(0);
+    }
+
+    post_entry(
+        event_processor,
+        tx,
+        booking.id,
+        FolioEntryKind::CancellationFee,
+        "Cancellation fee",
+        fee,
+        today,
+    )
+    .await?;
+
+    Ok(fee)
+}
+
+/// Posts the penalty for not turning up, if there is one. Returns the fee.
+pub async fn post_no_show_fee(
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    booking: &Booking,
+    today: NaiveDate,
+) -> Result<i64> {
+    let fee = no_show_fee(booking);
+    if fee == 0 {
+        return Ok(0);
+    }
+
+    post_entry(
+        event_processor,
+        tx,
+        booking.id,
+        FolioEntryKind::CancellationFee,
+        "No-show fee",
+        fee,
+        today,
+    )
+    .await?;
+
+    Ok(fee)
+}
+
+/// Charges the guest for an extra, such as an early check-in or a late checkout.
+pub async fn post_extra(
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+    description: &str,
+    amount: i64,
+    date: NaiveDate,
+) -> Result<()> {
+    post_entry(
+        event_processor,
+        tx,
+        booking_id,
+        FolioEntryKind::Extra,
+        description,
+        amount,
+        date,
+    )
+    .await
+}
+
+/// Builds the invoice for a stay from the booking's folio. Taxes and payments are listed separately
+/// from the charges, payments with positive amounts.
+pub fn build_invoice(
+    invoice_number: i64,
+    hotel: &Hotel,
+    booking: &Booking,
+    entries: &[FolioEntry],
+    issued_at: DateTime<Utc>,
+) -> Invoice {
+    let line = |entry: &FolioEntry, amount: i64| InvoiceLine {
+        date: entry.date,
+        description: entry.description.clone(),
+        amount,
+    };
+
+    let lines: Vec<InvoiceLine> = entries
+        .iter()
+        .filter(|e| !matches!(e.kind, FolioEntryKind::Payment | FolioEntryKind::Tax))
+        .map(|e| line(e, e.amount))
+        .collect();
+    let taxes: Vec<InvoiceLine> = entries
+        .iter()
+        .filter(|e| e.kind == FolioEntryKind::Tax)
+        .map(|e| line(e, e.amount))
+        .collect();
+    let payments: Vec<InvoiceLine> = entries
+        .iter()
+        .filter(|e| e.kind == FolioEntryKind::Payment)
+        .map(|e| line(e, -e.amount))
+        .collect();
+
+    let tax_total = taxes.iter().map(|l| l.amount).sum::<i64>();
+    let total = lines.iter().map(|l| l.amount).sum::<i64>() + tax_total;
+    let paid = payments.iter().map(|l| l.amount).sum();
+
+    Invoice {
+        invoice_number,
+        hotel_id: hotel.id,
+        hotel_name: hotel.name.clone(),
+        booking_id: booking.id,
+        guest_name: booking.guest_name.clone(),
+        start_time: booking.start_time,
+        end_time: booking.end_time,
+        issued_at,
+        currency: Some(hotel.base_currency.clone()),
+        lines,
+        taxes,
+        payments,
+        total,
+        tax_total,
+        paid,
+        balance: total - paid,
+    }
+}
+
+/// Formats an amount in minor units, assuming the currency has two decimal places.
+pub fn format_amount(amount: i64) -> String {
+    let sign = if amount < 0 { "-" } else { "" };
+    let amount = amount.unsigned_abs();
+    format!("{}{}.{:02}", sign, amount / 100, amount % 100)
+}
+
+const INVOICE_WIDTH: usize = 64;
+
+/// Renders an invoice as plain text, for printing.
+pub fn render_invoice_text(invoice: &Invoice) -> String {
+    let currency = invoice.currency.as_deref().unwrap_or("");
+    let mut text = String::new();
+
+    // Writing to a String can't fail
+    let _ = writeln!(text, "{}", invoice.hotel_name);
+    let _ = writeln!(text, "INVOICE No. {}", invoice.invoice_number);
+    let _ = writeln!(text, "Issued: {}", invoice.issued_at.format("%Y-%m-%d"));
+    let _ = writeln!(text, "Guest: {}", invoice.guest_name);
+    let _ = writeln!(
+        text,
+        "Booking {}: {} to {}",
+        invoice.booking_id, invoice.start_time, invoice.end_time
+    );
+
+    let mut section = |title: &str, lines: &[InvoiceLine]| {
+        let _ = writeln!(text, "\n{}", title);
+        let _ = writeln!(text, "{}", "-".repeat(INVOICE_WIDTH));
+        for line in lines {
+            let _ = writeln!(
+                text,
+                "{}  {:<38}{:>12}",
+                line.date,
+                line.description,
+                format_amount(line.amount)
+            );
+        }
+    };
+    section("Charges", &invoice.lines);
+    if !invoice.taxes.is_empty() {
+        section("Taxes and fees", &invoice.taxes);
+    }
+    if !invoice.payments.is_empty() {
+        section("Payments", &invoice.payments);
+    }
+
+    let _ = writeln!(text, "{}", "=".repeat(INVOICE_WIDTH));
+    let mut totals = vec![("Total", invoice.total)];
+    if !invoice.taxes.is_empty() {
+        totals.push(("Of which taxes", invoice.tax_total));
+    }
+    totals.extend([("Paid", invoice.paid), ("Balance", invoice.balance)]);
+    for (label, amount) in totals {
+        let _ = writeln!(
+            text,
+            "{:>48}{:>4}{:>12}",
+            label,
+            currency,
+            format_amount(amount)
+        );
+    }
+
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BookingStatus, OverbookingLimit, PriceQuote, RoomPreferences};
+    use chrono::{NaiveTime, TimeZone};
+    use chrono_tz::Tz;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    fn priced_booking(start_day: u32, end_day: u32, nightly_rate: i64) -> Booking {
+        let nights: Vec<NightlyPrice> = (start_day..end_day)
+            .map(|day| NightlyPrice {
+                date: date(day),
+                amount: nightly_rate,
+            })
+            .collect();
+        let subtotal = nights.iter().map(|n| n.amount).sum();
+
+        Booking {
+            id: 42,
+            hotel_id: 1,
+            room_number: Some(101),
+            room_pinned: false,
+            guest_name: "Jane Doe".to_string(),
+            start_time: date(start_day),
+            end_time: date(end_day),
+            status: BookingStatus::CheckedIn,
+            preferences: RoomPreferences::default(),
+            hold_expires_at: None,
+            group_id: None,
+            adults: 1,
+            children: 0,
+            guest_id: None,
+            quote: Some(PriceQuote {
+                rate_plan_id: 1,
+                room_type: "standard".to_string(),
+                currency: "EUR".to_string(),
+                nights,
+                subtotal,
+                discount: 0,
+                total: subtotal,
+                cancellation_policy: None,
+                taxes: vec![],
+                promo: None,
+                converted: None,
+            }),
+            room_type: None,
+        }
+    }
+
+    fn entry(
+        id: i64,
+        kind: FolioEntryKind,
+        description: &str,
+        amount: i64,
+        day: u32,
+    ) -> FolioEntry {
+        FolioEntry {
+            id,
+            booking_id: 42,
+            kind,
+            description: description.to_string(),
+            amount,
+            date: date(day),
+            posted_at: Utc.with_ymd_and_hms(2024, 1, day, 12, 0, 0).unwrap(),
+        }
+    }
+
+    fn hotel() -> Hotel {
+        Hotel {
+            id: 1,
+            name: "Grand Hotel".to_string(),
+            room_count: 10,
+            no_show_cutoff: NaiveTime::from_hms_opt(18, 0, 0).unwrap(),
+            overbooking_limit: OverbookingLimit::None,
+            base_currency: "EUR".to_string(),
+            time_zone: Tz::UTC,
+            check_in_time: NaiveTime::from_hms_opt(15, 0, 0).unwrap(),
+            check_out_time: NaiveTime::from_hms_opt(11, 0, 0).unwrap(),
+            archived_at: None,
+        }
+    }
+
+    #[test]
+    fn test_unposted_room_charges_skip_charged_nights() {
+        let booking = priced_booking(1, 5, 10000);
+        let entries = vec![entry(
+            1,
+            FolioEntryKind::RoomCharge,
+            "Room charge",
+            10000,
+            1,
+        )];
+
+        let nights = unposted_room_charges(&booking, &entries, date(4));
+
+        let dates: Vec<NaiveDate> = nights.iter().map(|n| n.date).collect();
+        assert_eq!(dates, vec![date(2), date(3)]);
+    }
+
+    #[test]
+    fn test_unposted_room_charges_stop_at_shortened_stay() {
+        // After an early checkout, the stay ends on the departure date
+        let booking = Booking {
+            end_time: date(3),
+            ..priced_booking(1, 5, 10000)
+        };
+
+        let nights = unposted_room_charges(&booking, &[], date(10));
+
+        assert_eq!(nights.len(), 2);
+    }
+
+    #[test]
+    fn test_unpriced_bookings_have_no_room_charges() {
+        let booking = Booking {
+            quote: None,
+            ..priced_booking(1, 5, 10000)
+        };
+
+        assert!(unposted_room_charges(&booking, &[], date(10)).is_empty());
+    }
+
+    #[test]
+    fn test_invoice_totals() {
+        let booking = priced_booking(1, 3, 10000);
+        let entries = vec![
+            entry(1, FolioEntryKind::RoomCharge, "Room charge", 10000, 1),
+            entry(2, FolioEntryKind::Extra, "Minibar", 850, 1),
+            entry(3, FolioEntryKind::RoomCharge, "Room charge", 10000, 2),
+            entry(4, FolioEntryKind::Adjustment, "Late breakfast", -350, 2),
+            entry(5, FolioEntryKind::Payment, "Card payment", -20000, 3),
+        ];
+        let issued_at = Utc.with_ymd_and_hms(2024, 1, 3, 10, 0, 0).unwrap();
+
+        let invoice = build_invoice(7, &hotel(), &booking, &entries, issued_at);
+
+        assert_eq!(invoice.lines.len(), 4);
+        assert_eq!(invoice.total, 20500);
+        assert_eq!(invoice.paid, 20000);
+        assert_eq!(invoice.balance, 500);
+        assert_eq!(invoice.balance, folio_balance(&entries));
+        assert_eq!(invoice.currency.as_deref(), Some("EUR"));
+    }
+
+    #[test]
+    fn test_invoice_itemizes_taxes() {
+        let booking = priced_booking(1, 2, 10000);
+        let entries = vec![
+            entry(1, FolioEntryKind::RoomCharge, "Room charge", 10000, 1),
+            entry(2, FolioEntryKind::Tax, "VAT 8.1%", 810, 1),
+            entry(3, FolioEntryKind::Tax, "City tax", 500, 1),
+        ];
+        let issued_at = Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+
+        let invoice = build_invoice(1, &hotel(), &booking, &entries, issued_at);
+
+        assert_eq!(invoice.lines.len(), 1);
+        assert_eq!(invoice.taxes.len(), 2);
+        assert_eq!(invoice.tax_total, 1310);
+        assert_eq!(invoice.total, 11310);
+        assert_eq!(invoice.balance, folio_balance(&entries));
+
+        let text = render_invoice_text(&invoice);
+        assert!(text.contains("Taxes and fees\n"));
+        assert!(text.contains("2024-01-01  City tax                                      5.00\n"));
+        assert!(text.contains("Of which taxes EUR       13.10\n"));
+    }
+
+    #[test]
+    fn test_render_invoice_text() {
+        let booking = priced_booking(1, 2, 12500);
+        let entries = vec![
+            entry(1, FolioEntryKind::RoomCharge, "Room charge", 12500, 1),
+            entry(2, FolioEntryKind::Payment, "Cash", -12500, 2),
+        ];
+        let issued_at = Utc.with_ymd_and_hms(2024, 1, 2, 10, 0, 0).unwrap();
+        let invoice = build_invoice(3, &hotel(), &booking, &entries, issued_at);
+
+        let text = render_invoice_text(&invoice);
+
+        let expected = [
+            "Grand Hotel",
+            "INVOICE No. 3",
+            "Issued: 2024-01-02",
+            "Guest: Jane Doe",
+            "Booking 42: 2024-01-01 to 2024-01-02",
+            "",
+            "Charges",
+            &"-".repeat(64),
+            "2024-01-01  Room charge                                 125.00",
+            "",
+            "Payments",
+            &"-".repeat(64),
+            "2024-01-02  Cash                                        125.00",
+            &"=".repeat(64),
+            "                                           Total EUR      125.00",
+            "                                            Paid EUR      125.00",
+            "                                         Balance EUR        0.00",
+        ]
+        .join("\n")
+            + "\n";
+        assert_eq!(text, expected);
+    }
+
+    #[test]
+    fn test_format_amount() {
+        assert_eq!(format_amount(0), "0.00");
+        assert_eq!(format_amount(5), "0.05");
+        assert_eq!(format_amount(123456), "1234.56");
+        assert_eq!(format_amount(-250), "-2.50");
+    }
+}
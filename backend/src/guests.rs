@@ -0,0 +1,196 @@
+use crate::models::Guest;
+
+/// The most typos tolerated when matching a word of the query against a word of the guest's name
+const MAX_NAME_TYPOS: usize = 1;
+/// Shorter words (and phone number fragments) have to match exactly, or as a prefix
+const MIN_FUZZY_WORD_LENGTH: usize = 4;
+const MIN_PHONE_DIGITS: usize = 4;
+
+/// Scores how well a guest matches a search query, which may be (part of) the guest's name, email
+/// or phone number. Returns `None` if the guest doesn't match at all. Names match if every word of
+/// the query is a prefix of, or a near-miss (one typo) for, some word of the name; phone numbers
+/// are compared by digits only, so formatting doesn't matter.
+pub fn match_score(guest: &Guest, query: &str) -> Option<u32> {
+    let query = query.trim().to_lowercase();
+    if query.is_empty() {
+        return None;
+    }
+
+    if let Some(email) = &guest.email {
+        let email = email.to_lowercase();
+        if email == query {
+            return Some(100);
+        }
+        if query.contains('@') && email.starts_with(&query) {
+            return Some(80);
+        }
+    }
+
+    let query_digits = digits(&query);
+    if let Some(phone) = &guest.phone
+        && query_digits.len() >= MIN_PHONE_DIGITS
+        && query_digits.len() * 2 >= query.chars().filter(|c| !c.is_whitespace()).count()
+    {
+        let phone_digits = digits(phone);
+        if phone_digits == query_digits {
+            return Some(100);
+        }
+        // Allows searching without the country code, or by the last digits only
+        if phone_digits.ends_with(&query_digits) {
+            return Some(70);
+        }
+    }
+
+    name_score(&guest.name, &query)
+}
+
+/// Finds the guests matching a search query, best matches first.
+pub fn search_guests<'a>(guests: &'a [Guest], query: &str, limit: usize) -> Vec<&'a Guest> {
+    let mut matches: Vec<(u32, &Guest)> = guests
+        .iter()
+        .filter_map(|guest| match_score(guest, query).map(|score| (score, guest)))
+        .collect();
+
+    matches.sort_by_key(|&(score, guest)| (std::cmp::Reverse(score), guest.id));
+    matches
+        .into_iter()
+        .take(limit)
+        .map(|(_, guest)| guest)
+        .collect()
+}
+
+fn name_score(name: &str, query: &str) -> Option<u32> {
+    let name = name.to_lowercase();
+    let name_words: Vec<&str> = name.split_whitespace().collect();
+
+    let mut score = 0;
+    let mut query_word_count = 0;
+    for query_word in query.split_whitespace() {
+        query_word_count += 1;
+        let word_score = name_words
+            .iter()
+            .filter_map(|name_word| word_score(name_word, query_word))
+            .max()?;
+        score += word_score;
+    }
+
+    // Average over the words of the query, so that longer queries aren't favored
+    Some(score / query_word_count)
+}
+
+fn word_score(name_word: &str, query_word: &str) -> Option<u32> {
+    if name_word == query_word {
+        Some(60)
+    } else if name_word.starts_with(query_word) {
+        Some(50)
+    } else if query_word.chars().count() >= MIN_FUZZY_WORD_LENGTH
+        && edit_distance(name_word, query_word) <= MAX_NAME_TYPOS
+    {
+        Some(40)
+    } else {
+        None
+    }
+}
+
+fn digits(s: &str) -> String {
+    s.chars().filter(|c| c.is_ascii_digit()).collect()
+}
+
+/// The Levenshtein distance between two strings
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, &b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != b_char);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::RoomPreferences;
+
+    fn guest(id: i64, name: &str, email: Option<&str>, phone: Option<&str>) -> Guest {
+        Guest {
+            id,
+            name: name.to_string(),
+            email: email.map(str::to_string),
+            phone: phone.map(str::to_string),
+            preferences: RoomPreferences::default(),
+        }
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("kowalski", "kowalski"), 0);
+        assert_eq!(edit_distance("kowalski", "kowalsky"), 1);
+        assert_eq!(edit_distance("kowalski", "kowlaski"), 2);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_matches_name_ignoring_case_and_word_order() {
+        let guest = guest(1, "Jan Kowalski", None, None);
+
+        assert!(match_score(&guest, "kowalski jan").is_some());
+        assert!(match_score(&guest, "JAN").is_some());
+        assert!(match_score(&guest, "kow").is_some());
+        assert!(match_score(&guest, "nowak").is_none());
+    }
+
+    #[test]
+    fn test_matches_name_with_a_typo() {
+        let guest = guest(1, "Jan Kowalski", None, None);
+
+        assert!(match_score(&guest, "kowalsky").is_some());
+        assert!(match_score(&guest, "kovalsky").is_none());
+        // Short words must match exactly
+        assert!(match_score(&guest, "jon").is_none());
+    }
+
+    #[test]
+    fn test_matches_email_and_phone() {
+        let guest = guest(
+            1,
+            "Jan Kowalski",
+            Some("Jan.Kowalski@example.com"),
+            Some("+48 600 123 456"),
+        );
+
+        assert_eq!(match_score(&guest, "jan.kowalski@example.com"), Some(100));
+        assert!(match_score(&guest, "jan.kowalski@").is_some());
+        assert_eq!(match_score(&guest, "48600123456"), Some(100));
+        assert!(match_score(&guest, "600-123-456").is_some());
+        assert!(match_score(&guest, "123 457").is_none());
+    }
+
+    #[test]
+    fn test_search_orders_by_score() {
+        let guests = vec![
+            guest(1, "Anna Kowalsky", None, None),
+            guest(2, "Jan Kowalski", None, None),
+            guest(3, "Piotr Nowak", None, None),
+            guest(4, "Anna Kowalski", Some("anna@example.com"), None),
+        ];
+
+        let ids: Vec<i64> = search_guests(&guests, "kowalski", 10)
+            .iter()
+            .map(|g| g.id)
+            .collect();
+        assert_eq!(ids, vec![2, 4, 1]);
+
+        let ids: Vec<i64> = search_guests(&guests, "kowalski", 1)
+            .iter()
+            .map(|g| g.id)
+            .collect();
+        assert_eq!(ids, vec![2]);
+    }
+}
@@ -1,31 +1,184 @@
 use crate::app_state::AppState;
+use crate::currency::{convert_quote, find_exchange_rate, is_currency_code};
+use crate::db;
 use crate::db::{
-    get_all_hotels, get_and_lock_overlapping_bookings, get_booking_by_id,
-    get_bookings_by_hotel_id_and_date, get_hotel_by_id, get_next_booking_id,
+    get_active_hotels, get_all_exchange_rates, get_all_guests, get_all_hotels, get_all_promo_codes,
+    get_and_lock_booking_by_id, get_and_lock_current_and_future_bookings,
+    get_and_lock_group_bookings, get_and_lock_hotel_by_id, get_and_lock_loyalty_balance,
+    get_and_lock_open_authorizations, get_and_lock_overlapping_bookings,
+    get_and_share_lock_hotel_by_id, get_booking_by_id, get_bookings_by_guest_id,
+    get_bookings_by_hotel_id_and_date, get_folio_entries, get_group_hotel_id, get_guest_by_id,
+    get_hotel_by_id, get_hotel_rooms, get_invoice_by_booking_id, get_loyalty_balance,
+    get_loyalty_transactions_by_guest_id, get_next_booking_id, get_next_folio_entry_id,
+    get_next_group_id, get_next_guest_id, get_next_invoice_number, get_next_waitlist_entry_id,
+    get_overlapping_bookings, get_overstaying_bookings, get_payment_authorizations,
+    get_rate_plans_by_hotel_id, get_waitlist_by_hotel_id, get_waitlist_entry_by_id, insert_hotel,
+    insert_promo_code, insert_stay_restriction, insert_tax_rule, update_hotel_overbooking_limit,
+    upsert_exchange_rate, upsert_rate_plan, upsert_room, upsert_room_connection,
 };
 use crate::error::{AppError, AppResult};
-use crate::models::{BookingStatus, Hotel};
+use crate::folio::{
+    build_invoice, folio_balance, format_amount, post_cancellation_fee, post_extra,
+    post_room_charges, render_invoice_text,
+};
+use crate::guests;
+use crate::hotel_time::{
+    hotel_today, is_early_checkin, is_late_checkout, local_time, overstay, stay_end_at_checkout,
+};
+use crate::housekeeping::{housekeeping_tasks, is_valid_transition};
+use crate::loyalty::{POINTS_CURRENCY, points_value, redeem_points, redeemable_amount};
+use crate::models::{
+    Booking, BookingStatus, CancellationPenalty, DEFAULT_ROOM_TYPE, ExchangeRate, Folio,
+    FolioEntryKind, GroupRoomConstraint, Guest, Hotel, HousekeepingStatus, OverbookingLimit,
+    Overstay, PriceQuote, PromoCode, PromoDiscount, RatePlan, Room, RoomConnection,
+    RoomPreferences, StayRestriction, TaxCharge, TaxRule, WaitlistStatus, default_adults,
+};
 use crate::models_client_events::ClientEvent;
 use crate::models_events::{
     BookingCancelledEvent, BookingCheckedInEvent, BookingCheckedOutEvent, BookingCreatedEvent,
-    Event,
+    BookingDatesChangedEvent, BookingHeldEvent, BookingHoldConfirmedEvent,
+    BookingRoomPreassignedEvent, BookingRoomReassignedEvent, BookingWalkedEvent, Event,
+    FolioEntryPostedEvent, GuestProfileEvent, InvoiceIssuedEvent, PaymentDeclinedEvent,
+    RoomHousekeepingStatusChangedEvent, WaitlistJoinedEvent, WaitlistLeftEvent,
+};
+use crate::models_request::{
+    ConfirmHoldRequest, CreateBookingRequest, CreateGroupBookingRequest, CreateHoldRequest,
+    CreateHotelRequest, CreatePromoCodeRequest, CreateStayRestrictionRequest, CreateTaxRuleRequest,
+    GuestProfileRequest, JoinWaitlistRequest, ModifyBookingRequest, PostFolioEntryRequest,
+    PreassignRoomRequest, RatePlanRequest, RedeemLoyaltyPointsRequest, UpdateExchangeRateRequest,
+    UpdateHotelRequest, UpdateHousekeepingStatusRequest, UpdateRoomRequest, WalkGuestRequest,
+};
+use crate::payments::{PaymentError, authorize_deposit, balance_after_settlement, settle_deposits};
+use crate::pricing::{cancellation_fee, free_cancellation_deadline, quote_stay};
+use crate::promo_codes::{PromoCodeError, normalize_promo_code, redeem_promo_code};
+use crate::restrictions::{
+    RestrictionViolation, check_departure_restrictions, check_stay_restrictions,
+};
+use crate::room_assignment::{
+    GroupAllocationError, allocate_group_rooms, assign_room_for_checkin, can_accommodate_booking,
+    can_accommodate_bookings, can_accommodate_parties, can_accommodate_party, nightly_availability,
+    overbooked_nights, repack_preassignments,
 };
-use crate::models_request::CreateBookingRequest;
-use crate::room_assignment::{assign_room_for_checkin, can_accommodate_booking};
 use axum::{
     Json,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{StatusCode, header},
     response::{IntoResponse, Json as ResponseJson, Response},
 };
-use chrono::NaiveDate;
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
 use serde::Deserialize;
 use serde_json::{Value, json};
-use sqlx::{Executor, Postgres};
+use sqlx::{Executor, PgConnection, Postgres, Transaction};
+use tracing::warn;
 
 #[derive(Deserialize)]
 pub struct CheckinQueryParams {
-    today: String,
+    /// Whether to only assign rooms which have been cleaned since the last checkout
+    #[serde(default)]
+    refuse_dirty_rooms: bool,
+    /// Whether to charge the deposit now, rather than at checkout
+    #[serde(default)]
+    capture_deposit: bool,
+    /// Let the guest in before the hotel's check-in time
+    #[serde(default)]
+    early_checkin: bool,
+    /// What to charge for an early check-in, in minor units; free if not given
+    early_checkin_fee: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct CheckoutQueryParams {
+    /// Check out even though the folio isn't settled
+    #[serde(default)]
+    override_balance: bool,
+    /// Let the guest leave after the hotel's check-out time
+    #[serde(default)]
+    late_checkout: bool,
+    /// What to charge for a late checkout, in minor units; free if not given
+    late_checkout_fee: Option<i64>,
+}
+
+#[derive(Deserialize)]
+pub struct InvoiceQueryParams {
+    /// "json" (the default) or "text"
+    format: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct GuestSearchQueryParams {
+    q: String,
+    #[serde(default = "default_guest_search_limit")]
+    limit: usize,
+}
+
+fn default_guest_search_limit() -> usize {
+    10
+}
+
+#[derive(Deserialize)]
+pub struct QuoteQueryParams {
+    from: String,
+    to: String,
+    room_type: Option<String>,
+    /// The guest's currency, to show the price in as well
+    currency: Option<String>,
+    /// The guests staying, for taxes charged per person
+    #[serde(default = "default_adults")]
+    adults: i32,
+    #[serde(default)]
+    children: i32,
+}
+
+#[derive(Deserialize)]
+pub struct HotelsQueryParams {
+    /// Whether to include archived hotels too, which are hidden from guests
+    #[serde(default)]
+    include_archived: bool,
+}
+
+#[derive(Deserialize)]
+pub struct AvailabilityQueryParams {
+    from: String,
+    to: String,
+}
+
+/// Longest stay for which availability can be queried
+const MAX_AVAILABILITY_NIGHTS: i64 = 366;
+const DEFAULT_HOLD_MINUTES: i64 = 15;
+const MAX_HOLD_MINUTES: i64 = 60;
+
+fn parse_date_param(value: &str, name: &str) -> AppResult<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y-%m-%d").map_err(|_| {
+        AppError::bad_request(
+            format!("Invalid date format for '{}'. Use YYYY-MM-DD", name),
+            "INVALID_DATE_FORMAT",
+        )
+    })
+}
+
+fn parse_availability_range(params: &AvailabilityQueryParams) -> AppResult<(NaiveDate, NaiveDate)> {
+    let from = parse_date_param(&params.from, "from")?;
+    let to = parse_date_param(&params.to, "to")?;
+
+    if from >= to {
+        return Err(AppError::bad_request(
+            "'from' must be before 'to'",
+            "INVALID_DATE_RANGE",
+        ));
+    }
+
+    if (to - from).num_days() > MAX_AVAILABILITY_NIGHTS {
+        return Err(AppError::bad_request(
+            format!(
+                "Availability can be queried for at most {} nights",
+                MAX_AVAILABILITY_NIGHTS
+            ),
+            "DATE_RANGE_TOO_LONG",
+        ));
+    }
+
+    Ok((from, to))
 }
 
 async fn get_hotel_or_not_found<'a, E>(executor: E, hotel_id: i64) -> AppResult<Hotel>
@@ -38,6 +191,651 @@ where
     }
 }
 
+/// Gets and exclusively locks a hotel, before any of its bookings are locked. Needed to change the
+/// hotel, or to release its rooms, which offers them to the waitlist.
+async fn get_and_lock_hotel_or_not_found(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+) -> AppResult<Hotel> {
+    match get_and_lock_hotel_by_id(tx, hotel_id).await? {
+        Some(hotel) => Ok(hotel),
+        None => Err(AppError::not_found("Hotel not found")),
+    }
+}
+
+/// Gets and locks a hotel in shared mode, before any of its bookings are locked. Needed to add or
+/// assign bookings.
+async fn get_and_share_lock_hotel_or_not_found(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+) -> AppResult<Hotel> {
+    match get_and_share_lock_hotel_by_id(tx, hotel_id).await? {
+        Some(hotel) => Ok(hotel),
+        None => Err(AppError::not_found("Hotel not found")),
+    }
+}
+
+/// Gets and locks a booking together with its hotel, which is locked first (exclusively if the
+/// booking's room may be released), so that the locks are taken in the same order everywhere.
+async fn get_and_lock_booking_and_hotel(
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+    exclusive: bool,
+) -> AppResult<(Booking, Hotel)> {
+    let hotel_id = get_booking_or_not_found(&mut **tx, booking_id)
+        .await?
+        .hotel_id;
+    let hotel = if exclusive {
+        get_and_lock_hotel_or_not_found(tx, hotel_id).await?
+    } else {
+        get_and_share_lock_hotel_or_not_found(tx, hotel_id).await?
+    };
+
+    match get_and_lock_booking_by_id(tx, booking_id).await? {
+        Some(booking) => Ok((booking, hotel)),
+        None => Err(AppError::not_found("Booking not found")),
+    }
+}
+
+/// Archived hotels keep their bookings, but don't take new ones
+fn ensure_hotel_open(hotel: &Hotel) -> AppResult<()> {
+    if hotel.archived_at.is_some() {
+        return Err(AppError::bad_request(
+            "The hotel has been archived and no longer takes bookings",
+            "HOTEL_ARCHIVED",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Gets and locks the bookings overlapping the given stay, together with all bookings overlapping
+/// those, so that pre-assignments can be re-packed without creating conflicts outside the stay.
+async fn get_and_lock_bookings_for_repacking(
+    tx: &mut Transaction<'_, Postgres>,
+    hotel_id: i64,
+    start_time: NaiveDate,
+    end_time: NaiveDate,
+) -> AppResult<Vec<Booking>> {
+    let overlapping_bookings =
+        get_and_lock_overlapping_bookings(tx, hotel_id, start_time, end_time).await?;
+
+    let window_start = overlapping_bookings
+        .iter()
+        .map(|b| b.start_time)
+        .fold(start_time, NaiveDate::min);
+    let window_end = overlapping_bookings
+        .iter()
+        .map(|b| b.end_time)
+        .fold(end_time, NaiveDate::max);
+
+    if window_start == start_time && window_end == end_time {
+        return Ok(overlapping_bookings);
+    }
+
+    Ok(get_and_lock_overlapping_bookings(tx, hotel_id, window_start, window_end).await?)
+}
+
+/// Validates the size of a party, which must fit into the requested room, or into at least one of
+/// the hotel's rooms if no room is requested. Whether a fitting room is free for the stay is left
+/// to the availability check.
+fn validate_party_size(
+    rooms: &[Room],
+    adults: i32,
+    children: i32,
+    requested_room: Option<i32>,
+) -> AppResult<()> {
+    if adults < 1 || children < 0 {
+        return Err(AppError::bad_request(
+            "A booking needs at least one adult, and the number of children can't be negative",
+            "INVALID_GUEST_COUNT",
+        ));
+    }
+
+    let guest_count = adults + children;
+    if let Some(room) = requested_room.and_then(|n| rooms.iter().find(|r| r.room_number == n)) {
+        if !room.fits(guest_count) {
+            return Err(AppError::bad_request(
+                format!(
+                    "Room {} fits at most {} guests",
+                    room.room_number, room.max_occupancy
+                ),
+                "ROOM_TOO_SMALL",
+            ));
+        }
+    } else if !rooms.iter().any(|room| room.fits(guest_count)) {
+        return Err(AppError::bad_request(
+            format!("No room in the hotel fits {} guests", guest_count),
+            "PARTY_TOO_LARGE",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Decides which room type a stay is priced in: the requested one, or that of the requested room,
+/// or the default one.
+fn resolve_room_type(
+    rooms: &[Room],
+    room_type: Option<&str>,
+    requested_room: Option<i32>,
+) -> AppResult<String> {
+    let requested_room = requested_room.and_then(|n| rooms.iter().find(|r| r.room_number == n));
+
+    match (room_type, requested_room) {
+        (Some(room_type), _) if !rooms.iter().any(|r| r.room_type == room_type) => {
+            Err(AppError::bad_request(
+                format!("The hotel has no rooms of type '{}'", room_type),
+                "INVALID_ROOM_TYPE",
+            ))
+        }
+        (Some(room_type), Some(room)) if room.room_type != room_type => Err(AppError::bad_request(
+            format!(
+                "Room {} is of type '{}', not '{}'",
+                room.room_number, room.room_type, room_type
+            ),
+            "INVALID_ROOM_TYPE",
+        )),
+        (Some(room_type), _) => Ok(room_type.to_string()),
+        (None, Some(room)) => Ok(room.room_type.clone()),
+        (None, None) => Ok(DEFAULT_ROOM_TYPE.to_string()),
+    }
+}
+
+/// Bookings only get rooms of the type they were sold as
+fn ensure_room_of_type(room: &Room, room_type: Option<&str>) -> AppResult<()> {
+    if let Some(room_type) = room_type
+        && !room.is_of_type(Some(room_type))
+    {
+        return Err(AppError::bad_request(
+            format!(
+                "Room {} is of type '{}', not '{}'",
+                room.room_number, room.room_type, room_type
+            ),
+            "INVALID_ROOM_TYPE",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Finds the room reassignments needed to fit a stay for a party of `guest_count` in a room of
+/// `room_type` (optionally a requested one) into the hotel, or returns an error if that's not
+/// possible.
+fn plan_room_repacking(
+    rooms: &[Room],
+    bookings: Vec<Booking>,
+    start_time: NaiveDate,
+    end_time: NaiveDate,
+    requested_room: Option<i32>,
+    guest_count: i32,
+    room_type: Option<&str>,
+) -> AppResult<Vec<BookingRoomReassignedEvent>> {
+    if let Some(room_number) = requested_room {
+        let Some(room) = rooms.iter().find(|r| r.room_number == room_number) else {
+            return Err(AppError::bad_request(
+                "Invalid room number",
+                "INVALID_ROOM_NUMBER",
+            ));
+        };
+        ensure_room_of_type(room, room_type)?;
+    }
+
+    if let Some(reassignments) = repack_preassignments(
+        rooms,
+        bookings.clone(),
+        start_time,
+        end_time,
+        requested_room,
+        guest_count,
+        room_type,
+    ) {
+        return Ok(reassignments);
+    }
+
+    // Distinguish between the requested room being taken, and the hotel being full
+    if requested_room.is_some()
+        && repack_preassignments(
+            rooms,
+            bookings,
+            start_time,
+            end_time,
+            None,
+            guest_count,
+            room_type,
+        )
+        .is_some()
+    {
+        return Err(AppError::bad_request(
+            "Requested room is not available for the requested dates",
+            "ROOM_NOT_AVAILABLE",
+        ));
+    }
+
+    Err(AppError::bad_request(
+        "No rooms available for the requested dates",
+        "NO_ROOMS_AVAILABLE",
+    ))
+}
+
+/// Processes room reassignment events, each within the stream of the reassigned booking.
+async fn process_reassignments(
+    app_state: &AppState,
+    tx: &mut Transaction<'_, Postgres>,
+    reassignments: Vec<BookingRoomReassignedEvent>,
+) -> AppResult<()> {
+    for reassignment in reassignments {
+        let stream_id = reassignment.booking_id;
+        app_state
+            .event_processor
+            .process_event_with_tx(tx, stream_id, Event::BookingRoomReassigned(reassignment))
+            .await?;
+    }
+
+    Ok(())
+}
+
+/// Finds the first stay restriction of the hotel that the stay violates, if any.
+async fn find_restriction_violation<'a, E>(
+    executor: E,
+    hotel_id: i64,
+    start_time: NaiveDate,
+    end_time: NaiveDate,
+) -> AppResult<Option<RestrictionViolation>>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    let restrictions = db::get_stay_restrictions(executor, hotel_id, start_time, end_time).await?;
+    Ok(check_stay_restrictions(&restrictions, start_time, end_time).err())
+}
+
+/// Returns an error with the violation's code if the stay doesn't satisfy the hotel's stay
+/// restrictions.
+async fn enforce_stay_restrictions<'a, E>(
+    executor: E,
+    hotel_id: i64,
+    start_time: NaiveDate,
+    end_time: NaiveDate,
+) -> AppResult<()>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    match find_restriction_violation(executor, hotel_id, start_time, end_time).await? {
+        Some(violation) => Err(violation.into()),
+        None => Ok(()),
+    }
+}
+
+/// Adds the price in the guest's currency to a quote, at the current exchange rate from the hotel's
+/// base currency.
+async fn convert_for_guest(
+    conn: &mut PgConnection,
+    quote: &mut Option<PriceQuote>,
+    currency: Option<&str>,
+) -> AppResult<()> {
+    let Some(currency) = currency else {
+        return Ok(());
+    };
+    if !is_currency_code(currency) {
+        return Err(AppError::bad_request(
+            "The currency must be a three-letter ISO 4217 code",
+            "INVALID_CURRENCY",
+        ));
+    }
+    let Some(quote) = quote.as_mut().filter(|quote| quote.currency != currency) else {
+        return Ok(());
+    };
+
+    match find_exchange_rate(conn, &quote.currency, currency).await? {
+        Some(rate) => {
+            convert_quote(quote, currency, rate);
+            Ok(())
+        }
+        None => Err(AppError::bad_request(
+            format!(
+                "There is no exchange rate from {} to {}",
+                quote.currency, currency
+            ),
+            "NO_EXCHANGE_RATE",
+        )),
+    }
+}
+
+/// Takes the price of the stay as a deposit, if the guest gave a card and the booking is priced.
+async fn authorize_booking_deposit(
+    app_state: &AppState,
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+    payment_token: Option<&str>,
+    quote: Option<&PriceQuote>,
+) -> Result<(), PaymentError> {
+    if let (Some(payment_token), Some(quote)) = (payment_token, quote) {
+        authorize_deposit(
+            app_state.payment_provider.as_ref(),
+            &app_state.event_processor,
+            tx,
+            booking_id,
+            payment_token,
+            quote.total_with_taxes(),
+            &quote.currency,
+        )
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Posts the fee for cancelling a booking today on its folio. Holds are cancelled for free.
+/// Returns the fee.
+async fn post_cancellation_charge(
+    app_state: &AppState,
+    tx: &mut Transaction<'_, Postgres>,
+    booking: &Booking,
+) -> AppResult<i64> {
+    if booking.status != BookingStatus::Confirmed {
+        return Ok(0);
+    }
+
+    let hotel = get_hotel_or_not_found(&mut **tx, booking.hotel_id).await?;
+    Ok(post_cancellation_fee(&app_state.event_processor, tx, booking, hotel_today(&hotel)).await?)
+}
+
+/// Takes what the guest owes for a cancelled booking from its deposits, and releases the rest of
+/// the deposits. Cards are charged, so this has to come last. Returns the amount captured and
+/// what the guest still owes.
+async fn settle_cancellation(
+    app_state: &AppState,
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+) -> Result<(i64, i64), PaymentError> {
+    let balance = folio_balance(&get_folio_entries(&mut **tx, booking_id).await?);
+    let captured = settle_deposits(
+        app_state.payment_provider.as_ref(),
+        &app_state.event_processor,
+        tx,
+        booking_id,
+        Some(balance),
+    )
+    .await?;
+
+    Ok((captured, balance - captured))
+}
+
+/// Rolls back the transaction of an operation that failed because of a payment, and records the
+/// payment on the booking's stream (in a separate transaction) if it was declined. Returns the
+/// error to respond with.
+async fn payment_failed(
+    app_state: &AppState,
+    tx: Transaction<'_, Postgres>,
+    booking_id: i64,
+    error: PaymentError,
+) -> AppError {
+    // The events of the failed operation must be rolled back first, as they share the stream
+    if let Err(e) = tx.rollback().await {
+        return AppError::from(e);
+    }
+
+    if let PaymentError::Declined { reason } = &error {
+        let event = Event::PaymentDeclined(PaymentDeclinedEvent {
+            booking_id,
+            reason: reason.clone(),
+        });
+        let result: anyhow::Result<()> = async {
+            let mut tx = app_state.db_pool.begin().await?;
+            app_state
+                .event_processor
+                .process_event_with_tx(&mut tx, booking_id, event)
+                .await?;
+            tx.commit().await?;
+            Ok(())
+        }
+        .await;
+        if let Err(e) = result {
+            warn!(
+                "Failed to record declined payment of booking {}: {:?}",
+                booking_id, e
+            );
+        }
+    }
+
+    error.into()
+}
+
+async fn get_guest_or_not_found<'a, E>(executor: E, guest_id: i64) -> AppResult<Guest>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    match get_guest_by_id(executor, guest_id).await? {
+        Some(guest) => Ok(guest),
+        None => Err(AppError::not_found("Guest not found")),
+    }
+}
+
+/// Assigns a room to a confirmed booking and checks the guest in, within the given transaction.
+/// The preferences stored in the guest's profile are taken into account as well as the ones given
+/// with the booking. Guests can only move in before the hotel's check-in time if an early
+/// check-in is requested, which may be charged. If a room is requested (e.g. one given out by the
+/// front desk while offline), it's validated and used instead. The arrival date and time are
+/// judged at `checked_in_at`, which is when the guest actually arrived for check-ins synced later
+/// on (times in the future are taken as now). Returns the assigned room and the preferences used.
+async fn check_in_booking(
+    app_state: &AppState,
+    tx: &mut Transaction<'_, Postgres>,
+    booking: &Booking,
+    params: &CheckinQueryParams,
+    requested_room: Option<i32>,
+    checked_in_at: DateTime<Utc>,
+) -> AppResult<(i32, RoomPreferences)> {
+    // Get hotel info for room assignment
+    let hotel = get_hotel_or_not_found(&mut **tx, booking.hotel_id).await?;
+    let now = Utc::now();
+    let today = local_time(&hotel, now).date();
+    let arrived_at = local_time(&hotel, checked_in_at.min(now));
+
+    if arrived_at.date() < booking.start_time {
+        return Err(AppError::bad_request(
+            "Cannot check in before the arrival date",
+            "INVALID_ARRIVAL_DATE",
+        ));
+    }
+    let early = is_early_checkin(&hotel, booking.start_time, arrived_at);
+    if early && !params.early_checkin {
+        return Err(AppError::bad_request(
+            format!(
+                "Check-in is from {}; request an early check-in to let the guest in now",
+                hotel.check_in_time.format("%H:%M")
+            ),
+            "EARLY_CHECKIN",
+        ));
+    }
+    let early_checkin_fee = validate_fee(params.early_checkin_fee)?;
+
+    // Get bookings for today and filter for active bookings with assigned rooms
+    let all_bookings =
+        get_bookings_by_hotel_id_and_date(&mut **tx, booking.hotel_id, today).await?;
+    let mut active_bookings: Vec<_> = all_bookings
+        .into_iter()
+        .filter(|b| b.status == BookingStatus::CheckedIn && b.room_number.is_some())
+        .collect();
+
+    // Rooms pinned to other upcoming guests during the stay aren't available either
+    let pinned_bookings =
+        get_and_lock_overlapping_bookings(tx, booking.hotel_id, today, booking.end_time)
+            .await?
+            .into_iter()
+            .filter(|b| {
+                b.id != booking.id && b.status == BookingStatus::Confirmed && b.room_pinned
+            });
+    active_bookings.extend(pinned_bookings);
+
+    // Surface the preferences from the guest's profile, for returning guests
+    let mut booking = booking.clone();
+    if let Some(guest_id) = booking.guest_id {
+        let guest = get_guest_or_not_found(&mut **tx, guest_id).await?;
+        booking.preferences = booking.preferences.merge(&guest.preferences);
+    }
+
+    // Assign a room using the room assignment algorithm, taking the guest's preferences into account
+    let rooms = get_hotel_rooms(&mut **tx, &hotel).await?;
+    let assigned_room = match requested_room {
+        Some(room_number) => validate_requested_room(
+            &rooms,
+            &active_bookings,
+            &booking,
+            room_number,
+            params.refuse_dirty_rooms,
+        )?,
+        None => assign_room_for_checkin(
+            &rooms,
+            active_bookings,
+            &booking,
+            app_state.room_assignment_strategy.as_ref(),
+            params.refuse_dirty_rooms,
+        )
+        .ok_or_else(|| {
+            AppError::bad_request("No available rooms for check-in", "NO_ROOMS_AVAILABLE")
+        })?,
+    };
+
+    // Create the checkin event with assigned room
+    let event = Event::BookingCheckedIn(BookingCheckedInEvent {
+        booking_id: booking.id,
+        assigned_room,
+    });
+
+    // Process the event within the transaction
+    let stream_id = booking.id; // Use booking_id as stream_id
+    app_state
+        .event_processor
+        .process_event_with_tx(tx, stream_id, event)
+        .await?;
+
+    if let Some(fee) = early_checkin_fee.filter(|_| early) {
+        post_extra(
+            &app_state.event_processor,
+            tx,
+            booking.id,
+            "Early check-in",
+            fee,
+            arrived_at.date(),
+        )
+        .await?;
+    }
+
+    Ok((assigned_room, booking.preferences))
+}
+
+/// Checks that a requested room can be given to a guest checking in: it must be of the booking's
+/// type and fit the party, not be taken by any of the `active_bookings`, and have been cleaned if
+/// dirty rooms are refused.
+fn validate_requested_room(
+    rooms: &[Room],
+    active_bookings: &[Booking],
+    booking: &Booking,
+    room_number: i32,
+    refuse_dirty_rooms: bool,
+) -> AppResult<i32> {
+    let Some(room) = rooms.iter().find(|r| r.room_number == room_number) else {
+        return Err(AppError::bad_request(
+            "Invalid room number",
+            "INVALID_ROOM_NUMBER",
+        ));
+    };
+
+    ensure_room_of_type(room, booking.room_type.as_deref())?;
+    if !room.fits(booking.guest_count()) {
+        return Err(AppError::bad_request(
+            format!(
+                "Room {} fits at most {} guests",
+                room.room_number, room.max_occupancy
+            ),
+            "ROOM_TOO_SMALL",
+        ));
+    }
+
+    if active_bookings
+        .iter()
+        .any(|b| b.room_number == Some(room_number))
+    {
+        return Err(AppError::bad_request(
+            "Room is already occupied",
+            "ROOM_OCCUPIED",
+        ));
+    }
+
+    if refuse_dirty_rooms && room.housekeeping_status < HousekeepingStatus::Clean {
+        return Err(AppError::bad_request(
+            "Room hasn't been cleaned since the last checkout",
+            "ROOM_NOT_READY",
+        ));
+    }
+
+    Ok(room_number)
+}
+
+/// Fees for early check-ins and late checkouts are optional, but have to be positive if given.
+fn validate_fee(fee: Option<i64>) -> AppResult<Option<i64>> {
+    if fee.is_some_and(|fee| fee <= 0) {
+        return Err(AppError::bad_request(
+            "Fees must be positive",
+            "INVALID_FEE",
+        ));
+    }
+
+    Ok(fee)
+}
+
+/// Gets and locks the bookings of a group, and returns the confirmed ones which the action is
+/// taken on. See `confirmed_group_bookings`. The group's hotel is locked first, exclusively if
+/// the action releases rooms.
+async fn get_and_lock_group_or_not_found(
+    tx: &mut Transaction<'_, Postgres>,
+    group_id: i64,
+    allowed: &[BookingStatus],
+    action: &str,
+    exclusive: bool,
+) -> AppResult<Vec<Booking>> {
+    let Some(hotel_id) = get_group_hotel_id(&mut **tx, group_id).await? else {
+        return Err(AppError::not_found("Group not found"));
+    };
+    if exclusive {
+        get_and_lock_hotel_or_not_found(tx, hotel_id).await?;
+    } else {
+        get_and_share_lock_hotel_or_not_found(tx, hotel_id).await?;
+    }
+
+    let bookings = get_and_lock_group_bookings(tx, group_id).await?;
+    if bookings.is_empty() {
+        return Err(AppError::not_found("Group not found"));
+    }
+
+    confirmed_group_bookings(bookings, allowed, action)
+}
+
+/// Verifies that each booking of a group is in one of the `allowed` states, so that an action
+/// on the group is taken on all of its bookings or on none. Returns the confirmed bookings; the
+/// others have had the action taken on them individually already.
+fn confirmed_group_bookings(
+    bookings: Vec<Booking>,
+    allowed: &[BookingStatus],
+    action: &str,
+) -> AppResult<Vec<Booking>> {
+    if let Some(booking) = bookings.iter().find(|b| !allowed.contains(&b.status)) {
+        return Err(AppError::bad_request(
+            format!(
+                "Booking {} of the group is {}, so the group can't be {}",
+                booking.id, booking.status, action
+            ),
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+
+    Ok(bookings
+        .into_iter()
+        .filter(|b| b.status == BookingStatus::Confirmed)
+        .collect())
+}
+
 pub async fn health_check() -> ResponseJson<Value> {
     ResponseJson(json!({
         "status": "healthy",
@@ -45,230 +843,2507 @@ pub async fn health_check() -> ResponseJson<Value> {
     }))
 }
 
-pub async fn get_hotels(State(app_state): State<AppState>) -> AppResult<Response> {
-    let hotels = get_all_hotels(&app_state.db_pool).await?;
-    Ok((StatusCode::OK, ResponseJson(hotels)).into_response())
+pub async fn get_hotels(
+    State(app_state): State<AppState>,
+    Query(params): Query<HotelsQueryParams>,
+) -> AppResult<Response> {
+    let hotels = if params.include_archived {
+        get_all_hotels(&app_state.db_pool).await?
+    } else {
+        get_active_hotels(&app_state.db_pool).await?
+    };
+    Ok((StatusCode::OK, ResponseJson(hotels)).into_response())
+}
+
+pub async fn get_hotel_availability(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Query(params): Query<AvailabilityQueryParams>,
+) -> AppResult<Response> {
+    let (from, to) = parse_availability_range(&params)?;
+
+    let hotel = get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    ensure_hotel_open(&hotel)?;
+    let bookings = get_overlapping_bookings(&app_state.db_pool, hotel_id, from, to).await?;
+    let violation = find_restriction_violation(&app_state.db_pool, hotel_id, from, to).await?;
+
+    // Rooms which may still be sold, including the overbooking allowance
+    let sellable_rooms = hotel.room_count + hotel.overbooking_allowance();
+    let nights = nightly_availability(sellable_rooms, &bookings, from, to);
+    let bookable = violation.is_none()
+        && can_accommodate_booking(
+            hotel.room_count,
+            hotel.overbooking_allowance(),
+            bookings,
+            from,
+            to,
+        );
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "hotel_id": hotel_id,
+            "from": from,
+            "to": to,
+            "nights": nights,
+            "bookable": bookable,
+            "restriction": violation.map(|v| v.code())
+        })),
+    )
+        .into_response())
+}
+
+/// Lists all hotels which can accommodate a stay for the whole date range, and whose stay
+/// restrictions allow it.
+pub async fn search_available_hotels(
+    State(app_state): State<AppState>,
+    Query(params): Query<AvailabilityQueryParams>,
+) -> AppResult<Response> {
+    let (from, to) = parse_availability_range(&params)?;
+
+    let mut available_hotels = Vec::new();
+    for hotel in get_active_hotels(&app_state.db_pool).await? {
+        if find_restriction_violation(&app_state.db_pool, hotel.id, from, to)
+            .await?
+            .is_some()
+        {
+            continue;
+        }
+
+        let bookings = get_overlapping_bookings(&app_state.db_pool, hotel.id, from, to).await?;
+        if can_accommodate_booking(
+            hotel.room_count,
+            hotel.overbooking_allowance(),
+            bookings,
+            from,
+            to,
+        ) {
+            available_hotels.push(hotel);
+        }
+    }
+
+    Ok((StatusCode::OK, ResponseJson(available_hotels)).into_response())
+}
+
+pub async fn create_booking(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Json(request): Json<CreateBookingRequest>,
+) -> AppResult<Response> {
+    // Validate date range
+    if request.start_time >= request.end_time {
+        return Err(AppError::bad_request(
+            "Start time must be before end time",
+            "INVALID_DATE_RANGE",
+        ));
+    }
+
+    // Start a single database transaction for the entire operation
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // First, get hotel info to check room count within the transaction
+    let hotel = get_and_share_lock_hotel_or_not_found(&mut tx, hotel_id).await?;
+    ensure_hotel_open(&hotel)?;
+    enforce_stay_restrictions(&mut *tx, hotel_id, request.start_time, request.end_time).await?;
+    let rooms = get_hotel_rooms(&mut *tx, &hotel).await?;
+    validate_party_size(
+        &rooms,
+        request.adults,
+        request.children,
+        request.room_number,
+    )?;
+    let room_type = resolve_room_type(&rooms, request.room_type.as_deref(), request.room_number)?;
+
+    if let Some(guest_id) = request.guest_id {
+        get_guest_or_not_found(&mut *tx, guest_id).await?;
+    }
+
+    // Check room availability within the transaction
+    // Using SELECT ... FOR UPDATE so that it's not possible to concurrently add overlapping bookings,
+    // which might use stale data to be used to verify booking possibility (write skew).
+    let bookings = get_and_lock_bookings_for_repacking(
+        &mut tx,
+        hotel_id,
+        request.start_time,
+        request.end_time,
+    )
+    .await?;
+
+    let guest_count = request.adults + request.children;
+    let reassignments = if request.room_number.is_none()
+        && can_accommodate_party(
+            &rooms,
+            hotel.overbooking_allowance(),
+            bookings.clone(),
+            request.start_time,
+            request.end_time,
+            guest_count,
+            Some(&room_type),
+        ) {
+        vec![]
+    } else {
+        // Unpinned pre-assignments of other bookings might have to be moved to make room
+        plan_room_repacking(
+            &rooms,
+            bookings,
+            request.start_time,
+            request.end_time,
+            request.room_number,
+            guest_count,
+            Some(&room_type),
+        )?
+    };
+
+    // Redeeming the code counts a use of it, which is given back if the booking fails
+    let promo = match &request.promo_code {
+        Some(code) => Some(redeem_promo_code(&mut tx, code, hotel_id, request.start_time).await?),
+        None => None,
+    };
+
+    // Price the stay with the current rates, which the booking keeps even if they change later
+    let mut quote = quote_stay(
+        &mut tx,
+        hotel_id,
+        &room_type,
+        request.start_time,
+        request.end_time,
+        request.adults + request.children,
+        promo.as_ref().map(|p| (p.code.as_str(), &p.discount)),
+    )
+    .await?;
+    if promo.is_some() && quote.as_ref().is_none_or(|quote| quote.promo.is_none()) {
+        return Err(PromoCodeError::NotApplicable.into());
+    }
+    convert_for_guest(&mut tx, &mut quote, request.currency.as_deref()).await?;
+
+    // Generate booking ID within the transaction
+    let booking_id = get_next_booking_id(&mut tx).await?;
+
+    // Create the booking event
+    let event = Event::BookingCreated(BookingCreatedEvent {
+        booking_id,
+        hotel_id,
+        guest_name: request.guest_name,
+        start_time: request.start_time,
+        end_time: request.end_time,
+        preferences: request.preferences,
+        group_id: None,
+        adults: request.adults,
+        children: request.children,
+        guest_id: request.guest_id,
+        quote: quote.clone(),
+        room_type: Some(room_type),
+    });
+
+    // Process the event within the existing transaction
+    let stream_id = booking_id; // Use booking_id as stream_id
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, stream_id, event)
+        .await?;
+
+    process_reassignments(&app_state, &mut tx, reassignments).await?;
+
+    if let Some(room_number) = request.room_number {
+        let event = Event::BookingRoomPreassigned(BookingRoomPreassignedEvent {
+            booking_id,
+            room_number,
+            pinned: request.pin_room,
+        });
+        app_state
+            .event_processor
+            .process_event_with_tx(&mut tx, stream_id, event)
+            .await?;
+    }
+
+    // Last, so that nothing fails after the guest's card has been charged
+    if let Err(e) = authorize_booking_deposit(
+        &app_state,
+        &mut tx,
+        booking_id,
+        request.payment_token.as_deref(),
+        quote.as_ref(),
+    )
+    .await
+    {
+        return Err(payment_failed(&app_state, tx, booking_id, e).await);
+    }
+
+    // Commit the transaction
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        ResponseJson(json!({
+            "booking_id": booking_id,
+            "quote": quote,
+            "message": "Booking created successfully"
+        })),
+    )
+        .into_response())
+}
+
+/// Holds a room for the given dates for a limited time, so that the guest can enter their
+/// details. The hold counts toward the hotel's capacity until it's confirmed or expires.
+pub async fn create_hold(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Json(request): Json<CreateHoldRequest>,
+) -> AppResult<Response> {
+    if request.start_time >= request.end_time {
+        return Err(AppError::bad_request(
+            "Start time must be before end time",
+            "INVALID_DATE_RANGE",
+        ));
+    }
+
+    let hold_minutes = request.hold_minutes.unwrap_or(DEFAULT_HOLD_MINUTES);
+    if !(1..=MAX_HOLD_MINUTES).contains(&hold_minutes) {
+        return Err(AppError::bad_request(
+            format!("Holds must last between 1 and {} minutes", MAX_HOLD_MINUTES),
+            "INVALID_HOLD_DURATION",
+        ));
+    }
+
+    let mut tx = app_state.db_pool.begin().await?;
+
+    let hotel = get_and_share_lock_hotel_or_not_found(&mut tx, hotel_id).await?;
+    ensure_hotel_open(&hotel)?;
+    enforce_stay_restrictions(&mut *tx, hotel_id, request.start_time, request.end_time).await?;
+    let rooms = get_hotel_rooms(&mut *tx, &hotel).await?;
+    validate_party_size(&rooms, request.adults, request.children, None)?;
+    let room_type = resolve_room_type(&rooms, request.room_type.as_deref(), None)?;
+
+    let bookings = get_and_lock_bookings_for_repacking(
+        &mut tx,
+        hotel_id,
+        request.start_time,
+        request.end_time,
+    )
+    .await?;
+
+    let guest_count = request.adults + request.children;
+    let reassignments = if can_accommodate_party(
+        &rooms,
+        hotel.overbooking_allowance(),
+        bookings.clone(),
+        request.start_time,
+        request.end_time,
+        guest_count,
+        Some(&room_type),
+    ) {
+        vec![]
+    } else {
+        plan_room_repacking(
+            &rooms,
+            bookings,
+            request.start_time,
+            request.end_time,
+            None,
+            guest_count,
+            Some(&room_type),
+        )?
+    };
+
+    let mut quote = quote_stay(
+        &mut tx,
+        hotel_id,
+        &room_type,
+        request.start_time,
+        request.end_time,
+        request.adults + request.children,
+        None,
+    )
+    .await?;
+    convert_for_guest(&mut tx, &mut quote, request.currency.as_deref()).await?;
+
+    let booking_id = get_next_booking_id(&mut tx).await?;
+    let expires_at = Utc::now() + Duration::minutes(hold_minutes);
+
+    let event = Event::BookingHeld(BookingHeldEvent {
+        booking_id,
+        hotel_id,
+        start_time: request.start_time,
+        end_time: request.end_time,
+        expires_at,
+        adults: request.adults,
+        children: request.children,
+        quote: quote.clone(),
+        room_type: Some(room_type),
+    });
+
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, booking_id, event)
+        .await?;
+
+    process_reassignments(&app_state, &mut tx, reassignments).await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        ResponseJson(json!({
+            "booking_id": booking_id,
+            "expires_at": expires_at,
+            "quote": quote,
+            "message": "Room held successfully"
+        })),
+    )
+        .into_response())
+}
+
+/// Turns a hold into a confirmed booking, provided the hold hasn't expired yet.
+pub async fn confirm_hold(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+    Json(request): Json<ConfirmHoldRequest>,
+) -> AppResult<Response> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // The booking is locked before its status and expiry are checked, so that the expiry job
+    // (which locks the hotel exclusively) can't expire the hold while it's being confirmed
+    let (booking, _) = get_and_lock_booking_and_hotel(&mut tx, booking_id, false).await?;
+
+    if booking.status == BookingStatus::Expired
+        || (booking.status == BookingStatus::Held
+            && booking
+                .hold_expires_at
+                .is_some_and(|expires_at| expires_at <= Utc::now()))
+    {
+        return Err(AppError::bad_request(
+            "The hold has expired",
+            "HOLD_EXPIRED",
+        ));
+    }
+
+    if booking.status != BookingStatus::Held {
+        return Err(AppError::bad_request(
+            "Booking must be held to confirm",
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+
+    if let Some(guest_id) = request.guest_id {
+        get_guest_or_not_found(&mut *tx, guest_id).await?;
+    }
+
+    let event = Event::BookingHoldConfirmed(BookingHoldConfirmedEvent {
+        booking_id,
+        guest_name: request.guest_name,
+        preferences: request.preferences,
+        guest_id: request.guest_id,
+    });
+
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, booking_id, event)
+        .await?;
+
+    if let Err(e) = authorize_booking_deposit(
+        &app_state,
+        &mut tx,
+        booking_id,
+        request.payment_token.as_deref(),
+        booking.quote.as_ref(),
+    )
+    .await
+    {
+        return Err(payment_failed(&app_state, tx, booking_id, e).await);
+    }
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "booking_id": booking_id,
+            "message": "Booking confirmed successfully"
+        })),
+    )
+        .into_response())
+}
+
+pub async fn create_group_booking(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Json(request): Json<CreateGroupBookingRequest>,
+) -> AppResult<Response> {
+    // Validate date range
+    if request.start_time >= request.end_time {
+        return Err(AppError::bad_request(
+            "Start time must be before end time",
+            "INVALID_DATE_RANGE",
+        ));
+    }
+
+    if request.number_of_rooms < 1 {
+        return Err(AppError::bad_request(
+            "At least one room must be requested",
+            "INVALID_NUMBER_OF_ROOMS",
+        ));
+    }
+
+    // Start a single database transaction for the entire operation
+    let mut tx = app_state.db_pool.begin().await?;
+
+    let hotel = get_and_share_lock_hotel_or_not_found(&mut tx, hotel_id).await?;
+    ensure_hotel_open(&hotel)?;
+    // No group can be larger than the hotel, which also bounds the parties allocated below
+    if request.number_of_rooms > hotel.room_count + hotel.overbooking_allowance() {
+        return Err(AppError::bad_request(
+            "More rooms were requested than the hotel has",
+            "INVALID_NUMBER_OF_ROOMS",
+        ));
+    }
+    enforce_stay_restrictions(&mut *tx, hotel_id, request.start_time, request.end_time).await?;
+    let hotel_rooms = get_hotel_rooms(&mut *tx, &hotel).await?;
+    validate_party_size(
+        &hotel_rooms,
+        request.adults_per_room,
+        request.children_per_room,
+        None,
+    )?;
+    let connections = db::get_room_connections(&mut *tx, hotel_id).await?;
+
+    // Lock the bookings around the stay, so that no conflicting bookings can be added concurrently
+    let bookings = get_and_lock_bookings_for_repacking(
+        &mut tx,
+        hotel_id,
+        request.start_time,
+        request.end_time,
+    )
+    .await?;
+
+    let parties =
+        vec![request.adults_per_room + request.children_per_room; request.number_of_rooms as usize];
+    let rooms = allocate_group_rooms(
+        &hotel_rooms,
+        bookings,
+        request.start_time,
+        request.end_time,
+        &parties,
+        request.constraint,
+        &connections,
+    )
+    .map_err(|e| {
+        let code = match e {
+            GroupAllocationError::NotEnoughRooms { .. } => "NO_ROOMS_AVAILABLE",
+            GroupAllocationError::NotEnoughConnectedRooms { .. }
+            | GroupAllocationError::ConnectedRoomsNeededElsewhere { .. } => {
+                "NO_CONNECTED_ROOMS_AVAILABLE"
+            }
+        };
+        AppError::bad_request(e.to_string(), code)
+    })?;
+
+    // Create one booking per room, with the room pre-assigned. The rooms are pinned if they have
+    // to stay next to each other, otherwise re-packing may still move them.
+    let group_id = get_next_group_id(&mut tx).await?;
+    let mut booking_ids = Vec::with_capacity(rooms.len());
+    for &room_number in &rooms {
+        let booking_id = get_next_booking_id(&mut tx).await?;
+        let stream_id = booking_id;
+
+        // Each booking is sold and priced as the type of its room
+        let room_type = hotel_rooms
+            .get((room_number - 1) as usize)
+            .map_or(DEFAULT_ROOM_TYPE, |room| room.room_type.as_str());
+        let quote = quote_stay(
+            &mut tx,
+            hotel_id,
+            room_type,
+            request.start_time,
+            request.end_time,
+            request.adults_per_room + request.children_per_room,
+            None,
+        )
+        .await?;
+
+        let events = [
+            Event::BookingCreated(BookingCreatedEvent {
+                booking_id,
+                hotel_id,
+                guest_name: request.guest_name.clone(),
+                start_time: request.start_time,
+                end_time: request.end_time,
+                preferences: Default::default(),
+                group_id: Some(group_id),
+                adults: request.adults_per_room,
+                children: request.children_per_room,
+                guest_id: None,
+                quote,
+                room_type: Some(room_type.to_string()),
+            }),
+            Event::BookingRoomPreassigned(BookingRoomPreassignedEvent {
+                booking_id,
+                room_number,
+                pinned: request.constraint != GroupRoomConstraint::None,
+            }),
+        ];
+        for event in events {
+            app_state
+                .event_processor
+                .process_event_with_tx(&mut tx, stream_id, event)
+                .await?;
+        }
+
+        booking_ids.push(booking_id);
+    }
+
+    // Commit the transaction
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        ResponseJson(json!({
+            "group_id": group_id,
+            "booking_ids": booking_ids,
+            "rooms": rooms,
+            "message": "Group booking created successfully"
+        })),
+    )
+        .into_response())
+}
+
+/// Checks in every confirmed booking of a group at once. Either all of them get a room, or none
+/// of them is checked in.
+pub async fn checkin_group(
+    State(app_state): State<AppState>,
+    Path(group_id): Path<i64>,
+    Query(params): Query<CheckinQueryParams>,
+) -> AppResult<Response> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Bookings already checked in (individually) or cancelled are skipped
+    let bookings = get_and_lock_group_or_not_found(
+        &mut tx,
+        group_id,
+        &[
+            BookingStatus::Confirmed,
+            BookingStatus::CheckedIn,
+            BookingStatus::Cancelled,
+        ],
+        "checked in",
+        false,
+    )
+    .await?;
+
+    let mut checked_in = Vec::new();
+    for booking in &bookings {
+        let (room_number, preferences) =
+            check_in_booking(&app_state, &mut tx, booking, &params, None, Utc::now()).await?;
+        checked_in.push(json!({
+            "booking_id": booking.id,
+            "room_number": room_number,
+            "preferences": preferences
+        }));
+    }
+
+    if checked_in.is_empty() {
+        return Err(AppError::bad_request(
+            "The group has no bookings to check in",
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "bookings": checked_in,
+            "message": "Group checked in successfully"
+        })),
+    )
+        .into_response())
+}
+
+/// Cancels every booking of a group at once. Not possible once any of the guests checked in.
+pub async fn cancel_group(
+    State(app_state): State<AppState>,
+    Path(group_id): Path<i64>,
+) -> AppResult<Response> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Bookings already cancelled (individually) are skipped
+    let bookings = get_and_lock_group_or_not_found(
+        &mut tx,
+        group_id,
+        &[BookingStatus::Confirmed, BookingStatus::Cancelled],
+        "cancelled",
+        true,
+    )
+    .await?;
+
+    let mut cancelled_ids = Vec::new();
+    let mut fees = 0;
+    for booking in &bookings {
+        let event = Event::BookingCancelled(BookingCancelledEvent {
+            booking_id: booking.id,
+        });
+        app_state
+            .event_processor
+            .process_event_with_tx(&mut tx, booking.id, event)
+            .await?;
+
+        fees += post_cancellation_charge(&app_state, &mut tx, booking).await?;
+        cancelled_ids.push(booking.id);
+    }
+
+    // Cards are only charged once every booking is cancelled. Once one has been, a failing payment
+    // mustn't roll the charge back, so the guests are left owing the rest instead.
+    let mut captured = 0;
+    for booking in &bookings {
+        match settle_cancellation(&app_state, &mut tx, booking.id).await {
+            Ok((booking_captured, _)) => captured += booking_captured,
+            Err(e) if captured > 0 => warn!(
+                "Failed to settle the deposits of cancelled booking {}: {}",
+                booking.id, e
+            ),
+            Err(e) => return Err(payment_failed(&app_state, tx, booking.id, e).await),
+        }
+    }
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "booking_ids": cancelled_ids,
+            "cancellation_fees": fees,
+            "message": "Group cancelled successfully"
+        })),
+    )
+        .into_response())
+}
+
+/// Puts a guest on the waitlist of a fully-booked hotel. Once a room frees up for the requested
+/// dates, a held booking is created for the guest.
+pub async fn join_waitlist(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Json(request): Json<JoinWaitlistRequest>,
+) -> AppResult<Response> {
+    if request.start_time >= request.end_time {
+        return Err(AppError::bad_request(
+            "Start time must be before end time",
+            "INVALID_DATE_RANGE",
+        ));
+    }
+
+    let mut tx = app_state.db_pool.begin().await?;
+
+    let hotel = get_and_share_lock_hotel_or_not_found(&mut tx, hotel_id).await?;
+    ensure_hotel_open(&hotel)?;
+    enforce_stay_restrictions(&mut *tx, hotel_id, request.start_time, request.end_time).await?;
+    let rooms = get_hotel_rooms(&mut *tx, &hotel).await?;
+    validate_party_size(&rooms, request.adults, request.children, None)?;
+
+    // Lock the bookings, so that a room can't be released without the waitlist being checked.
+    // Guests on the waitlist are offered rooms of the default type.
+    let bookings =
+        get_and_lock_overlapping_bookings(&mut tx, hotel_id, request.start_time, request.end_time)
+            .await?;
+    if can_accommodate_party(
+        &rooms,
+        hotel.overbooking_allowance(),
+        bookings,
+        request.start_time,
+        request.end_time,
+        request.adults + request.children,
+        Some(DEFAULT_ROOM_TYPE),
+    ) {
+        return Err(AppError::bad_request(
+            "Rooms are available for the requested dates, so they can be booked directly",
+            "ROOMS_AVAILABLE",
+        ));
+    }
+
+    let entry_id = get_next_waitlist_entry_id(&mut tx).await?;
+    let event = Event::WaitlistJoined(WaitlistJoinedEvent {
+        entry_id,
+        hotel_id,
+        guest_name: request.guest_name,
+        email: request.email,
+        start_time: request.start_time,
+        end_time: request.end_time,
+        adults: request.adults,
+        children: request.children,
+    });
+
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, entry_id, event)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        ResponseJson(json!({
+            "entry_id": entry_id,
+            "message": "Joined the waitlist successfully"
+        })),
+    )
+        .into_response())
+}
+
+/// Lists the open waitlist entries of a hotel, in the order they'll be offered rooms.
+pub async fn get_waitlist(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+) -> AppResult<Response> {
+    get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    let entries = get_waitlist_by_hotel_id(&app_state.db_pool, hotel_id).await?;
+
+    Ok((StatusCode::OK, ResponseJson(entries)).into_response())
+}
+
+pub async fn leave_waitlist(
+    State(app_state): State<AppState>,
+    Path(entry_id): Path<i64>,
+) -> AppResult<Response> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    let entry = match get_waitlist_entry_by_id(&mut *tx, entry_id).await? {
+        Some(entry) => entry,
+        None => return Err(AppError::not_found("Waitlist entry not found")),
+    };
+
+    // Once offered, the held booking has to be cancelled instead
+    if entry.status != WaitlistStatus::Waiting {
+        return Err(AppError::bad_request(
+            "Only waiting entries can leave the waitlist",
+            "INVALID_WAITLIST_STATUS",
+        ));
+    }
+
+    let event = Event::WaitlistLeft(WaitlistLeftEvent { entry_id });
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, entry_id, event)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "message": "Left the waitlist successfully"
+        })),
+    )
+        .into_response())
+}
+
+pub async fn create_guest(
+    State(app_state): State<AppState>,
+    Json(request): Json<GuestProfileRequest>,
+) -> AppResult<Response> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    let guest_id = get_next_guest_id(&mut tx).await?;
+    let event = Event::GuestProfileCreated(guest_profile_event(guest_id, request)?);
+
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, guest_id, event)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        ResponseJson(json!({
+            "guest_id": guest_id,
+            "message": "Guest profile created successfully"
+        })),
+    )
+        .into_response())
+}
+
+pub async fn update_guest(
+    State(app_state): State<AppState>,
+    Path(guest_id): Path<i64>,
+    Json(request): Json<GuestProfileRequest>,
+) -> AppResult<Response> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    get_guest_or_not_found(&mut *tx, guest_id).await?;
+    let event = Event::GuestProfileUpdated(guest_profile_event(guest_id, request)?);
+
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, guest_id, event)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "message": "Guest profile updated successfully"
+        })),
+    )
+        .into_response())
+}
+
+fn guest_profile_event(
+    guest_id: i64,
+    request: GuestProfileRequest,
+) -> AppResult<GuestProfileEvent> {
+    let name = request.name.trim().to_string();
+    if name.is_empty() {
+        return Err(AppError::bad_request(
+            "Guest name must not be empty",
+            "INVALID_GUEST_NAME",
+        ));
+    }
+
+    // Blank contact details are treated as missing
+    let non_blank = |value: Option<String>| {
+        value
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+    };
+
+    Ok(GuestProfileEvent {
+        guest_id,
+        name,
+        email: non_blank(request.email),
+        phone: non_blank(request.phone),
+        preferences: request.preferences,
+    })
+}
+
+/// Gets a guest profile, together with the guest's stay history (latest stays first).
+pub async fn get_guest(
+    State(app_state): State<AppState>,
+    Path(guest_id): Path<i64>,
+) -> AppResult<Response> {
+    let guest = get_guest_or_not_found(&app_state.db_pool, guest_id).await?;
+    let bookings = get_bookings_by_guest_id(&app_state.db_pool, guest_id).await?;
+    let completed_stays = bookings
+        .iter()
+        .filter(|b| b.status == BookingStatus::CheckedOut)
+        .count();
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "guest": guest,
+            "completed_stays": completed_stays,
+            "bookings": bookings
+        })),
+    )
+        .into_response())
+}
+
+/// Searches guest profiles by (parts of) name, email or phone number, tolerating small typos.
+pub async fn search_guests(
+    State(app_state): State<AppState>,
+    Query(params): Query<GuestSearchQueryParams>,
+) -> AppResult<Response> {
+    // Fuzzy matching can't be expressed as a simple filter, so the profiles are scored in memory
+    let guests = get_all_guests(&app_state.db_pool).await?;
+    let matches = guests::search_guests(&guests, &params.q, params.limit);
+
+    Ok((StatusCode::OK, ResponseJson(matches)).into_response())
+}
+
+pub async fn get_hotel(
+    State(app_state): State<AppState>,
+    Path(id): Path<i64>,
+    Query(params): Query<HotelsQueryParams>,
+) -> AppResult<Response> {
+    let hotel = get_hotel_or_not_found(&app_state.db_pool, id).await?;
+    if hotel.archived_at.is_some() && !params.include_archived {
+        return Err(AppError::not_found("Hotel not found"));
+    }
+    Ok((StatusCode::OK, ResponseJson(hotel)).into_response())
+}
+
+pub async fn create_hotel(
+    State(app_state): State<AppState>,
+    Json(request): Json<CreateHotelRequest>,
+) -> AppResult<Response> {
+    validate_hotel(&request.name, request.room_count)?;
+    if !is_currency_code(&request.base_currency) {
+        return Err(AppError::bad_request(
+            "The base currency must be an ISO 4217 code, e.g. EUR",
+            "INVALID_CURRENCY",
+        ));
+    }
+    let time_zone = parse_time_zone(&request.time_zone)?;
+
+    let mut hotel = Hotel {
+        id: 0,
+        name: request.name.trim().to_string(),
+        room_count: request.room_count,
+        no_show_cutoff: request
+            .no_show_cutoff
+            .unwrap_or_else(|| NaiveTime::from_hms_opt(23, 59, 0).unwrap()),
+        overbooking_limit: OverbookingLimit::None,
+        base_currency: request.base_currency,
+        time_zone,
+        check_in_time: request
+            .check_in_time
+            .unwrap_or_else(|| NaiveTime::from_hms_opt(15, 0, 0).unwrap()),
+        check_out_time: request
+            .check_out_time
+            .unwrap_or_else(|| NaiveTime::from_hms_opt(11, 0, 0).unwrap()),
+        archived_at: None,
+    };
+    hotel.id = insert_hotel(&app_state.db_pool, &hotel).await?;
+
+    Ok((StatusCode::CREATED, ResponseJson(hotel)).into_response())
+}
+
+/// Renames a hotel, changes its number of rooms, or its time zone and check-in/check-out times.
+/// Rooms can only be taken away if the bookings which have been accepted still fit into the rest,
+/// and none of the rooms taken away is assigned.
+pub async fn update_hotel(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Json(request): Json<UpdateHotelRequest>,
+) -> AppResult<Response> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Locking the hotel keeps bookings from being added or moved while it changes
+    let mut hotel = get_and_lock_hotel_or_not_found(&mut tx, hotel_id).await?;
+    let old_room_count = hotel.room_count;
+    if let Some(name) = request.name {
+        hotel.name = name.trim().to_string();
+    }
+    if let Some(room_count) = request.room_count {
+        hotel.room_count = room_count;
+    }
+    if let Some(time_zone) = &request.time_zone {
+        hotel.time_zone = parse_time_zone(time_zone)?;
+    }
+    if let Some(check_in_time) = request.check_in_time {
+        hotel.check_in_time = check_in_time;
+    }
+    if let Some(check_out_time) = request.check_out_time {
+        hotel.check_out_time = check_out_time;
+    }
+    validate_hotel(&hotel.name, hotel.room_count)?;
+
+    if hotel.room_count < old_room_count {
+        // Lock the bookings, so that none can be moved into the rooms being taken away
+        let bookings =
+            get_and_lock_current_and_future_bookings(&mut tx, hotel_id, hotel_today(&hotel))
+                .await?;
+
+        if let Some(booking) = bookings
+            .iter()
+            .find(|b| b.room_number.is_some_and(|room| room > hotel.room_count))
+        {
+            return Err(AppError::bad_request(
+                format!(
+                    "Room {} is assigned to booking {}; move the booking to another room first",
+                    booking.room_number.unwrap_or_default(),
+                    booking.id
+                ),
+                "ROOM_IN_USE",
+            ));
+        }
+
+        if !can_accommodate_bookings(hotel.room_count, hotel.overbooking_allowance(), bookings) {
+            return Err(AppError::bad_request(
+                "The hotel's bookings wouldn't fit into fewer rooms",
+                "CAPACITY_IN_USE",
+            ));
+        }
+    }
+
+    db::update_hotel(&mut tx, &hotel).await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, ResponseJson(hotel)).into_response())
+}
+
+/// Hides a hotel from guests and stops it from taking new bookings. Its existing bookings, folios
+/// and invoices are kept, and can still be managed.
+pub async fn archive_hotel(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+) -> AppResult<Response> {
+    let Some(archived_at) = db::archive_hotel(&app_state.db_pool, hotel_id).await? else {
+        return Err(AppError::not_found("Hotel not found"));
+    };
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "hotel_id": hotel_id,
+            "archived_at": archived_at,
+            "message": "Hotel archived successfully"
+        })),
+    )
+        .into_response())
+}
+
+pub async fn get_rooms(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+) -> AppResult<Response> {
+    let hotel = get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    let rooms = get_hotel_rooms(&app_state.db_pool, &hotel).await?;
+
+    Ok((StatusCode::OK, ResponseJson(rooms)).into_response())
+}
+
+/// Changes how many guests a room fits, and the type it's sold and priced as. Existing bookings keep
+/// the price they were quoted.
+pub async fn update_room(
+    State(app_state): State<AppState>,
+    Path((hotel_id, room_number)): Path<(i64, i32)>,
+    Json(request): Json<UpdateRoomRequest>,
+) -> AppResult<Response> {
+    if request.max_occupancy.is_some_and(|max| max < 1) {
+        return Err(AppError::bad_request(
+            "A room must fit at least one guest",
+            "INVALID_ROOM",
+        ));
+    }
+    if request
+        .room_type
+        .as_ref()
+        .is_some_and(|room_type| room_type.trim().is_empty())
+    {
+        return Err(AppError::bad_request(
+            "The room type can't be empty",
+            "INVALID_ROOM",
+        ));
+    }
+
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Locking the hotel keeps bookings from being added or moved into the room while it changes
+    let hotel = get_and_lock_hotel_or_not_found(&mut tx, hotel_id).await?;
+    let mut rooms = get_hotel_rooms(&mut *tx, &hotel).await?;
+    let room = rooms
+        .iter_mut()
+        .find(|room| room.room_number == room_number)
+        .ok_or_else(|| AppError::not_found("Room not found"))?;
+
+    let (old_max_occupancy, old_room_type) = (room.max_occupancy, room.room_type.clone());
+    if let Some(max_occupancy) = request.max_occupancy {
+        room.max_occupancy = max_occupancy;
+    }
+    if let Some(room_type) = &request.room_type {
+        room.room_type = room_type.trim().to_string();
+    }
+    let room = room.clone();
+
+    if room.max_occupancy < old_max_occupancy || room.room_type != old_room_type {
+        // Lock the bookings, so that none which the room no longer suits can be moved into it
+        let bookings =
+            get_and_lock_current_and_future_bookings(&mut tx, hotel_id, hotel_today(&hotel))
+                .await?;
+
+        if let Some(booking) = bookings.iter().find(|b| {
+            b.status.holds_inventory()
+                && b.room_number == Some(room_number)
+                && !(room.fits(b.guest_count()) && room.is_of_type(b.room_type.as_deref()))
+        }) {
+            return Err(AppError::bad_request(
+                format!(
+                    "Room {} is assigned to booking {}, which it wouldn't suit; move the booking to another room first",
+                    room_number, booking.id
+                ),
+                "ROOM_IN_USE",
+            ));
+        }
+
+        if !can_accommodate_parties(&rooms, hotel.overbooking_allowance(), bookings) {
+            return Err(AppError::bad_request(
+                "The hotel's bookings wouldn't fit into its rooms",
+                "CAPACITY_IN_USE",
+            ));
+        }
+    }
+
+    upsert_room(&mut tx, hotel_id, &room).await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, ResponseJson(room)).into_response())
+}
+
+pub async fn get_room_connections(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+) -> AppResult<Response> {
+    get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    let connections = db::get_room_connections(&app_state.db_pool, hotel_id).await?;
+
+    Ok((StatusCode::OK, ResponseJson(connections)).into_response())
+}
+
+/// Orders the rooms of a connection as they're stored, the lower room number first, and checks
+/// that they're two different rooms of the hotel.
+fn normalize_room_pair(hotel: &Hotel, room_a: i32, room_b: i32) -> AppResult<(i32, i32)> {
+    let rooms = 1..=hotel.room_count;
+    if room_a == room_b || !rooms.contains(&room_a) || !rooms.contains(&room_b) {
+        return Err(AppError::bad_request(
+            "A connection needs two different rooms of the hotel",
+            "INVALID_ROOM_CONNECTION",
+        ));
+    }
+
+    Ok((room_a.min(room_b), room_a.max(room_b)))
+}
+
+/// Connects two rooms, or changes how they're connected. Groups which already have their rooms
+/// keep them.
+pub async fn create_room_connection(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Json(connection): Json<RoomConnection>,
+) -> AppResult<Response> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Group bookings read the connections under a shared lock of the hotel
+    let hotel = get_and_lock_hotel_or_not_found(&mut tx, hotel_id).await?;
+    let (room_a, room_b) = normalize_room_pair(&hotel, connection.room_a, connection.room_b)?;
+    let connection = RoomConnection {
+        room_a,
+        room_b,
+        kind: connection.kind,
+    };
+
+    upsert_room_connection(&mut tx, hotel_id, &connection).await?;
+    tx.commit().await?;
+
+    Ok((StatusCode::OK, ResponseJson(connection)).into_response())
+}
+
+pub async fn delete_room_connection(
+    State(app_state): State<AppState>,
+    Path((hotel_id, room_a, room_b)): Path<(i64, i32, i32)>,
+) -> AppResult<Response> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    let hotel = get_and_lock_hotel_or_not_found(&mut tx, hotel_id).await?;
+    let (room_a, room_b) = normalize_room_pair(&hotel, room_a, room_b)?;
+    if !db::delete_room_connection(&mut tx, hotel_id, room_a, room_b).await? {
+        return Err(AppError::not_found("Room connection not found"));
+    }
+    tx.commit().await?;
+
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
+
+fn parse_time_zone(name: &str) -> AppResult<Tz> {
+    name.parse::<Tz>().map_err(|_| {
+        AppError::bad_request(
+            format!("{} isn't an IANA time zone, e.g. Europe/Warsaw", name),
+            "INVALID_TIME_ZONE",
+        )
+    })
+}
+
+fn validate_hotel(name: &str, room_count: i32) -> AppResult<()> {
+    if name.trim().is_empty() {
+        return Err(AppError::bad_request("A name is required", "INVALID_HOTEL"));
+    }
+    if room_count <= 0 {
+        return Err(AppError::bad_request(
+            "A hotel must have at least one room",
+            "INVALID_HOTEL",
+        ));
+    }
+
+    Ok(())
+}
+
+pub async fn preassign_room(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+    Json(request): Json<PreassignRoomRequest>,
+) -> AppResult<Response> {
+    // Start a database transaction
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Get the booking and verify it exists and is in confirmed state
+    let (booking, hotel) = get_and_lock_booking_and_hotel(&mut tx, booking_id, false).await?;
+
+    // Verify booking is in confirmed state
+    if booking.status != BookingStatus::Confirmed {
+        return Err(AppError::bad_request(
+            "Booking must be in confirmed state to pre-assign a room",
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+
+    let rooms = get_hotel_rooms(&mut *tx, &hotel).await?;
+    validate_party_size(
+        &rooms,
+        booking.adults,
+        booking.children,
+        Some(request.room_number),
+    )?;
+
+    // Lock the other bookings around the stay, and check if the room can be assigned,
+    // possibly moving other unpinned pre-assignments
+    let other_bookings: Vec<_> = get_and_lock_bookings_for_repacking(
+        &mut tx,
+        booking.hotel_id,
+        booking.start_time,
+        booking.end_time,
+    )
+    .await?
+    .into_iter()
+    .filter(|b| b.id != booking_id)
+    .collect();
+
+    let reassignments = plan_room_repacking(
+        &rooms,
+        other_bookings,
+        booking.start_time,
+        booking.end_time,
+        Some(request.room_number),
+        booking.guest_count(),
+        booking.room_type.as_deref(),
+    )?;
+
+    process_reassignments(&app_state, &mut tx, reassignments).await?;
+
+    let event = Event::BookingRoomPreassigned(BookingRoomPreassignedEvent {
+        booking_id,
+        room_number: request.room_number,
+        pinned: request.pinned,
+    });
+
+    // Process the event within the transaction
+    let stream_id = booking_id;
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, stream_id, event)
+        .await?;
+
+    // Commit the transaction
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "message": "Room pre-assigned successfully"
+        })),
+    )
+        .into_response())
+}
+
+pub async fn modify_booking(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+    Json(request): Json<ModifyBookingRequest>,
+) -> AppResult<Response> {
+    // Validate date range
+    if request.start_time >= request.end_time {
+        return Err(AppError::bad_request(
+            "Start time must be before end time",
+            "INVALID_DATE_RANGE",
+        ));
+    }
+
+    // Start a database transaction
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Get the booking and verify it exists and is in confirmed or checked-in state. It's locked,
+    // so that it can't be checked in, out or cancelled while its dates change. Changing the dates
+    // may release nights, so the hotel is locked exclusively.
+    let (booking, hotel) = get_and_lock_booking_and_hotel(&mut tx, booking_id, true).await?;
+    let rooms = get_hotel_rooms(&mut *tx, &hotel).await?;
+
+    match booking.status {
+        BookingStatus::Confirmed => {
+            // The new dates are a new stay, so the hotel must take bookings and its rules apply
+            ensure_hotel_open(&hotel)?;
+            enforce_stay_restrictions(&mut *tx, hotel.id, request.start_time, request.end_time)
+                .await?;
+        }
+        BookingStatus::CheckedIn => {
+            // The guest has already arrived, so only the departure date can change
+            if request.start_time != booking.start_time {
+                return Err(AppError::bad_request(
+                    "Cannot change the arrival date of a checked-in booking",
+                    "INVALID_DATE_CHANGE",
+                ));
+            }
+            // Nights which have passed can't be given back
+            if request.end_time <= hotel_today(&hotel) {
+                return Err(AppError::bad_request(
+                    "The stay of a checked-in guest must end after today",
+                    "INVALID_DATE_CHANGE",
+                ));
+            }
+            // The arrival rules were met at booking time, only the new departure is checked
+            let restrictions =
+                db::get_stay_restrictions(&mut *tx, hotel.id, request.end_time, request.end_time)
+                    .await?;
+            check_departure_restrictions(&restrictions, request.end_time)?;
+        }
+        _ => {
+            return Err(AppError::bad_request(
+                "Booking must be in confirmed or checked-in state to modify",
+                "INVALID_BOOKING_STATUS",
+            ));
+        }
+    }
+
+    // Re-run the availability check for the new dates, without the booking itself
+    let other_bookings: Vec<_> = get_and_lock_bookings_for_repacking(
+        &mut tx,
+        booking.hotel_id,
+        request.start_time,
+        request.end_time,
+    )
+    .await?
+    .into_iter()
+    .filter(|b| b.id != booking_id)
+    .collect();
+
+    // Extending a stay must not overbook the hotel, but a stay which only shrinks takes no nights
+    // it didn't already hold, so it may keep using the hotel's overbooking allowance
+    let allowance =
+        if request.start_time >= booking.start_time && request.end_time <= booking.end_time {
+            hotel.overbooking_allowance()
+        } else {
+            0
+        };
+
+    // If a room is (pre-)assigned, the guest keeps it, so it must stay free for the new dates
+    let reassignments = if booking.room_number.is_none()
+        && can_accommodate_party(
+            &rooms,
+            allowance,
+            other_bookings.clone(),
+            request.start_time,
+            request.end_time,
+            booking.guest_count(),
+            booking.room_type.as_deref(),
+        ) {
+        vec![]
+    } else {
+        plan_room_repacking(
+            &rooms,
+            other_bookings,
+            request.start_time,
+            request.end_time,
+            booking.room_number,
+            booking.guest_count(),
+            booking.room_type.as_deref(),
+        )
+        .map_err(|e| match e {
+            AppError::BadRequest { code, .. }
+                if booking.status == BookingStatus::CheckedIn && code == "ROOM_NOT_AVAILABLE" =>
+            {
+                AppError::bad_request(
+                    "The guest's current room is not available for the extra nights",
+                    "ROOM_NOT_AVAILABLE",
+                )
+            }
+            e => e,
+        })?
+    };
+
+    process_reassignments(&app_state, &mut tx, reassignments).await?;
+
+    // The new stay is priced with the current rates of the booking's room type, keeping the terms
+    // of the promo code it was booked with
+    let mut quote = match &booking.quote {
+        Some(quote) => {
+            quote_stay(
+                &mut tx,
+                booking.hotel_id,
+                &quote.room_type,
+                request.start_time,
+                request.end_time,
+                booking.guest_count(),
+                quote.promo.as_ref().map(|p| (p.code.as_str(), &p.discount)),
+            )
+            .await?
+        }
+        None => None,
+    };
+    // The price in the guest's currency stays at the rate the booking was made at
+    let converted = booking.quote.as_ref().and_then(|q| q.converted.as_ref());
+    if let (Some(quote), Some(converted)) = (quote.as_mut(), converted) {
+        convert_quote(quote, &converted.currency, converted.exchange_rate);
+    }
+
+    let event = Event::BookingDatesChanged(BookingDatesChangedEvent {
+        booking_id,
+        start_time: request.start_time,
+        end_time: request.end_time,
+        quote,
+    });
+
+    // Process the event within the transaction
+    let stream_id = booking_id;
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, stream_id, event)
+        .await?;
+
+    // Commit the transaction
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "message": "Booking modified successfully"
+        })),
+    )
+        .into_response())
+}
+
+pub async fn checkin_booking(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+    Query(params): Query<CheckinQueryParams>,
+) -> AppResult<Response> {
+    // Start a database transaction
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Get the booking and verify it exists and is in confirmed state
+    let (booking, _) = get_and_lock_booking_and_hotel(&mut tx, booking_id, false).await?;
+
+    // Verify booking is in confirmed state
+    if booking.status != BookingStatus::Confirmed {
+        return Err(AppError::bad_request(
+            "Booking must be in confirmed state to check in",
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+
+    let (assigned_room, preferences) =
+        check_in_booking(&app_state, &mut tx, &booking, &params, None, Utc::now()).await?;
+
+    if params.capture_deposit
+        && let Err(e) = settle_deposits(
+            app_state.payment_provider.as_ref(),
+            &app_state.event_processor,
+            &mut tx,
+            booking_id,
+            None,
+        )
+        .await
+    {
+        return Err(payment_failed(&app_state, tx, booking_id, e).await);
+    }
+
+    // Commit the transaction
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "assigned_room": assigned_room,
+            "preferences": preferences,
+            "message": "Booking checked in successfully"
+        })),
+    )
+        .into_response())
+}
+
+pub async fn checkout_booking(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+    Query(params): Query<CheckoutQueryParams>,
+) -> AppResult<Response> {
+    let late_checkout_fee = validate_fee(params.late_checkout_fee)?;
+
+    // Start a database transaction
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Get the booking and verify it exists and is in checked-in state. The hotel is locked
+    // exclusively, as the invoice number is taken from it, and the room's housekeeping status
+    // changes.
+    let (booking, hotel) = get_and_lock_booking_and_hotel(&mut tx, booking_id, true).await?;
+
+    // Verify booking is in checked-in state
+    if booking.status != BookingStatus::CheckedIn {
+        return Err(AppError::bad_request(
+            "Booking must be in checked-in state to check out",
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+
+    // The actual departure date, at the hotel
+    let now = local_time(&hotel, Utc::now());
+    let today = now.date();
+
+    if today < booking.start_time {
+        return Err(AppError::bad_request(
+            "Cannot check out before the arrival date",
+            "INVALID_DEPARTURE_DATE",
+        ));
+    }
+
+    // Charge the nights which the night audit hasn't charged yet. Leaving early shortens the stay.
+    let departure_date = stay_end_at_checkout(&booking, today);
+    post_room_charges(
+        &app_state.event_processor,
+        &mut tx,
+        &booking,
+        departure_date,
+    )
+    .await?;
+
+    if is_late_checkout(&hotel, booking.end_time, now) {
+        if !params.late_checkout {
+            return Err(AppError::bad_request(
+                format!(
+                    "Check-out is until {}; confirm a late checkout to check the guest out now",
+                    hotel.check_out_time.format("%H:%M")
+                ),
+                "LATE_CHECKOUT",
+            ));
+        }
+        if let Some(fee) = late_checkout_fee {
+            post_extra(
+                &app_state.event_processor,
+                &mut tx,
+                booking_id,
+                "Late checkout",
+                fee,
+                today,
+            )
+            .await?;
+        }
+    }
+
+    // The deposits have to settle the balance, which is checked before any card is charged
+    let balance = folio_balance(&get_folio_entries(&mut *tx, booking_id).await?);
+    let open_authorizations = get_and_lock_open_authorizations(&mut tx, booking_id).await?;
+    let outstanding = balance_after_settlement(&open_authorizations, balance);
+    if outstanding != 0 && !params.override_balance {
+        return Err(AppError::bad_request(
+            format!(
+                "The folio has an outstanding balance of {}",
+                format_amount(outstanding)
+            ),
+            "BALANCE_OUTSTANDING",
+        ));
+    }
+
+    // Create the checkout event, recording when the guest actually left. Leaving before the end
+    // of the stay shortens it.
+    let event = Event::BookingCheckedOut(BookingCheckedOutEvent {
+        booking_id,
+        departure_date: Some(today),
+    });
+
+    // Process the event within the transaction
+    let stream_id = booking_id;
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, stream_id, event)
+        .await?;
+
+    // The room needs cleaning before the next guest can use it
+    if let Some(room_number) = booking.room_number {
+        let event = Event::RoomHousekeepingStatusChanged(RoomHousekeepingStatusChangedEvent {
+            hotel_id: booking.hotel_id,
+            room_number,
+            status: HousekeepingStatus::Dirty,
+        });
+        app_state
+            .event_processor
+            .process_event_with_tx(&mut tx, booking.hotel_id, event)
+            .await?;
+    }
+
+    // Settle the balance from the deposits, releasing whatever isn't needed. Cards are charged,
+    // so nothing after this may fail the checkout.
+    if let Err(e) = settle_deposits(
+        app_state.payment_provider.as_ref(),
+        &app_state.event_processor,
+        &mut tx,
+        booking_id,
+        Some(balance),
+    )
+    .await
+    {
+        return Err(payment_failed(&app_state, tx, booking_id, e).await);
+    }
+
+    // Bill the stay
+    let entries = get_folio_entries(&mut *tx, booking_id).await?;
+    let balance = folio_balance(&entries);
+    let invoice_number = get_next_invoice_number(&mut tx, hotel.id).await?;
+    let booking = Booking {
+        end_time: departure_date,
+        ..booking
+    };
+    let invoice = build_invoice(invoice_number, &hotel, &booking, &entries, Utc::now());
+    let event = Event::InvoiceIssued(InvoiceIssuedEvent { invoice });
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, booking_id, event)
+        .await?;
+
+    // Commit the transaction
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "invoice_number": invoice_number,
+            "balance": balance,
+            "message": "Booking checked out successfully"
+        })),
+    )
+        .into_response())
+}
+
+pub async fn cancel_booking(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+) -> AppResult<Response> {
+    // Start a database transaction
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Get the booking and verify it exists and is in confirmed (or held) state. Its room is
+    // released, so the hotel is locked exclusively.
+    let (booking, _) = get_and_lock_booking_and_hotel(&mut tx, booking_id, true).await?;
+
+    // Verify booking is in confirmed state; cancelling a hold releases it early
+    if booking.status != BookingStatus::Confirmed && booking.status != BookingStatus::Held {
+        return Err(AppError::bad_request(
+            "Booking must be in confirmed or held state to cancel",
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+
+    // Create the cancel event
+    let event = Event::BookingCancelled(BookingCancelledEvent { booking_id });
+
+    // Process the event within the transaction
+    let stream_id = booking_id;
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, stream_id, event)
+        .await?;
+
+    // Charged once cancelled, so that loyalty points given back are settled too
+    let fee = post_cancellation_charge(&app_state, &mut tx, &booking).await?;
+    let balance = match settle_cancellation(&app_state, &mut tx, booking_id).await {
+        Ok((_, balance)) => balance,
+        Err(e) => return Err(payment_failed(&app_state, tx, booking_id, e).await),
+    };
+
+    // Commit the transaction
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "cancellation_fee": fee,
+            "balance": balance,
+            "message": "Booking cancelled successfully"
+        })),
+    )
+        .into_response())
+}
+
+/// Shows what cancelling a booking today would cost, so that the guest knows before committing to
+/// it.
+pub async fn get_cancellation_fee(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+) -> AppResult<Response> {
+    let booking = get_booking_or_not_found(&app_state.db_pool, booking_id).await?;
+    if booking.status != BookingStatus::Confirmed && booking.status != BookingStatus::Held {
+        return Err(AppError::bad_request(
+            "Booking must be in confirmed or held state to cancel",
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+
+    // Holds are free to cancel, but show the fee that applies once the hold is confirmed
+    let hotel = get_hotel_or_not_found(&app_state.db_pool, booking.hotel_id).await?;
+    let today = hotel_today(&hotel);
+    let fee = if booking.status == BookingStatus::Held {
+        0
+    } else {
+        cancellation_fee(&booking, today)
+    };
+    let policy = booking
+        .quote
+        .as_ref()
+        .and_then(|quote| quote.cancellation_policy.as_ref());
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "booking_id": booking_id,
+            "cancellation_fee": fee,
+            "currency": booking.quote.as_ref().map(|quote| &quote.currency),
+            "cancellation_policy": policy,
+            "free_until": policy.map(|policy| free_cancellation_deadline(policy, booking.start_time)),
+            "fee_if_confirmed": cancellation_fee(&booking, today)
+        })),
+    )
+        .into_response())
+}
+
+/// Lists the nights for which more rooms are booked than the hotel has, so that guests will have
+/// to be walked unless enough of them don't show up.
+pub async fn get_overbooking_report(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Query(params): Query<AvailabilityQueryParams>,
+) -> AppResult<Response> {
+    let (from, to) = parse_availability_range(&params)?;
+
+    let hotel = get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    let bookings = get_overlapping_bookings(&app_state.db_pool, hotel_id, from, to).await?;
+    let nights = overbooked_nights(hotel.room_count, &bookings, from, to);
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "hotel_id": hotel_id,
+            "overbooking_limit": hotel.overbooking_limit,
+            "overbooked_nights": nights
+        })),
+    )
+        .into_response())
+}
+
+pub async fn update_overbooking_limit(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Json(limit): Json<OverbookingLimit>,
+) -> AppResult<Response> {
+    let valid = match limit {
+        OverbookingLimit::None => true,
+        OverbookingLimit::Rooms(rooms) => rooms >= 0,
+        OverbookingLimit::Percentage(percentage) => (0..=100).contains(&percentage),
+    };
+    if !valid {
+        return Err(AppError::bad_request(
+            "Overbooking must be limited to a non-negative number of rooms, or a percentage between 0 and 100",
+            "INVALID_OVERBOOKING_LIMIT",
+        ));
+    }
+
+    get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    update_hotel_overbooking_limit(&app_state.db_pool, hotel_id, limit).await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "message": "Overbooking limit updated successfully"
+        })),
+    )
+        .into_response())
+}
+
+/// Relocates a confirmed guest to another hotel, when there's no room left for them because the
+/// hotel is overbooked.
+pub async fn walk_guest(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+    Json(request): Json<WalkGuestRequest>,
+) -> AppResult<Response> {
+    let mut tx = app_state.db_pool.begin().await?;
+
+    // Walking the guest releases their nights, so the hotel is locked exclusively. This also keeps
+    // the hotel's bookings from changing while it's checked for being overbooked.
+    let (booking, hotel) = get_and_lock_booking_and_hotel(&mut tx, booking_id, true).await?;
+
+    if booking.status != BookingStatus::Confirmed {
+        return Err(AppError::bad_request(
+            "Booking must be in confirmed state to walk the guest",
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+
+    // Only guests arriving on an overbooked night can be walked; otherwise there's a room for them
+    let (arrival, next_day) = (booking.start_time, booking.start_time + Duration::days(1));
+    let bookings = get_overlapping_bookings(&mut *tx, hotel.id, arrival, next_day).await?;
+    if overbooked_nights(hotel.room_count, &bookings, arrival, next_day).is_empty() {
+        return Err(AppError::bad_request(
+            "The hotel isn't overbooked on the guest's arrival night",
+            "NOT_OVERBOOKED",
+        ));
+    }
+
+    let event = Event::BookingWalked(BookingWalkedEvent {
+        booking_id,
+        relocated_to: request.relocated_to,
+    });
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, booking_id, event)
+        .await?;
+
+    // The hotel couldn't give the guest a room, so they owe nothing and their deposits are released
+    if let Err(e) = settle_deposits(
+        app_state.payment_provider.as_ref(),
+        &app_state.event_processor,
+        &mut tx,
+        booking_id,
+        Some(0),
+    )
+    .await
+    {
+        return Err(payment_failed(&app_state, tx, booking_id, e).await);
+    }
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "message": "Guest walked successfully"
+        })),
+    )
+        .into_response())
+}
+
+async fn get_booking_or_not_found<'a, E>(executor: E, booking_id: i64) -> AppResult<Booking>
+where
+    E: Executor<'a, Database = Postgres>,
+{
+    match get_booking_by_id(executor, booking_id).await? {
+        Some(booking) => Ok(booking),
+        None => Err(AppError::not_found("Booking not found")),
+    }
+}
+
+pub async fn get_folio(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+) -> AppResult<Response> {
+    let booking = get_booking_or_not_found(&app_state.db_pool, booking_id).await?;
+    let hotel = get_hotel_or_not_found(&app_state.db_pool, booking.hotel_id).await?;
+    let entries = get_folio_entries(&app_state.db_pool, booking_id).await?;
+
+    let folio = Folio {
+        booking_id,
+        currency: hotel.base_currency,
+        balance: folio_balance(&entries),
+        entries,
+    };
+
+    Ok((StatusCode::OK, ResponseJson(folio)).into_response())
+}
+
+/// Posts an extra charge, a payment or an adjustment to a booking's folio. Payments are given as
+/// positive amounts, and credited to the guest.
+pub async fn post_folio_entry(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+    Json(request): Json<PostFolioEntryRequest>,
+) -> AppResult<Response> {
+    let amount = match request.kind {
+        FolioEntryKind::Extra | FolioEntryKind::Payment if request.amount <= 0 => {
+            return Err(AppError::bad_request(
+                "Extra charges and payments must have a positive amount",
+                "INVALID_FOLIO_ENTRY",
+            ));
+        }
+        FolioEntryKind::Adjustment if request.amount == 0 => {
+            return Err(AppError::bad_request(
+                "Adjustments can't be zero",
+                "INVALID_FOLIO_ENTRY",
+            ));
+        }
+        FolioEntryKind::RoomCharge | FolioEntryKind::CancellationFee | FolioEntryKind::Tax => {
+            return Err(AppError::bad_request(
+                "Room charges, taxes and cancellation fees are posted automatically",
+                "INVALID_FOLIO_ENTRY",
+            ));
+        }
+        FolioEntryKind::Payment => -request.amount,
+        FolioEntryKind::Extra | FolioEntryKind::Adjustment => request.amount,
+    };
+
+    if request.description.trim().is_empty() {
+        return Err(AppError::bad_request(
+            "A description is required",
+            "INVALID_FOLIO_ENTRY",
+        ));
+    }
+
+    let mut tx = app_state.db_pool.begin().await?;
+
+    let booking = get_booking_or_not_found(&mut *tx, booking_id).await?;
+    if !matches!(
+        booking.status,
+        BookingStatus::Confirmed | BookingStatus::CheckedIn
+    ) {
+        return Err(AppError::bad_request(
+            "Only confirmed and checked-in bookings can be charged",
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+
+    let date = match request.date {
+        Some(date) => date,
+        None => hotel_today(&get_hotel_or_not_found(&mut *tx, booking.hotel_id).await?),
+    };
+
+    let entry_id = get_next_folio_entry_id(&mut tx).await?;
+    let event = Event::FolioEntryPosted(FolioEntryPostedEvent {
+        entry_id,
+        booking_id,
+        kind: request.kind,
+        description: request.description,
+        amount,
+        date,
+        posted_at: Utc::now(),
+    });
+    app_state
+        .event_processor
+        .process_event_with_tx(&mut tx, booking_id, event)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        ResponseJson(json!({
+            "entry_id": entry_id,
+            "message": "Folio entry posted successfully"
+        })),
+    )
+        .into_response())
+}
+
+pub async fn get_payments(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+) -> AppResult<Response> {
+    get_booking_or_not_found(&app_state.db_pool, booking_id).await?;
+    let authorizations = get_payment_authorizations(&app_state.db_pool, booking_id).await?;
+
+    Ok((StatusCode::OK, ResponseJson(authorizations)).into_response())
+}
+
+/// Shows a guest's loyalty points balance, with the transactions behind it (latest first).
+pub async fn get_loyalty_account(
+    State(app_state): State<AppState>,
+    Path(guest_id): Path<i64>,
+) -> AppResult<Response> {
+    let Some(balance) = get_loyalty_balance(&app_state.db_pool, guest_id).await? else {
+        return Err(AppError::not_found("Guest not found"));
+    };
+    let transactions = get_loyalty_transactions_by_guest_id(&app_state.db_pool, guest_id).await?;
+
+    Ok((
+        StatusCode::OK,
+        ResponseJson(json!({
+            "guest_id": guest_id,
+            "balance": balance,
+            "transactions": transactions
+        })),
+    )
+        .into_response())
+}
+
+/// Pays for (part of) a booking with the loyalty points of its guest. The points are given back if
+/// the booking is cancelled.
+pub async fn redeem_loyalty_points(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+    Json(request): Json<RedeemLoyaltyPointsRequest>,
+) -> AppResult<Response> {
+    if request.points <= 0 {
+        return Err(AppError::bad_request(
+            "The number of points must be positive",
+            "INVALID_POINTS",
+        ));
+    }
+
+    let mut tx = app_state.db_pool.begin().await?;
+
+    let (booking, hotel) = get_and_lock_booking_and_hotel(&mut tx, booking_id, false).await?;
+    if !matches!(
+        booking.status,
+        BookingStatus::Confirmed | BookingStatus::CheckedIn
+    ) {
+        return Err(AppError::bad_request(
+            "Points can only be redeemed for confirmed and checked-in bookings",
+            "INVALID_BOOKING_STATUS",
+        ));
+    }
+    let Some(guest_id) = booking.guest_id else {
+        return Err(AppError::bad_request(
+            "The booking isn't linked to a guest profile",
+            "NO_GUEST_PROFILE",
+        ));
+    };
+
+    let balance = get_and_lock_loyalty_balance(&mut tx, guest_id)
+        .await?
+        .unwrap_or(0);
+    if request.points > balance {
+        return Err(AppError::bad_request(
+            format!(
+                "The guest only has {} points, {} were requested",
+                balance, request.points
+            ),
+            "INSUFFICIENT_POINTS",
+        ));
+    }
+
+    let Some(rate) = find_exchange_rate(&mut tx, POINTS_CURRENCY, &hotel.base_currency).await?
+    else {
+        return Err(AppError::bad_request(
+            format!(
+                "There is no exchange rate from {} to {}",
+                POINTS_CURRENCY, hotel.base_currency
+            ),
+            "NO_EXCHANGE_RATE",
+        ));
+    };
+    let amount = points_value(request.points, rate);
+
+    let entries = get_folio_entries(&mut *tx, booking_id).await?;
+    if amount > redeemable_amount(&booking, &entries) {
+        return Err(AppError::bad_request(
+            "The points are worth more than what's left to pay for the booking",
+            "REDEMPTION_EXCEEDS_BALANCE",
+        ));
+    }
+
+    redeem_points(
+        &app_state.event_processor,
+        &mut tx,
+        guest_id,
+        booking_id,
+        request.points,
+        amount,
+    )
+    .await?;
+
+    tx.commit().await?;
+
+    Ok((
+        StatusCode::CREATED,
+        ResponseJson(json!({
+            "points": request.points,
+            "amount": amount,
+            "balance": balance - request.points,
+            "message": "Loyalty points redeemed successfully"
+        })),
+    )
+        .into_response())
+}
+
+/// Gets the invoice issued at checkout, as JSON or as printable text.
+pub async fn get_invoice(
+    State(app_state): State<AppState>,
+    Path(booking_id): Path<i64>,
+    Query(params): Query<InvoiceQueryParams>,
+) -> AppResult<Response> {
+    let Some(invoice) = get_invoice_by_booking_id(&app_state.db_pool, booking_id).await? else {
+        return Err(AppError::not_found("Invoice not found"));
+    };
+
+    match params.format.as_deref() {
+        None | Some("json") => Ok((StatusCode::OK, ResponseJson(invoice)).into_response()),
+        Some("text") => Ok((
+            StatusCode::OK,
+            [(header::CONTENT_TYPE, "text/plain; charset=utf-8")],
+            render_invoice_text(&invoice),
+        )
+            .into_response()),
+        Some(format) => Err(AppError::bad_request(
+            format!("Unknown invoice format '{}'. Use json or text", format),
+            "INVALID_FORMAT",
+        )),
+    }
+}
+
+pub async fn get_rate_plans(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+) -> AppResult<Response> {
+    get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    let rate_plans = get_rate_plans_by_hotel_id(&app_state.db_pool, hotel_id).await?;
+
+    Ok((StatusCode::OK, ResponseJson(rate_plans)).into_response())
+}
+
+/// Sets the rate plan of a room type. Existing bookings keep the price they were quoted.
+pub async fn update_rate_plan(
+    State(app_state): State<AppState>,
+    Path((hotel_id, room_type)): Path<(i64, String)>,
+    Json(request): Json<RatePlanRequest>,
+) -> AppResult<Response> {
+    let invalid = |message: &str| Err(AppError::bad_request(message, "INVALID_RATE_PLAN"));
+
+    if request.base_rate < 0 || request.seasons.iter().any(|s| s.nightly_rate < 0) {
+        return invalid("Rates can't be negative");
+    }
+    if request.weekend_surcharge_percent < -100 {
+        return invalid("The weekend surcharge can't be less than -100%");
+    }
+    if request.seasons.iter().any(|s| s.start_date >= s.end_date) {
+        return invalid("Seasons must start before they end");
+    }
+    if request
+        .length_of_stay_discounts
+        .iter()
+        .any(|d| d.min_nights < 1 || !(0..=100).contains(&d.percent))
+    {
+        return invalid(
+            "Length of stay discounts need at least one night, and a percentage from 0 to 100",
+        );
+    }
+    if let Some(policy) = &request.cancellation_policy {
+        let valid_penalty = match policy.penalty {
+            CancellationPenalty::Percentage(percent) => (0..=100).contains(&percent),
+            CancellationPenalty::Nights(nights) => nights >= 1,
+        };
+        if policy.free_until_days_before < 0 || !valid_penalty {
+            return invalid(
+                "Cancellation penalties need a non-negative number of days, and either a percentage from 0 to 100 or at least one night",
+            );
+        }
+    }
+
+    // Folios are kept in the hotel's base currency, so stays have to be priced in it
+    let hotel = get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    let currency = request.currency.unwrap_or(hotel.base_currency.clone());
+    if currency != hotel.base_currency {
+        return invalid(&format!(
+            "Rate plans must be in the hotel's base currency, {}",
+            hotel.base_currency
+        ));
+    }
+
+    let mut rate_plan = RatePlan {
+        id: 0,
+        hotel_id,
+        room_type,
+        name: request.name,
+        currency,
+        base_rate: request.base_rate,
+        weekend_surcharge_percent: request.weekend_surcharge_percent,
+        seasons: request.seasons,
+        length_of_stay_discounts: request.length_of_stay_discounts,
+        cancellation_policy: request.cancellation_policy,
+    };
+    rate_plan.id = upsert_rate_plan(&app_state.db_pool, &rate_plan).await?;
+
+    Ok((StatusCode::OK, ResponseJson(rate_plan)).into_response())
+}
+
+/// Prices a stay without booking it.
+pub async fn get_quote(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Query(params): Query<QuoteQueryParams>,
+) -> AppResult<Response> {
+    let from = parse_date_param(&params.from, "from")?;
+    let to = parse_date_param(&params.to, "to")?;
+    if from >= to {
+        return Err(AppError::bad_request(
+            "'from' must be before 'to'",
+            "INVALID_DATE_RANGE",
+        ));
+    }
+
+    let hotel = get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    ensure_hotel_open(&hotel)?;
+    let room_type = params.room_type.as_deref().unwrap_or(DEFAULT_ROOM_TYPE);
+
+    let mut conn = app_state.db_pool.acquire().await?;
+    let guests = params.adults + params.children;
+    let mut quote = quote_stay(&mut conn, hotel_id, room_type, from, to, guests, None).await?;
+    if quote.is_none() {
+        return Err(AppError::bad_request(
+            format!("The hotel has no rate plan for room type '{}'", room_type),
+            "NO_RATE_PLAN",
+        ));
+    }
+    convert_for_guest(&mut conn, &mut quote, params.currency.as_deref()).await?;
+
+    Ok((StatusCode::OK, ResponseJson(quote)).into_response())
+}
+
+pub async fn get_exchange_rates(State(app_state): State<AppState>) -> AppResult<Response> {
+    let rates = get_all_exchange_rates(&app_state.db_pool).await?;
+
+    Ok((StatusCode::OK, ResponseJson(rates)).into_response())
 }
 
-pub async fn create_booking(
+/// Sets the rate for converting prices from one currency into another. Bookings keep the rate
+/// they were quoted at.
+pub async fn update_exchange_rate(
     State(app_state): State<AppState>,
-    Path(hotel_id): Path<i64>,
-    Json(request): Json<CreateBookingRequest>,
+    Path((from_currency, to_currency)): Path<(String, String)>,
+    Json(request): Json<UpdateExchangeRateRequest>,
 ) -> AppResult<Response> {
-    // Validate date range
-    if request.start_time >= request.end_time {
+    if !is_currency_code(&from_currency) || !is_currency_code(&to_currency) {
         return Err(AppError::bad_request(
-            "Start time must be before end time",
-            "INVALID_DATE_RANGE",
+            "Currencies must be three-letter ISO 4217 codes",
+            "INVALID_CURRENCY",
+        ));
+    }
+    if from_currency == to_currency || request.rate <= 0 {
+        return Err(AppError::bad_request(
+            "Exchange rates must be positive, and between two different currencies",
+            "INVALID_EXCHANGE_RATE",
         ));
     }
 
-    // Start a single database transaction for the entire operation
-    let mut tx = app_state.db_pool.begin().await?;
+    let rate = ExchangeRate {
+        from_currency,
+        to_currency,
+        rate: request.rate,
+        updated_at: Utc::now(),
+    };
+    upsert_exchange_rate(&app_state.db_pool, &rate).await?;
 
-    // First, get hotel info to check room count within the transaction
-    let hotel = get_hotel_or_not_found(&mut *tx, hotel_id).await?;
+    Ok((StatusCode::OK, ResponseJson(rate)).into_response())
+}
 
-    // Check room availability within the transaction
-    // Using SELECT ... FOR UPDATE so that it's not possible to concurrently add overlapping bookings,
-    // which might use stale data to be used to verify booking possibility (write skew).
-    let overlapping_bookings =
-        get_and_lock_overlapping_bookings(&mut tx, hotel_id, request.start_time, request.end_time)
-            .await?;
+pub async fn get_promo_codes(State(app_state): State<AppState>) -> AppResult<Response> {
+    let promo_codes = get_all_promo_codes(&app_state.db_pool).await?;
 
-    if !can_accommodate_booking(
-        hotel.room_count,
-        overlapping_bookings,
-        request.start_time,
-        request.end_time,
-    ) {
-        return Err(AppError::bad_request(
-            "No rooms available for the requested dates",
-            "NO_ROOMS_AVAILABLE",
-        ));
-    }
+    Ok((StatusCode::OK, ResponseJson(promo_codes)).into_response())
+}
 
-    // Generate booking ID within the transaction
-    let booking_id = get_next_booking_id(&mut tx).await?;
+pub async fn create_promo_code(
+    State(app_state): State<AppState>,
+    Json(request): Json<CreatePromoCodeRequest>,
+) -> AppResult<Response> {
+    let invalid = |message: &str| Err(AppError::bad_request(message, "INVALID_PROMO_CODE"));
 
-    // Create the booking event
-    let event = Event::BookingCreated(BookingCreatedEvent {
-        booking_id,
-        hotel_id,
-        guest_name: request.guest_name,
-        start_time: request.start_time,
-        end_time: request.end_time,
-    });
+    let code = normalize_promo_code(&request.code);
+    if code.is_empty() || !code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return invalid("Promo codes must consist of letters, digits and dashes");
+    }
+    match &request.discount {
+        PromoDiscount::Percentage(percent) if !(0..=100).contains(percent) => {
+            return invalid("Percentage discounts must be from 0 to 100");
+        }
+        PromoDiscount::Fixed { amount, currency } if *amount < 0 || !is_currency_code(currency) => {
+            return invalid(
+                "Fixed discounts can't be negative, and need a three-letter ISO 4217 currency code",
+            );
+        }
+        _ => {}
+    }
+    if let (Some(from), Some(until)) = (request.valid_from, request.valid_until)
+        && from > until
+    {
+        return invalid("The validity window must start before it ends");
+    }
+    if request.max_uses.is_some_and(|max_uses| max_uses < 0) {
+        return invalid("The usage limit can't be negative");
+    }
 
-    // Process the event within the existing transaction
-    let stream_id = booking_id; // Use booking_id as stream_id
-    app_state
-        .event_processor
-        .process_event_with_tx(&mut tx, stream_id, event)
-        .await?;
+    if let Some(hotel_id) = request.hotel_id {
+        get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    }
 
-    // Commit the transaction
-    tx.commit().await?;
+    let promo = PromoCode {
+        code,
+        hotel_id: request.hotel_id,
+        discount: request.discount,
+        valid_from: request.valid_from,
+        valid_until: request.valid_until,
+        max_uses: request.max_uses,
+        used_count: 0,
+    };
+    if !insert_promo_code(&app_state.db_pool, &promo).await? {
+        return Err(AppError::bad_request(
+            format!("Promo code {} already exists", promo.code),
+            "PROMO_CODE_EXISTS",
+        ));
+    }
 
-    Ok((
-        StatusCode::CREATED,
-        ResponseJson(json!({
-            "booking_id": booking_id,
-            "message": "Booking created successfully"
-        })),
-    )
-        .into_response())
+    Ok((StatusCode::CREATED, ResponseJson(promo)).into_response())
 }
 
-pub async fn get_hotel(
+pub async fn get_tax_rules(
     State(app_state): State<AppState>,
-    Path(id): Path<i64>,
+    Path(hotel_id): Path<i64>,
 ) -> AppResult<Response> {
-    let hotel = get_hotel_or_not_found(&app_state.db_pool, id).await?;
-    Ok((StatusCode::OK, ResponseJson(hotel)).into_response())
+    get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    let rules = db::get_tax_rules(&app_state.db_pool, hotel_id).await?;
+
+    Ok((StatusCode::OK, ResponseJson(rules)).into_response())
 }
 
-pub async fn checkin_booking(
+/// Adds a tax or fee to the prices of a hotel. Existing bookings keep the taxes they were quoted.
+pub async fn create_tax_rule(
     State(app_state): State<AppState>,
-    Path(booking_id): Path<i64>,
-    Query(params): Query<CheckinQueryParams>,
+    Path(hotel_id): Path<i64>,
+    Json(request): Json<CreateTaxRuleRequest>,
 ) -> AppResult<Response> {
-    // Parse the today date from query parameter
-    let today = NaiveDate::parse_from_str(&params.today, "%Y-%m-%d").map_err(|_| {
-        AppError::bad_request(
-            "Invalid date format for 'today'. Use YYYY-MM-DD",
-            "INVALID_DATE_FORMAT",
-        )
-    })?;
-
-    // Start a database transaction
-    let mut tx = app_state.db_pool.begin().await?;
+    if request.name.trim().is_empty() {
+        return Err(AppError::bad_request(
+            "A name is required",
+            "INVALID_TAX_RULE",
+        ));
+    }
 
-    // Get the booking and verify it exists and is in confirmed state
-    let booking = match get_booking_by_id(&mut *tx, booking_id).await? {
-        Some(booking) => booking,
-        None => return Err(AppError::not_found("Booking not found")),
+    let valid = match request.charge {
+        TaxCharge::Rate(basis_points) => (0..=10_000).contains(&basis_points),
+        TaxCharge::PerPersonPerNight(amount) => amount >= 0,
     };
-
-    // Verify booking is in confirmed state
-    if booking.status != BookingStatus::Confirmed {
+    if !valid {
         return Err(AppError::bad_request(
-            "Booking must be in confirmed state to check in",
-            "INVALID_BOOKING_STATUS",
+            "Tax rates must be from 0 to 10000 basis points, and amounts can't be negative",
+            "INVALID_TAX_RULE",
         ));
     }
 
-    // Get hotel info for room assignment
-    let hotel = get_hotel_or_not_found(&mut *tx, booking.hotel_id).await?;
+    get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
 
-    // Get bookings for today and filter for active bookings with assigned rooms
-    let all_bookings =
-        get_bookings_by_hotel_id_and_date(&app_state.db_pool, booking.hotel_id, today).await?;
-    let active_bookings: Vec<_> = all_bookings
-        .into_iter()
-        .filter(|b| b.status == BookingStatus::CheckedIn && b.room_number.is_some())
-        .collect();
+    let mut rule = TaxRule {
+        id: 0,
+        hotel_id,
+        name: request.name,
+        charge: request.charge,
+    };
+    rule.id = insert_tax_rule(&app_state.db_pool, &rule).await?;
 
-    // Assign a room using the room assignment algorithm
-    let assigned_room = assign_room_for_checkin(hotel.room_count, active_bookings, &booking)
-        .ok_or_else(|| {
-            AppError::bad_request("No available rooms for check-in", "NO_ROOMS_AVAILABLE")
-        })?;
+    Ok((StatusCode::CREATED, ResponseJson(rule)).into_response())
+}
 
-    // Create the checkin event with assigned room
-    let event = Event::BookingCheckedIn(BookingCheckedInEvent {
-        booking_id,
-        assigned_room,
-    });
+pub async fn delete_tax_rule(
+    State(app_state): State<AppState>,
+    Path((hotel_id, tax_rule_id)): Path<(i64, i64)>,
+) -> AppResult<Response> {
+    if !db::delete_tax_rule(&app_state.db_pool, hotel_id, tax_rule_id).await? {
+        return Err(AppError::not_found("Tax rule not found"));
+    }
 
-    // Process the event within the transaction
-    let stream_id = booking_id; // Use booking_id as stream_id
-    app_state
-        .event_processor
-        .process_event_with_tx(&mut tx, stream_id, event)
-        .await?;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
 
-    // Commit the transaction
-    tx.commit().await?;
+pub async fn get_stay_restrictions(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+    Query(params): Query<AvailabilityQueryParams>,
+) -> AppResult<Response> {
+    let (from, to) = parse_availability_range(&params)?;
 
-    Ok((
-        StatusCode::OK,
-        ResponseJson(json!({
-            "message": "Booking checked in successfully"
-        })),
-    )
-        .into_response())
+    get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    let restrictions = db::get_stay_restrictions(&app_state.db_pool, hotel_id, from, to).await?;
+
+    Ok((StatusCode::OK, ResponseJson(restrictions)).into_response())
 }
 
-pub async fn checkout_booking(
+pub async fn create_stay_restriction(
     State(app_state): State<AppState>,
-    Path(booking_id): Path<i64>,
+    Path(hotel_id): Path<i64>,
+    Json(request): Json<CreateStayRestrictionRequest>,
 ) -> AppResult<Response> {
-    // Start a database transaction
-    let mut tx = app_state.db_pool.begin().await?;
+    if request.start_date >= request.end_date {
+        return Err(AppError::bad_request(
+            "Start date must be before end date",
+            "INVALID_DATE_RANGE",
+        ));
+    }
 
-    // Get the booking and verify it exists and is in checked-in state
-    let booking = match get_booking_by_id(&mut *tx, booking_id).await? {
-        Some(booking) => booking,
-        None => return Err(AppError::not_found("Booking not found")),
-    };
+    if request.min_stay.is_some_and(|nights| nights < 1) {
+        return Err(AppError::bad_request(
+            "The minimum stay must be at least one night",
+            "INVALID_RESTRICTION",
+        ));
+    }
 
-    // Verify booking is in checked-in state
-    if booking.status != BookingStatus::CheckedIn {
+    if request.min_stay.is_none() && !request.closed_to_arrival && !request.closed_to_departure {
         return Err(AppError::bad_request(
-            "Booking must be in checked-in state to check out",
-            "INVALID_BOOKING_STATUS",
+            "A restriction must set a minimum stay, or close the hotel to arrivals or departures",
+            "INVALID_RESTRICTION",
         ));
     }
 
-    // Create the checkout event
-    let event = Event::BookingCheckedOut(BookingCheckedOutEvent { booking_id });
+    get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
 
-    // Process the event within the transaction
-    let stream_id = booking_id;
-    app_state
-        .event_processor
-        .process_event_with_tx(&mut tx, stream_id, event)
-        .await?;
+    let mut restriction = StayRestriction {
+        id: 0,
+        start_date: request.start_date,
+        end_date: request.end_date,
+        days_of_week: request.days_of_week,
+        min_stay: request.min_stay,
+        closed_to_arrival: request.closed_to_arrival,
+        closed_to_departure: request.closed_to_departure,
+    };
+    restriction.id = insert_stay_restriction(&app_state.db_pool, hotel_id, &restriction).await?;
 
-    // Commit the transaction
-    tx.commit().await?;
+    Ok((StatusCode::CREATED, ResponseJson(restriction)).into_response())
+}
 
-    Ok((
-        StatusCode::OK,
-        ResponseJson(json!({
-            "message": "Booking checked out successfully"
-        })),
-    )
-        .into_response())
+pub async fn delete_stay_restriction(
+    State(app_state): State<AppState>,
+    Path((hotel_id, restriction_id)): Path<(i64, i64)>,
+) -> AppResult<Response> {
+    if !db::delete_stay_restriction(&app_state.db_pool, hotel_id, restriction_id).await? {
+        return Err(AppError::not_found("Stay restriction not found"));
+    }
+
+    Ok(StatusCode::NO_CONTENT.into_response())
 }
 
-pub async fn cancel_booking(
+/// Lists checked-in guests who should have left before today.
+pub async fn get_overstays(
     State(app_state): State<AppState>,
-    Path(booking_id): Path<i64>,
+    Path(hotel_id): Path<i64>,
+) -> AppResult<Response> {
+    let hotel = get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    let today = hotel_today(&hotel);
+
+    let overstays: Vec<Overstay> = get_overstaying_bookings(&app_state.db_pool, hotel_id, today)
+        .await?
+        .into_iter()
+        .filter_map(|booking| overstay(booking, today))
+        .collect();
+
+    Ok((StatusCode::OK, ResponseJson(overstays)).into_response())
+}
+
+pub async fn update_room_housekeeping_status(
+    State(app_state): State<AppState>,
+    Path((hotel_id, room_number)): Path<(i64, i32)>,
+    Json(request): Json<UpdateHousekeepingStatusRequest>,
 ) -> AppResult<Response> {
     // Start a database transaction
     let mut tx = app_state.db_pool.begin().await?;
 
-    // Get the booking and verify it exists and is in confirmed state
-    let booking = match get_booking_by_id(&mut *tx, booking_id).await? {
-        Some(booking) => booking,
-        None => return Err(AppError::not_found("Booking not found")),
-    };
+    // The housekeeping events of a hotel share a stream, so the hotel is locked (as on checkout)
+    // to append them one at a time, and to check the transition against the current status
+    let hotel = get_and_lock_hotel_or_not_found(&mut tx, hotel_id).await?;
+    let rooms = get_hotel_rooms(&mut *tx, &hotel).await?;
 
-    // Verify booking is in confirmed state
-    if booking.status != BookingStatus::Confirmed {
+    let room = rooms
+        .iter()
+        .find(|room| room.room_number == room_number)
+        .ok_or_else(|| AppError::not_found("Room not found"))?;
+
+    if !is_valid_transition(room.housekeeping_status, request.status) {
         return Err(AppError::bad_request(
-            "Booking must be in confirmed state to cancel",
-            "INVALID_BOOKING_STATUS",
+            format!(
+                "Cannot change housekeeping status from {} to {}",
+                room.housekeeping_status, request.status
+            ),
+            "INVALID_HOUSEKEEPING_TRANSITION",
         ));
     }
 
-    // Create the cancel event
-    let event = Event::BookingCancelled(BookingCancelledEvent { booking_id });
+    let event = Event::RoomHousekeepingStatusChanged(RoomHousekeepingStatusChangedEvent {
+        hotel_id,
+        room_number,
+        status: request.status,
+    });
 
-    // Process the event within the transaction
-    let stream_id = booking_id;
+    // Housekeeping events of all rooms in a hotel form a single stream
+    let stream_id = hotel_id;
     app_state
         .event_processor
         .process_event_with_tx(&mut tx, stream_id, event)
@@ -280,12 +3355,22 @@ pub async fn cancel_booking(
     Ok((
         StatusCode::OK,
         ResponseJson(json!({
-            "message": "Booking cancelled successfully"
+            "message": "Housekeeping status updated successfully"
         })),
     )
         .into_response())
 }
 
+pub async fn get_housekeeping_tasks(
+    State(app_state): State<AppState>,
+    Path(hotel_id): Path<i64>,
+) -> AppResult<Response> {
+    let hotel = get_hotel_or_not_found(&app_state.db_pool, hotel_id).await?;
+    let rooms = get_hotel_rooms(&app_state.db_pool, &hotel).await?;
+
+    Ok((StatusCode::OK, ResponseJson(housekeeping_tasks(&rooms))).into_response())
+}
+
 pub async fn handle_client_event(
     State(app_state): State<AppState>,
     Json(client_event): Json<ClientEvent>,
@@ -311,10 +3396,7 @@ async fn handle_offline_checkin(
     let mut tx = app_state.db_pool.begin().await?;
 
     // Get the booking
-    let booking = match get_booking_by_id(&mut *tx, booking_id).await? {
-        Some(booking) => booking,
-        None => return Err(AppError::not_found("Booking not found")),
-    };
+    let (booking, _) = get_and_lock_booking_and_hotel(&mut tx, booking_id, false).await?;
 
     // Check if booking is already checked in to the same room (idempotency)
     if booking.status == BookingStatus::CheckedIn
@@ -339,54 +3421,36 @@ async fn handle_offline_checkin(
         ));
     }
 
-    // Get hotel info to validate room number
-    let hotel = get_hotel_or_not_found(&mut *tx, booking.hotel_id).await?;
-
-    // Validate that the specified room is within the hotel's room range
-    if offline_checkin.room_number < 1 || offline_checkin.room_number > hotel.room_count {
-        return Err(AppError::bad_request(
-            "Invalid room number",
-            "INVALID_ROOM_NUMBER",
-        ));
-    }
-
-    // Get bookings for today and filter for active bookings with assigned rooms
-    let all_bookings = get_bookings_by_hotel_id_and_date(
-        &app_state.db_pool,
-        booking.hotel_id,
-        offline_checkin.today,
+    // Check in to the client-specified room (no reassignment), with the same checks as online
+    let params = CheckinQueryParams {
+        refuse_dirty_rooms: offline_checkin.refuse_dirty_rooms,
+        capture_deposit: offline_checkin.capture_deposit,
+        early_checkin: offline_checkin.early_checkin,
+        early_checkin_fee: offline_checkin.early_checkin_fee,
+    };
+    check_in_booking(
+        &app_state,
+        &mut tx,
+        &booking,
+        &params,
+        Some(offline_checkin.room_number),
+        offline_checkin.checked_in_at.unwrap_or_else(Utc::now),
     )
     .await?;
-    let active_bookings: Vec<_> = all_bookings
-        .into_iter()
-        .filter(|b| b.status == BookingStatus::CheckedIn && b.room_number.is_some())
-        .collect();
-
-    // Check if the room is already occupied
-    let is_room_occupied = active_bookings
-        .iter()
-        .any(|b| b.room_number == Some(offline_checkin.room_number));
 
-    if is_room_occupied {
-        return Err(AppError::bad_request(
-            "Room is already occupied",
-            "ROOM_OCCUPIED",
-        ));
+    if params.capture_deposit
+        && let Err(e) = settle_deposits(
+            app_state.payment_provider.as_ref(),
+            &app_state.event_processor,
+            &mut tx,
+            booking_id,
+            None,
+        )
+        .await
+    {
+        return Err(payment_failed(&app_state, tx, booking_id, e).await);
     }
 
-    // Create the checkin event with the client-specified room (no reassignment)
-    let event = Event::BookingCheckedIn(BookingCheckedInEvent {
-        booking_id,
-        assigned_room: offline_checkin.room_number,
-    });
-
-    // Process the event within the transaction using existing machinery
-    let stream_id = booking_id;
-    app_state
-        .event_processor
-        .process_event_with_tx(&mut tx, stream_id, event)
-        .await?;
-
     // Commit the transaction
     tx.commit().await?;
 
@@ -398,3 +3462,131 @@ async fn handle_offline_checkin(
     )
         .into_response())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn group_booking(id: i64, status: BookingStatus) -> Booking {
+        Booking {
+            id,
+            hotel_id: 1,
+            room_number: None,
+            room_pinned: false,
+            guest_name: format!("Guest {}", id),
+            start_time: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            end_time: NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
+            status,
+            preferences: RoomPreferences::default(),
+            hold_expires_at: None,
+            group_id: Some(1),
+            adults: 1,
+            children: 0,
+            guest_id: None,
+            quote: None,
+            room_type: None,
+        }
+    }
+
+    fn error_code(error: AppError) -> String {
+        match error {
+            AppError::BadRequest { code, .. } => code,
+            e => panic!("Expected a bad request, got {:?}", e),
+        }
+    }
+
+    #[test]
+    fn test_group_action_skips_bookings_already_handled() {
+        let bookings = vec![
+            group_booking(1, BookingStatus::Confirmed),
+            group_booking(2, BookingStatus::Cancelled),
+            group_booking(3, BookingStatus::Confirmed),
+        ];
+
+        let confirmed = confirmed_group_bookings(
+            bookings,
+            &[BookingStatus::Confirmed, BookingStatus::Cancelled],
+            "cancelled",
+        )
+        .unwrap();
+
+        assert_eq!(confirmed.iter().map(|b| b.id).collect::<Vec<_>>(), [1, 3]);
+    }
+
+    #[test]
+    fn test_group_action_is_refused_for_the_whole_group() {
+        // Once one of the guests has checked in, none of the bookings can be cancelled
+        let bookings = vec![
+            group_booking(1, BookingStatus::Confirmed),
+            group_booking(2, BookingStatus::CheckedIn),
+        ];
+        let result = confirmed_group_bookings(
+            bookings,
+            &[BookingStatus::Confirmed, BookingStatus::Cancelled],
+            "cancelled",
+        );
+        assert_eq!(error_code(result.unwrap_err()), "INVALID_BOOKING_STATUS");
+
+        // A group can be checked in around the guests who did so already, but not once some left
+        let bookings = vec![
+            group_booking(1, BookingStatus::Confirmed),
+            group_booking(2, BookingStatus::CheckedOut),
+        ];
+        let result = confirmed_group_bookings(
+            bookings,
+            &[
+                BookingStatus::Confirmed,
+                BookingStatus::CheckedIn,
+                BookingStatus::Cancelled,
+            ],
+            "checked in",
+        );
+        assert_eq!(error_code(result.unwrap_err()), "INVALID_BOOKING_STATUS");
+    }
+
+    #[test]
+    fn test_requested_room_must_fit_and_be_free() {
+        let rooms = vec![
+            Room {
+                max_occupancy: 4,
+                ..Room::with_defaults(1)
+            },
+            Room::with_defaults(2),
+            Room {
+                housekeeping_status: HousekeepingStatus::Dirty,
+                ..Room::with_defaults(3)
+            },
+        ];
+        let active_bookings = vec![Booking {
+            room_number: Some(1),
+            ..group_booking(1, BookingStatus::CheckedIn)
+        }];
+        let family = Booking {
+            adults: 2,
+            children: 1,
+            ..group_booking(2, BookingStatus::Confirmed)
+        };
+        let couple = Booking {
+            adults: 2,
+            ..group_booking(3, BookingStatus::Confirmed)
+        };
+
+        let result = validate_requested_room(&rooms, &active_bookings, &couple, 4, false);
+        assert_eq!(error_code(result.unwrap_err()), "INVALID_ROOM_NUMBER");
+        let result = validate_requested_room(&rooms, &active_bookings, &family, 2, false);
+        assert_eq!(error_code(result.unwrap_err()), "ROOM_TOO_SMALL");
+        let result = validate_requested_room(&rooms, &active_bookings, &couple, 1, false);
+        assert_eq!(error_code(result.unwrap_err()), "ROOM_OCCUPIED");
+        let result = validate_requested_room(&rooms, &active_bookings, &couple, 3, true);
+        assert_eq!(error_code(result.unwrap_err()), "ROOM_NOT_READY");
+        let suite = Booking {
+            room_type: Some("suite".to_string()),
+            ..couple.clone()
+        };
+        let result = validate_requested_room(&rooms, &active_bookings, &suite, 3, false);
+        assert_eq!(error_code(result.unwrap_err()), "INVALID_ROOM_TYPE");
+
+        let result = validate_requested_room(&rooms, &active_bookings, &couple, 3, false);
+        assert_eq!(result.unwrap(), 3);
+    }
+}
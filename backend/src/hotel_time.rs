@@ -0,0 +1,181 @@
+use crate::models::{Booking, Hotel, Overstay};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, Utc};
+
+/// The hotel's local date and time at the given instant.
+pub fn local_time(hotel: &Hotel, at: DateTime<Utc>) -> NaiveDateTime {
+    at.with_timezone(&hotel.time_zone).naive_local()
+}
+
+/// The current date at the hotel, which booking dates are compared against.
+pub fn hotel_today(hotel: &Hotel) -> NaiveDate {
+    local_time(hotel, Utc::now()).date()
+}
+
+/// The latest arrival date whose no-show cutoff has passed at the given instant. Guests due to
+/// arrive on or before it who haven't checked in are no-shows.
+pub fn last_no_show_arrival(hotel: &Hotel, at: DateTime<Utc>) -> NaiveDate {
+    let now = local_time(hotel, at);
+    if now.time() >= hotel.no_show_cutoff {
+        now.date()
+    } else {
+        now.date() - Duration::days(1)
+    }
+}
+
+/// Whether a guest arriving on the given date would be moving in before the hotel's check-in time.
+/// Guests arriving on a later day are just late.
+pub fn is_early_checkin(hotel: &Hotel, arrival: NaiveDate, now: NaiveDateTime) -> bool {
+    now.date() == arrival && now.time() < hotel.check_in_time
+}
+
+/// Whether a guest leaving on the given date would be leaving after the hotel's check-out time.
+/// Guests leaving on a later day have stayed extra nights instead.
+pub fn is_late_checkout(hotel: &Hotel, departure: NaiveDate, now: NaiveDateTime) -> bool {
+    now.date() == departure && now.time() > hotel.check_out_time
+}
+
+/// When the stay of a guest checking out on the given date ends. Leaving early shortens the stay,
+/// though it keeps at least its first night; overstaying doesn't lengthen it.
+pub fn stay_end_at_checkout(booking: &Booking, checkout_date: NaiveDate) -> NaiveDate {
+    booking
+        .end_time
+        .min(checkout_date.max(booking.start_time + Duration::days(1)))
+}
+
+/// Reports a checked-in booking as an overstay if its guest should have left before `today`.
+pub fn overstay(booking: Booking, today: NaiveDate) -> Option<Overstay> {
+    let nights_overstayed = (today - booking.end_time).num_days();
+    (nights_overstayed > 0).then_some(Overstay {
+        booking,
+        nights_overstayed,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BookingStatus, OverbookingLimit, RoomPreferences};
+    use chrono::{NaiveTime, TimeZone};
+    use chrono_tz::Tz;
+
+    fn hotel(time_zone: Tz) -> Hotel {
+        Hotel {
+            id: 1,
+            name: "Grand Hotel".to_string(),
+            room_count: 10,
+            no_show_cutoff: NaiveTime::from_hms_opt(23, 59, 0).unwrap(),
+            overbooking_limit: OverbookingLimit::None,
+            base_currency: "EUR".to_string(),
+            time_zone,
+            check_in_time: NaiveTime::from_hms_opt(15, 0, 0).unwrap(),
+            check_out_time: NaiveTime::from_hms_opt(11, 0, 0).unwrap(),
+            archived_at: None,
+        }
+    }
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, day).unwrap()
+    }
+
+    fn at(day: u32, hour: u32) -> NaiveDateTime {
+        date(day).and_hms_opt(hour, 0, 0).unwrap()
+    }
+
+    fn stay(start_day: u32, end_day: u32) -> Booking {
+        Booking {
+            id: 1,
+            hotel_id: 1,
+            room_number: Some(101),
+            room_pinned: false,
+            guest_name: "Jane Doe".to_string(),
+            start_time: date(start_day),
+            end_time: date(end_day),
+            status: BookingStatus::CheckedIn,
+            preferences: RoomPreferences::default(),
+            hold_expires_at: None,
+            group_id: None,
+            adults: 2,
+            children: 0,
+            guest_id: None,
+            quote: None,
+            room_type: None,
+        }
+    }
+
+    #[test]
+    fn test_local_time_follows_hotel_time_zone() {
+        let instant = Utc.with_ymd_and_hms(2024, 6, 1, 23, 0, 0).unwrap();
+
+        // Already the next day in Tokyo, but not yet in New York (in summer time)
+        assert_eq!(local_time(&hotel(Tz::Asia__Tokyo), instant), at(2, 8));
+        assert_eq!(
+            local_time(&hotel(Tz::America__New_York), instant).date(),
+            date(1)
+        );
+        assert_eq!(local_time(&hotel(Tz::UTC), instant).date(), date(1));
+    }
+
+    #[test]
+    fn test_no_shows_are_due_after_the_cutoff_at_the_hotel() {
+        let mut warsaw = hotel(Tz::Europe__Warsaw);
+        warsaw.no_show_cutoff = NaiveTime::from_hms_opt(18, 0, 0).unwrap();
+        let mut tokyo = hotel(Tz::Asia__Tokyo);
+        tokyo.no_show_cutoff = warsaw.no_show_cutoff;
+
+        // 17:00 in Warsaw: today's arrivals still have time, yesterday's are no-shows
+        let afternoon = Utc.with_ymd_and_hms(2024, 6, 5, 15, 0, 0).unwrap();
+        assert_eq!(last_no_show_arrival(&warsaw, afternoon), date(4));
+        // 18:30 in Warsaw: today's arrivals are past the cutoff as well
+        let evening = Utc.with_ymd_and_hms(2024, 6, 5, 16, 30, 0).unwrap();
+        assert_eq!(last_no_show_arrival(&warsaw, evening), date(5));
+
+        // The cutoff is in the hotel's time: it's already 19:00 in Tokyo at 12:00 in Warsaw
+        let noon_in_warsaw = Utc.with_ymd_and_hms(2024, 6, 5, 10, 0, 0).unwrap();
+        assert_eq!(last_no_show_arrival(&tokyo, noon_in_warsaw), date(5));
+        assert_eq!(last_no_show_arrival(&warsaw, noon_in_warsaw), date(4));
+    }
+
+    #[test]
+    fn test_leaving_early_shortens_the_stay() {
+        let booking = stay(5, 10);
+
+        assert_eq!(stay_end_at_checkout(&booking, date(10)), date(10));
+        assert_eq!(stay_end_at_checkout(&booking, date(7)), date(7));
+        // Leaving on the day of arrival still counts as the first night
+        assert_eq!(stay_end_at_checkout(&booking, date(5)), date(6));
+        // Overstayed nights aren't added to the stay
+        assert_eq!(stay_end_at_checkout(&booking, date(12)), date(10));
+    }
+
+    #[test]
+    fn test_overstays_count_nights_past_departure() {
+        assert_eq!(
+            overstay(stay(5, 10), date(12)).map(|o| o.nights_overstayed),
+            Some(2)
+        );
+        // Guests leaving today haven't overstayed yet
+        assert!(overstay(stay(5, 10), date(10)).is_none());
+        assert!(overstay(stay(5, 10), date(8)).is_none());
+    }
+
+    #[test]
+    fn test_early_checkin_is_before_check_in_time_on_arrival() {
+        let hotel = hotel(Tz::Europe__Warsaw);
+
+        assert!(is_early_checkin(&hotel, date(5), at(5, 9)));
+        assert!(!is_early_checkin(&hotel, date(5), at(5, 15)));
+        // Late arrivals aren't early, even in the morning
+        assert!(!is_early_checkin(&hotel, date(5), at(6, 9)));
+    }
+
+    #[test]
+    fn test_late_checkout_is_after_check_out_time_on_departure() {
+        let hotel = hotel(Tz::Europe__Warsaw);
+
+        assert!(!is_late_checkout(&hotel, date(8), at(8, 10)));
+        assert!(is_late_checkout(&hotel, date(8), at(8, 14)));
+        // Leaving early, or after overstaying, isn't a late checkout
+        assert!(!is_late_checkout(&hotel, date(8), at(7, 14)));
+        assert!(!is_late_checkout(&hotel, date(8), at(9, 14)));
+    }
+}
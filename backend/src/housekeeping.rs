@@ -0,0 +1,114 @@
+use crate::models::{HousekeepingStatus, Room};
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HousekeepingTaskKind {
+    Clean,
+    FinishCleaning,
+    Inspect,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HousekeepingTask {
+    pub room_number: i32,
+    pub floor: i32,
+    pub status: HousekeepingStatus,
+    pub task: HousekeepingTaskKind,
+}
+
+/// Checks whether housekeeping may move a room from one status to another. Rooms go through
+/// dirty -> cleaning -> clean -> inspected (starting the cleaning might not be reported), and
+/// any room can be marked as dirty again, e.g. when it fails an inspection.
+pub fn is_valid_transition(from: HousekeepingStatus, to: HousekeepingStatus) -> bool {
+    use HousekeepingStatus::*;
+
+    from != to
+        && matches!(
+            (from, to),
+            (_, Dirty) | (Dirty, Cleaning) | (Dirty | Cleaning, Clean) | (Clean, Inspected)
+        )
+}
+
+/// Lists the work left for housekeepers: the rooms which aren't inspected yet, dirty rooms
+/// first, then by floor and room number.
+pub fn housekeeping_tasks(rooms: &[Room]) -> Vec<HousekeepingTask> {
+    let mut tasks: Vec<HousekeepingTask> = rooms
+        .iter()
+        .filter_map(|room| {
+            let task = match room.housekeeping_status {
+                HousekeepingStatus::Dirty => HousekeepingTaskKind::Clean,
+                HousekeepingStatus::Cleaning => HousekeepingTaskKind::FinishCleaning,
+                HousekeepingStatus::Clean => HousekeepingTaskKind::Inspect,
+                HousekeepingStatus::Inspected => return None,
+            };
+            Some(HousekeepingTask {
+                room_number: room.room_number,
+                floor: room.floor,
+                status: room.housekeeping_status,
+                task,
+            })
+        })
+        .collect();
+
+    tasks.sort_by_key(|task| (task.status, task.floor, task.room_number));
+    tasks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use HousekeepingStatus::*;
+
+    fn room(room_number: i32, floor: i32, housekeeping_status: HousekeepingStatus) -> Room {
+        Room {
+            floor,
+            housekeeping_status,
+            ..Room::with_defaults(room_number)
+        }
+    }
+
+    #[test]
+    fn test_valid_transitions() {
+        assert!(is_valid_transition(Dirty, Cleaning));
+        assert!(is_valid_transition(Dirty, Clean));
+        assert!(is_valid_transition(Cleaning, Clean));
+        assert!(is_valid_transition(Clean, Inspected));
+        assert!(is_valid_transition(Inspected, Dirty));
+        assert!(is_valid_transition(Clean, Dirty));
+    }
+
+    #[test]
+    fn test_invalid_transitions() {
+        assert!(!is_valid_transition(Dirty, Inspected));
+        assert!(!is_valid_transition(Cleaning, Inspected));
+        assert!(!is_valid_transition(Inspected, Clean));
+        assert!(!is_valid_transition(Dirty, Dirty));
+    }
+
+    #[test]
+    fn test_housekeeping_tasks_order() {
+        let rooms = vec![
+            room(1, 1, Clean),
+            room(2, 2, Dirty),
+            room(3, 1, Inspected),
+            room(4, 1, Dirty),
+            room(5, 1, Cleaning),
+        ];
+
+        let tasks: Vec<_> = housekeeping_tasks(&rooms)
+            .into_iter()
+            .map(|task| (task.room_number, task.task))
+            .collect();
+
+        assert_eq!(
+            tasks,
+            vec![
+                (4, HousekeepingTaskKind::Clean),
+                (2, HousekeepingTaskKind::Clean),
+                (5, HousekeepingTaskKind::FinishCleaning),
+                (1, HousekeepingTaskKind::Inspect),
+            ]
+        );
+    }
+}
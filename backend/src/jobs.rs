@@ -0,0 +1,174 @@
+use crate::db::{
+    DbPool, get_all_hotels, get_and_lock_checked_in_priced_bookings,
+    get_and_lock_due_no_show_bookings, get_and_lock_expired_holds, get_and_lock_hotel_by_id,
+    get_folio_entries, get_hotels_with_expired_holds,
+};
+use crate::event_processor::EventProcessor;
+use crate::folio::{folio_balance, post_no_show_fee, post_room_charges};
+use crate::hotel_time::{hotel_today, last_no_show_arrival};
+use crate::models_events::{BookingHoldExpiredEvent, BookingMarkedNoShowEvent, Event};
+use crate::payments::{PaymentProvider, settle_deposits};
+use anyhow::Result;
+use chrono::{NaiveDate, Utc};
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+const NO_SHOW_CHECK_INTERVAL: Duration = Duration::from_secs(5 * 60);
+const HOLD_EXPIRY_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+const NIGHT_AUDIT_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Periodically marks confirmed bookings as no-shows, once their hotel's cutoff time on the
+/// arrival date has passed. This releases the rooms for the remaining nights of the stay, and
+/// charges the no-show fee of the booking's cancellation policy to its deposits.
+pub fn spawn_no_show_job(
+    pool: DbPool,
+    event_processor: Arc<EventProcessor>,
+    payment_provider: Arc<dyn PaymentProvider>,
+) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(NO_SHOW_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match mark_no_shows(&pool, &event_processor, payment_provider.as_ref()).await {
+                Ok(0) => {}
+                Ok(count) => info!("Marked {} bookings as no-shows", count),
+                Err(e) => error!("Failed to mark no-shows: {:?}", e),
+            }
+        }
+    });
+}
+
+/// Periodically expires holds which weren't confirmed in time, releasing their rooms.
+pub fn spawn_hold_expiry_job(pool: DbPool, event_processor: Arc<EventProcessor>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(HOLD_EXPIRY_CHECK_INTERVAL);
+        loop {
+            interval.tick().await;
+            match expire_holds(&pool, &event_processor).await {
+                Ok(0) => {}
+                Ok(count) => info!("Expired {} holds", count),
+                Err(e) => error!("Failed to expire holds: {:?}", e),
+            }
+        }
+    });
+}
+
+/// Periodically posts the room charges of checked-in guests, for each night which has passed.
+pub fn spawn_night_audit_job(pool: DbPool, event_processor: Arc<EventProcessor>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(NIGHT_AUDIT_INTERVAL);
+        loop {
+            interval.tick().await;
+            match post_nightly_room_charges(&pool, &event_processor).await {
+                Ok(0) => {}
+                Ok(count) => info!("Posted {} room charges", count),
+                Err(e) => error!("Failed to post room charges: {:?}", e),
+            }
+        }
+    });
+}
+
+async fn mark_no_shows(
+    pool: &DbPool,
+    event_processor: &EventProcessor,
+    payment_provider: &dyn PaymentProvider,
+) -> Result<usize> {
+    let mut tx = pool.begin().await?;
+
+    // The cutoff is in each hotel's own time zone. Releasing the rooms of no-shows locks the
+    // hotel, which has to happen before its bookings are locked.
+    let now = Utc::now();
+    let mut count = 0;
+    for hotel in get_all_hotels(pool).await? {
+        let Some(hotel) = get_and_lock_hotel_by_id(&mut tx, hotel.id).await? else {
+            continue;
+        };
+        let last_arrival = last_no_show_arrival(&hotel, now);
+        let bookings = get_and_lock_due_no_show_bookings(&mut tx, hotel.id, last_arrival).await?;
+        for booking in &bookings {
+            let event = Event::BookingMarkedNoShow(BookingMarkedNoShowEvent {
+                booking_id: booking.id,
+            });
+            event_processor
+                .process_event_with_tx(&mut tx, booking.id, event)
+                .await?;
+
+            // The fee is taken from the deposits, and the rest of them is released. Cards are
+            // charged right away, so a failure only leaves the deposits open to be settled by hand.
+            post_no_show_fee(event_processor, &mut tx, booking, hotel_today(&hotel)).await?;
+            let balance = folio_balance(&get_folio_entries(&mut *tx, booking.id).await?);
+            if let Err(e) = settle_deposits(
+                payment_provider,
+                event_processor,
+                &mut tx,
+                booking.id,
+                Some(balance),
+            )
+            .await
+            {
+                warn!(
+                    "Failed to settle the deposits of no-show booking {}: {}",
+                    booking.id, e
+                );
+            }
+        }
+        count += bookings.len();
+    }
+
+    tx.commit().await?;
+
+    Ok(count)
+}
+
+async fn expire_holds(pool: &DbPool, event_processor: &EventProcessor) -> Result<usize> {
+    let mut tx = pool.begin().await?;
+
+    // Releasing the rooms locks the hotel, which has to happen before its bookings are locked
+    let mut count = 0;
+    for hotel_id in get_hotels_with_expired_holds(pool).await? {
+        if get_and_lock_hotel_by_id(&mut tx, hotel_id).await?.is_none() {
+            continue;
+        }
+        let bookings = get_and_lock_expired_holds(&mut tx, hotel_id).await?;
+        for booking in &bookings {
+            let event = Event::BookingHoldExpired(BookingHoldExpiredEvent {
+                booking_id: booking.id,
+            });
+            event_processor
+                .process_event_with_tx(&mut tx, booking.id, event)
+                .await?;
+        }
+        count += bookings.len();
+    }
+
+    tx.commit().await?;
+
+    Ok(count)
+}
+
+async fn post_nightly_room_charges(
+    pool: &DbPool,
+    event_processor: &EventProcessor,
+) -> Result<usize> {
+    let mut tx = pool.begin().await?;
+
+    // A night is charged once it's over, i.e. nights before today at the hotel
+    let today_by_hotel: HashMap<i64, NaiveDate> = get_all_hotels(pool)
+        .await?
+        .iter()
+        .map(|hotel| (hotel.id, hotel_today(hotel)))
+        .collect();
+    let mut count = 0;
+    for booking in get_and_lock_checked_in_priced_bookings(&mut tx).await? {
+        let Some(&today) = today_by_hotel.get(&booking.hotel_id) else {
+            continue;
+        };
+        count += post_room_charges(event_processor, &mut tx, &booking, today).await?;
+    }
+
+    tx.commit().await?;
+
+    Ok(count)
+}
@@ -0,0 +1,348 @@
+use crate::currency::{convert_amount, find_exchange_rate};
+use crate::db::{
+    get_booking_by_id, get_folio_entries, get_hotel_by_id, get_loyalty_transactions_by_booking_id,
+    get_next_folio_entry_id,
+};
+use crate::event_processor::EventProcessor;
+use crate::folio::{folio_balance, unposted_room_charges};
+use crate::models::{
+    Booking, FolioEntry, FolioEntryKind, LoyaltyTransaction, LoyaltyTransactionKind,
+};
+use crate::models_events::{
+    Event, LoyaltyPointsEarnedEvent, LoyaltyPointsRedeemedEvent, LoyaltyPointsReversedEvent,
+};
+use anyhow::Result;
+use chrono::Utc;
+use sqlx::{Postgres, Transaction};
+
+/// Points are earned and valued in one currency, whatever the hotel's, so that a point is worth
+/// the same everywhere
+pub const POINTS_CURRENCY: &str = "EUR";
+/// Guests earn a point for every 100 minor units (i.e. every euro) spent on rooms
+const MINOR_UNITS_PER_POINT_EARNED: i64 = 100;
+/// What a point is worth when redeemed, in minor units of `POINTS_CURRENCY`
+const POINT_VALUE: i64 = 1;
+
+/// The points earned for a stay with the given room charges, at the exchange rate from the hotel's
+/// currency into `POINTS_CURRENCY`. Taxes, extras and partial units don't earn points.
+pub fn points_for_stay(room_charges: i64, rate: i64) -> i64 {
+    convert_amount(room_charges.max(0), rate) / MINOR_UNITS_PER_POINT_EARNED
+}
+
+/// What points are worth in the hotel's currency, at the exchange rate from `POINTS_CURRENCY`
+/// into it
+pub fn points_value(points: i64, rate: i64) -> i64 {
+    convert_amount(points * POINT_VALUE, rate)
+}
+
+/// The net change to the guest's points, and the net amount credited on the folio, from a
+/// booking's loyalty transactions
+pub fn booking_net(transactions: &[LoyaltyTransaction]) -> (i64, i64) {
+    transactions.iter().fold((0, 0), |(points, amount), t| {
+        (points + t.points, amount + t.amount)
+    })
+}
+
+/// The most that can be paid for a booking with points: what the guest already owes, and the
+/// nights (with their taxes) which haven't been charged yet.
+pub fn redeemable_amount(booking: &Booking, entries: &[FolioEntry]) -> i64 {
+    let unposted = unposted_room_charges(booking, entries, booking.end_time);
+    let taxes: i64 = booking.quote.as_ref().map_or(0, |quote| {
+        quote
+            .taxes
+            .iter()
+            .filter(|tax| unposted.iter().any(|night| night.date == tax.date))
+            .map(|tax| tax.amount)
+            .sum()
+    });
+    let room_charges: i64 = unposted.iter().map(|night| night.amount).sum();
+
+    (folio_balance(entries) + room_charges + taxes).max(0)
+}
+
+/// Credits the guest of a checked-out booking with the points for their stay, on their loyalty
+/// stream. Bookings without a guest profile don't earn points, and a booking only earns them once.
+/// Hotels without an exchange rate into `POINTS_CURRENCY` can't award points, which isn't reason
+/// enough to fail the checkout.
+pub async fn accrue_points(
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+) -> Result<()> {
+    let Some(booking) = get_booking_by_id(&mut **tx, booking_id).await? else {
+        return Ok(());
+    };
+    let Some(guest_id) = booking.guest_id else {
+        return Ok(());
+    };
+
+    let transactions = get_loyalty_transactions_by_booking_id(tx, booking_id).await?;
+    if transactions
+        .iter()
+        .any(|t| t.kind == LoyaltyTransactionKind::Earned)
+    {
+        return Ok(());
+    }
+
+    let room_charges: i64 = get_folio_entries(&mut **tx, booking_id)
+        .await?
+        .iter()
+        .filter(|entry| entry.kind == FolioEntryKind::RoomCharge)
+        .map(|entry| entry.amount)
+        .sum();
+    let Some(hotel) = get_hotel_by_id(&mut **tx, booking.hotel_id).await? else {
+        return Ok(());
+    };
+    let Some(rate) = find_exchange_rate(tx, &hotel.base_currency, POINTS_CURRENCY).await? else {
+        tracing::warn!(
+            "No exchange rate from {} to {}, booking {} earns no points",
+            hotel.base_currency,
+            POINTS_CURRENCY,
+            booking_id
+        );
+        return Ok(());
+    };
+    let points = points_for_stay(room_charges, rate);
+    if points == 0 {
+        return Ok(());
+    }
+
+    // Appended without reactions, as this runs as one
+    let event = Event::LoyaltyPointsEarned(LoyaltyPointsEarnedEvent {
+        guest_id,
+        booking_id,
+        points,
+        earned_at: Utc::now(),
+    });
+    event_processor.append_event(tx, guest_id, &event).await
+}
+
+/// The booking whose stay won't take place after the event (it was cancelled, the guest didn't
+/// show up, or was walked to another hotel), so that its loyalty transactions are reversed.
+pub fn forfeited_booking_id(event: &Event) -> Option<i64> {
+    match event {
+        Event::BookingCancelled(e) => Some(e.booking_id),
+        Event::BookingMarkedNoShow(e) => Some(e.booking_id),
+        Event::BookingWalked(e) => Some(e.booking_id),
+        _ => None,
+    }
+}
+
+/// Undoes the loyalty transactions of a booking which won't be stayed (see
+/// `forfeited_booking_id`): points it earned are taken back, and
+/// points redeemed for it are given back to the guest, with their credit charged back on the
+/// folio.
+pub async fn reverse_points(
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+) -> Result<()> {
+    let transactions = get_loyalty_transactions_by_booking_id(tx, booking_id).await?;
+    let Some(guest_id) = transactions.first().map(|t| t.guest_id) else {
+        return Ok(());
+    };
+
+    let (points, amount) = booking_net(&transactions);
+    if points == 0 && amount == 0 {
+        return Ok(());
+    }
+
+    let entry_id = if amount != 0 {
+        Some(get_next_folio_entry_id(tx).await?)
+    } else {
+        None
+    };
+    let event = Event::LoyaltyPointsReversed(LoyaltyPointsReversedEvent {
+        guest_id,
+        booking_id,
+        points: -points,
+        amount: -amount,
+        entry_id,
+        reversed_at: Utc::now(),
+    });
+    event_processor.append_event(tx, guest_id, &event).await
+}
+
+/// Pays for (part of) a booking with the guest's points, crediting their value (see
+/// `points_value`) on the folio. The caller checks that the guest has enough points.
+pub async fn redeem_points(
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    guest_id: i64,
+    booking_id: i64,
+    points: i64,
+    amount: i64,
+) -> Result<()> {
+    let event = Event::LoyaltyPointsRedeemed(LoyaltyPointsRedeemedEvent {
+        guest_id,
+        booking_id,
+        points,
+        amount,
+        entry_id: get_next_folio_entry_id(tx).await?,
+        redeemed_at: Utc::now(),
+    });
+    event_processor
+        .process_event_with_tx(tx, guest_id, event)
+        .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::currency::RATE_SCALE;
+    use crate::models::{BookingStatus, NightlyPrice, PriceQuote, RoomPreferences, TaxLine};
+    use crate::models_events::{
+        BookingCancelledEvent, BookingCheckedOutEvent, BookingMarkedNoShowEvent, BookingWalkedEvent,
+    };
+    use chrono::NaiveDate;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, day).unwrap()
+    }
+
+    fn transaction(kind: LoyaltyTransactionKind, points: i64, amount: i64) -> LoyaltyTransaction {
+        LoyaltyTransaction {
+            id: 1,
+            guest_id: 1,
+            booking_id: 1,
+            kind,
+            points,
+            amount,
+            created_at: Utc::now(),
+        }
+    }
+
+    fn entry(kind: FolioEntryKind, amount: i64, day: u32) -> FolioEntry {
+        FolioEntry {
+            id: 1,
+            booking_id: 1,
+            kind,
+            description: String::new(),
+            amount,
+            date: date(day),
+            posted_at: Utc::now(),
+        }
+    }
+
+    fn booking() -> Booking {
+        let nights = vec![
+            NightlyPrice {
+                date: date(1),
+                amount: 10000,
+            },
+            NightlyPrice {
+                date: date(2),
+                amount: 12000,
+            },
+        ];
+        let taxes = nights
+            .iter()
+            .map(|night| TaxLine {
+                tax_rule_id: 1,
+                name: "City tax".to_string(),
+                date: night.date,
+                amount: 500,
+            })
+            .collect();
+        let quote = PriceQuote {
+            rate_plan_id: 1,
+            room_type: "standard".to_string(),
+            currency: "EUR".to_string(),
+            nights,
+            subtotal: 22000,
+            discount: 0,
+            total: 22000,
+            cancellation_policy: None,
+            taxes,
+            promo: None,
+            converted: None,
+        };
+
+        Booking {
+            id: 1,
+            hotel_id: 1,
+            room_number: Some(101),
+            room_pinned: false,
+            guest_name: "Jane Doe".to_string(),
+            start_time: date(1),
+            end_time: date(3),
+            status: BookingStatus::CheckedIn,
+            preferences: RoomPreferences::default(),
+            hold_expires_at: None,
+            group_id: None,
+            adults: 2,
+            children: 0,
+            guest_id: Some(1),
+            quote: Some(quote),
+            room_type: None,
+        }
+    }
+
+    #[test]
+    fn test_points_are_earned_per_whole_unit_spent() {
+        assert_eq!(points_for_stay(22050, RATE_SCALE), 220);
+        assert_eq!(points_for_stay(99, RATE_SCALE), 0);
+        // Credits (e.g. a refunded stay) don't take points away
+        assert_eq!(points_for_stay(-5000, RATE_SCALE), 0);
+    }
+
+    #[test]
+    fn test_points_are_worth_the_same_in_every_currency() {
+        // 220.50 USD is 203.00 EUR at 0.920635
+        assert_eq!(points_for_stay(22050, 920_635), 203);
+        // 500 points are worth 5.00 EUR, i.e. 5.43 USD at 1.086
+        assert_eq!(points_value(500, RATE_SCALE), 500);
+        assert_eq!(points_value(500, 1_086_000), 543);
+    }
+
+    #[test]
+    fn test_stays_which_dont_take_place_are_reversed() {
+        let cancelled = Event::BookingCancelled(BookingCancelledEvent { booking_id: 1 });
+        let no_show = Event::BookingMarkedNoShow(BookingMarkedNoShowEvent { booking_id: 2 });
+        let walked = Event::BookingWalked(BookingWalkedEvent {
+            booking_id: 3,
+            relocated_to: None,
+        });
+        let checked_out = Event::BookingCheckedOut(BookingCheckedOutEvent {
+            booking_id: 4,
+            departure_date: None,
+        });
+
+        assert_eq!(forfeited_booking_id(&cancelled), Some(1));
+        assert_eq!(forfeited_booking_id(&no_show), Some(2));
+        assert_eq!(forfeited_booking_id(&walked), Some(3));
+        assert_eq!(forfeited_booking_id(&checked_out), None);
+    }
+
+    #[test]
+    fn test_booking_net_adds_up_transactions() {
+        let transactions = vec![
+            transaction(LoyaltyTransactionKind::Redeemed, -500, 500),
+            transaction(LoyaltyTransactionKind::Redeemed, -200, 200),
+        ];
+        assert_eq!(booking_net(&transactions), (-700, 700));
+
+        // Once reversed, nothing is left to reverse
+        let mut reversed = transactions.clone();
+        reversed.push(transaction(LoyaltyTransactionKind::Reversed, 700, -700));
+        assert_eq!(booking_net(&reversed), (0, 0));
+    }
+
+    #[test]
+    fn test_redeemable_amount_covers_the_rest_of_the_stay() {
+        // Nothing charged yet: both nights with their taxes
+        assert_eq!(redeemable_amount(&booking(), &[]), 23000);
+
+        // The first night was charged, and partly paid with points already
+        let entries = vec![
+            entry(FolioEntryKind::RoomCharge, 10000, 1),
+            entry(FolioEntryKind::Tax, 500, 1),
+            entry(FolioEntryKind::Payment, -3000, 1),
+        ];
+        assert_eq!(redeemable_amount(&booking(), &entries), 20000);
+
+        // Nothing can be redeemed once the stay is paid for in full
+        let paid = vec![entry(FolioEntryKind::Payment, -30000, 1)];
+        assert_eq!(redeemable_amount(&booking(), &paid), 0);
+    }
+}
@@ -1,6 +1,6 @@
 use axum::{
     Router,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
 use std::env;
 use std::net::SocketAddr;
@@ -9,17 +9,30 @@ use tower_http::cors::CorsLayer;
 use tracing::{info, Level};
 
 mod app_state;
+mod currency;
 mod db;
 mod electric_proxy;
 mod error;
 mod event_processor;
+mod folio;
+mod guests;
 mod handlers;
+mod hotel_time;
+mod housekeeping;
+mod jobs;
+mod loyalty;
 mod models;
 mod models_events;
 mod models_client_events;
 mod models_request;
+mod payments;
+mod pricing;
 mod projections;
+mod promo_codes;
+mod restrictions;
+mod taxes;
 mod room_assignment;
+mod waitlist;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -42,22 +55,115 @@ async fn main() -> anyhow::Result<()> {
     // Set up event processor
     let event_processor = Arc::new(event_processor::EventProcessor::new(pool.clone()));
 
+    // Only the mock payment provider exists so far; real providers plug in here
+    let payment_provider: Arc<dyn payments::PaymentProvider> =
+        Arc::new(payments::MockPaymentProvider::default());
+
+    // Start background jobs
+    jobs::spawn_no_show_job(
+        pool.clone(),
+        event_processor.clone(),
+        payment_provider.clone(),
+    );
+    jobs::spawn_hold_expiry_job(pool.clone(), event_processor.clone());
+    jobs::spawn_night_audit_job(pool.clone(), event_processor.clone());
+
+    // Choose how rooms are assigned at check-in
+    let room_assignment_strategy: Arc<dyn room_assignment::RoomAssignmentStrategy> =
+        match env::var("ROOM_ASSIGNMENT_STRATEGY").as_deref() {
+            Ok("first_fit") => Arc::new(room_assignment::FirstFitStrategy),
+            _ => Arc::new(room_assignment::PreferenceScoringStrategy),
+        };
+
     // Create app state
     let app_state = app_state::AppState {
         db_pool: pool,
         event_processor,
         http_client: reqwest::Client::new(),
+        room_assignment_strategy,
+        payment_provider,
     };
 
     let app = Router::new()
         .route("/health", get(handlers::health_check))
-        .route("/hotels", get(handlers::get_hotels))
-        .route("/hotels/{id}", get(handlers::get_hotel))
+        .route(
+            "/hotels",
+            get(handlers::get_hotels).post(handlers::create_hotel),
+        )
+        .route("/exchange-rates", get(handlers::get_exchange_rates))
+        .route(
+            "/exchange-rates/{from_currency}/{to_currency}",
+            post(handlers::update_exchange_rate),
+        )
+        .route(
+            "/promo-codes",
+            get(handlers::get_promo_codes).post(handlers::create_promo_code),
+        )
+        .route(
+            "/hotels/{id}",
+            get(handlers::get_hotel).post(handlers::update_hotel),
+        )
+        .route("/hotels/{id}/archive", post(handlers::archive_hotel))
+        .route("/hotels/{id}/rooms", get(handlers::get_rooms))
+        .route(
+            "/hotels/{id}/rooms/{room_number}",
+            post(handlers::update_room),
+        )
+        .route(
+            "/hotels/{id}/room-connections",
+            get(handlers::get_room_connections).post(handlers::create_room_connection),
+        )
+        .route(
+            "/hotels/{id}/room-connections/{room_a}/{room_b}",
+            delete(handlers::delete_room_connection),
+        )
         .route("/hotels/{id}/bookings", post(handlers::create_booking))
+        .route("/hotels/{id}/holds", post(handlers::create_hold))
+        .route(
+            "/hotels/{id}/availability",
+            get(handlers::get_hotel_availability),
+        )
+        .route("/availability", get(handlers::search_available_hotels))
+        .route(
+            "/hotels/{id}/group-bookings",
+            post(handlers::create_group_booking),
+        )
+        .route(
+            "/hotels/{id}/waitlist",
+            get(handlers::get_waitlist).post(handlers::join_waitlist),
+        )
+        .route(
+            "/waitlist/{entry_id}/cancel",
+            post(handlers::leave_waitlist),
+        )
+        .route("/guests", post(handlers::create_guest))
+        .route("/guests/search", get(handlers::search_guests))
+        .route(
+            "/guests/{guest_id}",
+            get(handlers::get_guest).post(handlers::update_guest),
+        )
+        .route(
+            "/guests/{guest_id}/loyalty",
+            get(handlers::get_loyalty_account),
+        )
+        .route("/groups/{group_id}/checkin", post(handlers::checkin_group))
+        .route("/groups/{group_id}/cancel", post(handlers::cancel_group))
         .route(
             "/hotels/{id}/bookings/shape",
             get(electric_proxy::get_hotel_bookings_shape),
         )
+        .route(
+            "/bookings/{booking_id}/modify",
+            post(handlers::modify_booking),
+        )
+        .route(
+            "/bookings/{booking_id}/confirm",
+            post(handlers::confirm_hold),
+        )
+        .route(
+            "/bookings/{booking_id}/room",
+            post(handlers::preassign_room),
+        )
         .route(
             "/bookings/{booking_id}/checkin",
             post(handlers::checkin_booking),
@@ -70,6 +176,56 @@ async fn main() -> anyhow::Result<()> {
             "/bookings/{booking_id}/cancel",
             post(handlers::cancel_booking),
         )
+        .route(
+            "/hotels/{id}/rooms/{room_number}/housekeeping",
+            post(handlers::update_room_housekeeping_status),
+        )
+        .route("/hotels/{id}/overstays", get(handlers::get_overstays))
+        .route(
+            "/hotels/{id}/overbooking",
+            get(handlers::get_overbooking_report).post(handlers::update_overbooking_limit),
+        )
+        .route("/bookings/{booking_id}/walk", post(handlers::walk_guest))
+        .route(
+            "/bookings/{booking_id}/folio",
+            get(handlers::get_folio).post(handlers::post_folio_entry),
+        )
+        .route("/bookings/{booking_id}/invoice", get(handlers::get_invoice))
+        .route("/bookings/{booking_id}/payments", get(handlers::get_payments))
+        .route(
+            "/bookings/{booking_id}/loyalty-redemptions",
+            post(handlers::redeem_loyalty_points),
+        )
+        .route(
+            "/bookings/{booking_id}/cancellation-fee",
+            get(handlers::get_cancellation_fee),
+        )
+        .route("/hotels/{id}/rate-plans", get(handlers::get_rate_plans))
+        .route(
+            "/hotels/{id}/rate-plans/{room_type}",
+            post(handlers::update_rate_plan),
+        )
+        .route("/hotels/{id}/quote", get(handlers::get_quote))
+        .route(
+            "/hotels/{id}/tax-rules",
+            get(handlers::get_tax_rules).post(handlers::create_tax_rule),
+        )
+        .route(
+            "/hotels/{id}/tax-rules/{tax_rule_id}",
+            delete(handlers::delete_tax_rule),
+        )
+        .route(
+            "/hotels/{id}/restrictions",
+            get(handlers::get_stay_restrictions).post(handlers::create_stay_restriction),
+        )
+        .route(
+            "/hotels/{id}/restrictions/{restriction_id}",
+            delete(handlers::delete_stay_restriction),
+        )
+        .route(
+            "/hotels/{id}/housekeeping/tasks",
+            get(handlers::get_housekeeping_tasks),
+        )
         .route("/client-events", post(handlers::handle_client_event))
         .with_state(app_state)
         .layer(CorsLayer::permissive());
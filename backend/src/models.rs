@@ -1,22 +1,42 @@
-use chrono::NaiveDate;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum BookingStatus {
+    Held,
     Confirmed,
     CheckedIn,
     CheckedOut,
     Cancelled,
+    NoShow,
+    Expired,
+    /// Relocated to another hotel, because the hotel was overbooked
+    Walked,
+}
+
+impl BookingStatus {
+    /// Whether a booking in this status takes up a room for its nights
+    pub fn holds_inventory(&self) -> bool {
+        matches!(
+            self,
+            BookingStatus::Held | BookingStatus::Confirmed | BookingStatus::CheckedIn
+        )
+    }
 }
 
 impl std::fmt::Display for BookingStatus {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let status_str = match self {
+            BookingStatus::Held => "held",
             BookingStatus::Confirmed => "confirmed",
             BookingStatus::CheckedIn => "checked_in",
             BookingStatus::CheckedOut => "checked_out",
             BookingStatus::Cancelled => "cancelled",
+            BookingStatus::NoShow => "no_show",
+            BookingStatus::Expired => "expired",
+            BookingStatus::Walked => "walked",
         };
         write!(f, "{}", status_str)
     }
@@ -27,10 +47,14 @@ impl std::str::FromStr for BookingStatus {
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
         match s {
+            "held" => Ok(BookingStatus::Held),
             "confirmed" => Ok(BookingStatus::Confirmed),
             "checked_in" => Ok(BookingStatus::CheckedIn),
             "checked_out" => Ok(BookingStatus::CheckedOut),
             "cancelled" => Ok(BookingStatus::Cancelled),
+            "no_show" => Ok(BookingStatus::NoShow),
+            "expired" => Ok(BookingStatus::Expired),
+            "walked" => Ok(BookingStatus::Walked),
             _ => Err(format!("Invalid booking status: {}", s)),
         }
     }
@@ -41,6 +65,613 @@ pub struct Hotel {
     pub id: i64,
     pub name: String,
     pub room_count: i32,
+    /// Time on the arrival date after which guests who haven't checked in are no-shows
+    pub no_show_cutoff: NaiveTime,
+    pub overbooking_limit: OverbookingLimit,
+    /// ISO 4217 code of the currency the hotel's stays are priced and charged in
+    pub base_currency: String,
+    /// The IANA time zone the hotel is in. Booking dates and times of day are in its local time.
+    pub time_zone: Tz,
+    /// From when guests may move in on the arrival date
+    pub check_in_time: NaiveTime,
+    /// When guests have to leave by on the departure date
+    pub check_out_time: NaiveTime,
+    /// Archived hotels are hidden from guests and don't take new bookings, but keep their history
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+impl Hotel {
+    /// How many bookings beyond the number of rooms may be accepted for a night
+    pub fn overbooking_allowance(&self) -> i32 {
+        match self.overbooking_limit {
+            OverbookingLimit::None => 0,
+            OverbookingLimit::Rooms(rooms) => rooms,
+            OverbookingLimit::Percentage(percentage) => self.room_count * percentage / 100,
+        }
+    }
+}
+
+/// How far a hotel may be overbooked: by a number of rooms, or a percentage of its rooms
+/// (rounded down)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum OverbookingLimit {
+    #[default]
+    None,
+    Rooms(i32),
+    Percentage(i32),
+}
+
+/// The room type of rooms which don't have one configured, and of bookings which don't ask for one
+pub const DEFAULT_ROOM_TYPE: &str = "standard";
+
+/// Prices stays in one room type of a hotel. All amounts are in minor units of the currency.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatePlan {
+    pub id: i64,
+    pub hotel_id: i64,
+    pub room_type: String,
+    pub name: String,
+    /// ISO 4217 code, e.g. "EUR"
+    pub currency: String,
+    /// The nightly rate outside of any season
+    pub base_rate: i64,
+    /// Added to the rates of Friday and Saturday nights; negative for a weekend discount
+    pub weekend_surcharge_percent: i32,
+    pub seasons: Vec<SeasonalRate>,
+    pub length_of_stay_discounts: Vec<LengthOfStayDiscount>,
+    /// Cancelling is always free if there is no policy
+    pub cancellation_policy: Option<CancellationPolicy>,
+}
+
+/// A nightly rate for the nights from `start_date` (inclusive) to `end_date` (exclusive)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SeasonalRate {
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub nightly_rate: i64,
+}
+
+/// A discount on the whole stay, for stays of at least `min_nights` nights
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LengthOfStayDiscount {
+    pub min_nights: i32,
+    pub percent: i32,
+}
+
+/// Cancelling is free until `free_until_days_before` days before the arrival date (inclusive); later
+/// cancellations are charged a penalty.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CancellationPolicy {
+    pub free_until_days_before: i32,
+    pub penalty: CancellationPenalty,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum CancellationPenalty {
+    /// A percentage of the price of the stay
+    Percentage(i32),
+    /// The price of the first nights of the stay
+    Nights(i32),
+}
+
+/// The price of a stay, as computed from a rate plan. All amounts are in minor units of the
+/// currency.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PriceQuote {
+    pub rate_plan_id: i64,
+    pub room_type: String,
+    pub currency: String,
+    pub nights: Vec<NightlyPrice>,
+    /// The sum of the nightly prices
+    pub subtotal: i64,
+    /// The length of stay discount
+    pub discount: i64,
+    /// What the guest pays for the room, after all discounts
+    pub total: i64,
+    /// The rate plan's cancellation policy when the stay was quoted
+    #[serde(default)]
+    pub cancellation_policy: Option<CancellationPolicy>,
+    /// The taxes and fees on each night, which aren't included in `total`
+    #[serde(default)]
+    pub taxes: Vec<TaxLine>,
+    /// The promo code the booking was made with, if any
+    #[serde(default)]
+    pub promo: Option<AppliedPromo>,
+    /// The price in the guest's currency, if they asked for another one than the hotel's
+    #[serde(default)]
+    pub converted: Option<ConvertedPrice>,
+}
+
+/// A quote's price in another currency, at the exchange rate of when it was quoted
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConvertedPrice {
+    pub currency: String,
+    /// In millionths of the currency per unit of the quote's currency
+    pub exchange_rate: i64,
+    pub total: i64,
+    pub total_with_taxes: i64,
+}
+
+/// The rate at which amounts in one currency are converted into another
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExchangeRate {
+    pub from_currency: String,
+    pub to_currency: String,
+    /// In millionths of `to_currency` per unit of `from_currency`, e.g. 1085000 for 1.085
+    pub rate: i64,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A promo code's discount, as taken off a quote
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppliedPromo {
+    pub code: String,
+    /// The terms of the code when the stay was quoted, which later changes don't alter
+    pub discount: PromoDiscount,
+    /// The amount taken off the quote
+    pub amount: i64,
+}
+
+/// A discount on stays, given to guests who book with the code
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PromoCode {
+    /// Upper case
+    pub code: String,
+    /// The hotel the code is valid at, or `None` for every hotel
+    pub hotel_id: Option<i64>,
+    pub discount: PromoDiscount,
+    /// Stays arriving from `valid_from` to `valid_until` (inclusive) can use the code
+    pub valid_from: Option<NaiveDate>,
+    pub valid_until: Option<NaiveDate>,
+    /// How many bookings can use the code; unlimited if `None`
+    pub max_uses: Option<i32>,
+    pub used_count: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum PromoDiscount {
+    /// A percentage of the price of the room
+    Percentage(i32),
+    /// An amount in minor units, only given on stays priced in the same currency
+    Fixed { amount: i64, currency: String },
+}
+
+impl PriceQuote {
+    /// What the guest pays for the stay, taxes and fees included
+    pub fn total_with_taxes(&self) -> i64 {
+        self.total + self.taxes.iter().map(|tax| tax.amount).sum::<i64>()
+    }
+}
+
+/// A tax or fee on one night of a stay
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxLine {
+    pub tax_rule_id: i64,
+    pub name: String,
+    pub date: NaiveDate,
+    pub amount: i64,
+}
+
+/// A tax or fee which a hotel adds to the price of each night, as its jurisdiction requires
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TaxRule {
+    pub id: i64,
+    pub hotel_id: i64,
+    /// Shown on folios and invoices, e.g. "VAT 8.1%"
+    pub name: String,
+    pub charge: TaxCharge,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum TaxCharge {
+    /// A rate in basis points (hundredths of a percent) of the night's room price, e.g. 810 for
+    /// 8.1% VAT
+    Rate(i64),
+    /// A fixed amount in minor units per guest (children included), e.g. a city tax
+    PerPersonPerNight(i64),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NightlyPrice {
+    pub date: NaiveDate,
+    pub amount: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FolioEntryKind {
+    /// The price of a night of the stay, posted by the night audit or at checkout
+    RoomCharge,
+    /// Anything else the guest is charged for, such as the minibar
+    Extra,
+    Payment,
+    /// A correction, which may be a charge or a credit
+    Adjustment,
+    /// The penalty for cancelling a booking late, as set by its cancellation policy
+    CancellationFee,
+    /// A tax or fee on a night's room charge, posted with it
+    Tax,
+}
+
+impl std::fmt::Display for FolioEntryKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind_str = match self {
+            FolioEntryKind::RoomCharge => "room_charge",
+            FolioEntryKind::Extra => "extra",
+            FolioEntryKind::Payment => "payment",
+            FolioEntryKind::Adjustment => "adjustment",
+            FolioEntryKind::CancellationFee => "cancellation_fee",
+            FolioEntryKind::Tax => "tax",
+        };
+        write!(f, "{}", kind_str)
+    }
+}
+
+impl std::str::FromStr for FolioEntryKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "room_charge" => Ok(FolioEntryKind::RoomCharge),
+            "extra" => Ok(FolioEntryKind::Extra),
+            "payment" => Ok(FolioEntryKind::Payment),
+            "adjustment" => Ok(FolioEntryKind::Adjustment),
+            "cancellation_fee" => Ok(FolioEntryKind::CancellationFee),
+            "tax" => Ok(FolioEntryKind::Tax),
+            _ => Err(format!("Invalid folio entry kind: {}", s)),
+        }
+    }
+}
+
+/// A line of a booking's folio. Positive amounts are charged to the guest, negative amounts (such
+/// as payments) are credited.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FolioEntry {
+    pub id: i64,
+    pub booking_id: i64,
+    pub kind: FolioEntryKind,
+    pub description: String,
+    pub amount: i64,
+    pub date: NaiveDate,
+    pub posted_at: DateTime<Utc>,
+}
+
+/// The account of a booking, in the base currency of the hotel.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Folio {
+    pub booking_id: i64,
+    pub currency: String,
+    pub entries: Vec<FolioEntry>,
+    /// What the guest still owes; negative if they paid too much
+    pub balance: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InvoiceLine {
+    pub date: NaiveDate,
+    pub description: String,
+    pub amount: i64,
+}
+
+/// The bill for a stay, issued at checkout from the booking's folio
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Invoice {
+    /// Sequential per hotel
+    pub invoice_number: i64,
+    pub hotel_id: i64,
+    pub hotel_name: String,
+    pub booking_id: i64,
+    pub guest_name: String,
+    pub start_time: NaiveDate,
+    pub end_time: NaiveDate,
+    pub issued_at: DateTime<Utc>,
+    /// The hotel's base currency; missing on invoices of stays which weren't priced, issued before
+    /// hotels had one
+    pub currency: Option<String>,
+    /// The charges, adjustments included
+    pub lines: Vec<InvoiceLine>,
+    /// The taxes and fees on the charges, itemized
+    #[serde(default)]
+    pub taxes: Vec<InvoiceLine>,
+    pub payments: Vec<InvoiceLine>,
+    /// Taxes and fees included
+    pub total: i64,
+    #[serde(default)]
+    pub tax_total: i64,
+    pub paid: i64,
+    pub balance: i64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuthorizationStatus {
+    Authorized,
+    Captured,
+    Voided,
+}
+
+impl std::fmt::Display for AuthorizationStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status_str = match self {
+            AuthorizationStatus::Authorized => "authorized",
+            AuthorizationStatus::Captured => "captured",
+            AuthorizationStatus::Voided => "voided",
+        };
+        write!(f, "{}", status_str)
+    }
+}
+
+impl std::str::FromStr for AuthorizationStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "authorized" => Ok(AuthorizationStatus::Authorized),
+            "captured" => Ok(AuthorizationStatus::Captured),
+            "voided" => Ok(AuthorizationStatus::Voided),
+            _ => Err(format!("Invalid authorization status: {}", s)),
+        }
+    }
+}
+
+/// An amount reserved on a guest's card, as a deposit for a booking
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaymentAuthorization {
+    /// The payment provider's ID for the authorization
+    pub authorization_id: String,
+    pub booking_id: i64,
+    /// In minor units of the currency
+    pub amount: i64,
+    pub currency: String,
+    pub status: AuthorizationStatus,
+    pub captured_amount: i64,
+    pub authorized_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LoyaltyTransactionKind {
+    Earned,
+    Redeemed,
+    Reversed,
+}
+
+impl std::fmt::Display for LoyaltyTransactionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind_str = match self {
+            LoyaltyTransactionKind::Earned => "earned",
+            LoyaltyTransactionKind::Redeemed => "redeemed",
+            LoyaltyTransactionKind::Reversed => "reversed",
+        };
+        write!(f, "{}", kind_str)
+    }
+}
+
+impl std::str::FromStr for LoyaltyTransactionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "earned" => Ok(LoyaltyTransactionKind::Earned),
+            "redeemed" => Ok(LoyaltyTransactionKind::Redeemed),
+            "reversed" => Ok(LoyaltyTransactionKind::Reversed),
+            _ => Err(format!("Invalid loyalty transaction kind: {}", s)),
+        }
+    }
+}
+
+/// A change to a guest's loyalty points, for a booking
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LoyaltyTransaction {
+    pub id: i64,
+    pub guest_id: i64,
+    pub booking_id: i64,
+    pub kind: LoyaltyTransactionKind,
+    /// The change to the guest's balance, negative for redemptions
+    pub points: i64,
+    /// The amount credited on the booking's folio, in minor units of the hotel's base currency
+    pub amount: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A rule limiting which stays can be booked. It's in effect from `start_date` (inclusive) to
+/// `end_date` (exclusive), on the given days of the week (or every day, if none are given).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StayRestriction {
+    pub id: i64,
+    pub start_date: NaiveDate,
+    pub end_date: NaiveDate,
+    pub days_of_week: Vec<Weekday>,
+    /// The minimum number of nights for stays arriving while the restriction is in effect
+    pub min_stay: Option<i32>,
+    pub closed_to_arrival: bool,
+    pub closed_to_departure: bool,
+}
+
+/// A night for which more rooms are booked than the hotel has
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OverbookedNight {
+    pub date: NaiveDate,
+    pub booked_rooms: i32,
+    pub rooms: i32,
+}
+
+/// Cleaning state of a room. A room becomes dirty when a guest checks out, and is ready for the
+/// next guest once it's cleaned and then inspected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HousekeepingStatus {
+    Dirty,
+    Cleaning,
+    Clean,
+    Inspected,
+}
+
+impl std::fmt::Display for HousekeepingStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status_str = match self {
+            HousekeepingStatus::Dirty => "dirty",
+            HousekeepingStatus::Cleaning => "cleaning",
+            HousekeepingStatus::Clean => "clean",
+            HousekeepingStatus::Inspected => "inspected",
+        };
+        write!(f, "{}", status_str)
+    }
+}
+
+impl std::str::FromStr for HousekeepingStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dirty" => Ok(HousekeepingStatus::Dirty),
+            "cleaning" => Ok(HousekeepingStatus::Cleaning),
+            "clean" => Ok(HousekeepingStatus::Clean),
+            "inspected" => Ok(HousekeepingStatus::Inspected),
+            _ => Err(format!("Invalid housekeeping status: {}", s)),
+        }
+    }
+}
+
+/// Room features a guest would like to have, used when assigning a room at check-in
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RoomPreferences {
+    pub high_floor: bool,
+    pub quiet: bool,
+    pub near_elevator: bool,
+    pub accessible: bool,
+}
+
+impl RoomPreferences {
+    /// Combines two sets of preferences, wanting everything either of them wants
+    pub fn merge(&self, other: &RoomPreferences) -> RoomPreferences {
+        RoomPreferences {
+            high_floor: self.high_floor || other.high_floor,
+            quiet: self.quiet || other.quiet,
+            near_elevator: self.near_elevator || other.near_elevator,
+            accessible: self.accessible || other.accessible,
+        }
+    }
+}
+
+/// A guest profile, shared by all bookings of a returning guest
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Guest {
+    pub id: i64,
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    pub preferences: RoomPreferences,
+}
+
+/// A room in a hotel, with the features relevant for matching guest preferences
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Room {
+    pub room_number: i32,
+    pub floor: i32,
+    pub quiet: bool,
+    pub near_elevator: bool,
+    pub accessible: bool,
+    pub housekeeping_status: HousekeepingStatus,
+    /// The maximum number of guests (adults and children) the room can fit
+    pub max_occupancy: i32,
+    pub room_type: String,
+}
+
+impl Room {
+    /// A room with no known features, used for rooms that aren't configured in the `rooms` table
+    pub fn with_defaults(room_number: i32) -> Self {
+        Self {
+            room_number,
+            floor: 1,
+            quiet: false,
+            near_elevator: false,
+            accessible: false,
+            housekeeping_status: HousekeepingStatus::Inspected,
+            max_occupancy: 2,
+            room_type: DEFAULT_ROOM_TYPE.to_string(),
+        }
+    }
+
+    pub fn fits(&self, guest_count: i32) -> bool {
+        guest_count <= self.max_occupancy
+    }
+
+    /// Whether the room may be given to a booking sold as `room_type`; bookings without one may
+    /// get any room
+    pub fn is_of_type(&self, room_type: Option<&str>) -> bool {
+        room_type.is_none_or(|room_type| room_type == self.room_type)
+    }
+}
+
+/// How two rooms are related: adjacent rooms are next to each other, connecting rooms also have
+/// a door between them (so they're adjacent as well)
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RoomConnectionKind {
+    Adjacent,
+    Connecting,
+}
+
+impl std::fmt::Display for RoomConnectionKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kind_str = match self {
+            RoomConnectionKind::Adjacent => "adjacent",
+            RoomConnectionKind::Connecting => "connecting",
+        };
+        write!(f, "{}", kind_str)
+    }
+}
+
+impl std::str::FromStr for RoomConnectionKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "adjacent" => Ok(RoomConnectionKind::Adjacent),
+            "connecting" => Ok(RoomConnectionKind::Connecting),
+            _ => Err(format!("Invalid room connection kind: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomConnection {
+    pub room_a: i32,
+    pub room_b: i32,
+    pub kind: RoomConnectionKind,
+}
+
+/// Constraint on how the rooms of a group booking relate to each other
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GroupRoomConstraint {
+    #[default]
+    None,
+    /// Every room is reachable from every other one through adjacent (or connecting) rooms
+    Adjacent,
+    /// Every room is reachable from every other one through connecting doors
+    Connecting,
+}
+
+/// A checked-in booking whose guest should have left already
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Overstay {
+    #[serde(flatten)]
+    pub booking: Booking,
+    pub nights_overstayed: i64,
+}
+
+/// Number of rooms which are free on the night starting at `date`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NightAvailability {
+    pub date: NaiveDate,
+    pub free_rooms: i32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,8 +679,85 @@ pub struct Booking {
     pub id: i64,
     pub hotel_id: i64,
     pub room_number: Option<i32>,
+    /// Whether the (pre-)assigned room must not be changed by automatic re-packing
+    pub room_pinned: bool,
     pub guest_name: String,
     pub start_time: NaiveDate,
     pub end_time: NaiveDate,
     pub status: BookingStatus,
+    pub preferences: RoomPreferences,
+    /// When the hold on the room expires, for held bookings
+    pub hold_expires_at: Option<DateTime<Utc>>,
+    /// The group booking this booking is part of, if any
+    pub group_id: Option<i64>,
+    pub adults: i32,
+    pub children: i32,
+    /// The profile of the guest, if the booking is linked to one
+    pub guest_id: Option<i64>,
+    /// The price quoted when the booking was made, if the hotel has a rate plan for it
+    pub quote: Option<PriceQuote>,
+    /// The room type the booking was sold as, which it only gets rooms of. Bookings made before
+    /// room types were tracked may get any room.
+    pub room_type: Option<String>,
+}
+
+impl Booking {
+    /// The number of guests staying in the room
+    pub fn guest_count(&self) -> i32 {
+        self.adults + self.children
+    }
+}
+
+/// The number of adults on bookings which don't specify it
+pub fn default_adults() -> i32 {
+    1
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitlistStatus {
+    Waiting,
+    /// A held booking was created for the entry, which the guest still has to confirm
+    Offered,
+    Cancelled,
+}
+
+impl std::fmt::Display for WaitlistStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status_str = match self {
+            WaitlistStatus::Waiting => "waiting",
+            WaitlistStatus::Offered => "offered",
+            WaitlistStatus::Cancelled => "cancelled",
+        };
+        write!(f, "{}", status_str)
+    }
+}
+
+impl std::str::FromStr for WaitlistStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "waiting" => Ok(WaitlistStatus::Waiting),
+            "offered" => Ok(WaitlistStatus::Offered),
+            "cancelled" => Ok(WaitlistStatus::Cancelled),
+            _ => Err(format!("Invalid waitlist status: {}", s)),
+        }
+    }
+}
+
+/// A guest waiting for a room to free up in a fully-booked hotel
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitlistEntry {
+    pub id: i64,
+    pub hotel_id: i64,
+    pub guest_name: String,
+    pub email: Option<String>,
+    pub start_time: NaiveDate,
+    pub end_time: NaiveDate,
+    pub adults: i32,
+    pub children: i32,
+    pub status: WaitlistStatus,
+    /// The held booking offered to the guest
+    pub booking_id: Option<i64>,
 }
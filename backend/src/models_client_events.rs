@@ -1,5 +1,5 @@
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
-use chrono::NaiveDate;
 
 /// Client-side events that can be generated when offline and synced later
 #[derive(Debug, Serialize, Deserialize)]
@@ -9,9 +9,19 @@ pub enum ClientEvent {
     OfflineCheckin(OfflineCheckinEvent),
 }
 
+/// A check-in to a room chosen by the front desk. The options are the same as for online
+/// check-ins. Arrival rules are checked against when the guest checked in, if the client sends it,
+/// rather than when the event is synced.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OfflineCheckinEvent {
     pub booking_id: String, // Accept as string to handle large integers safely
     pub room_number: i32,
-    pub today: NaiveDate,
+    #[serde(default)]
+    pub refuse_dirty_rooms: bool,
+    #[serde(default)]
+    pub capture_deposit: bool,
+    #[serde(default)]
+    pub early_checkin: bool,
+    pub early_checkin_fee: Option<i64>,
+    pub checked_in_at: Option<DateTime<Utc>>,
 }
\ No newline at end of file
@@ -1,4 +1,7 @@
-use chrono::NaiveDate;
+use crate::models::{
+    FolioEntryKind, HousekeepingStatus, Invoice, PriceQuote, RoomPreferences, default_adults,
+};
+use chrono::{DateTime, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 
 // Event types for event sourcing
@@ -6,9 +9,83 @@ use serde::{Deserialize, Serialize};
 #[serde(tag = "event_type", content = "data")]
 pub enum Event {
     BookingCreated(BookingCreatedEvent),
+    BookingHeld(BookingHeldEvent),
+    BookingHoldConfirmed(BookingHoldConfirmedEvent),
+    BookingHoldExpired(BookingHoldExpiredEvent),
+    BookingRoomPreassigned(BookingRoomPreassignedEvent),
+    BookingRoomReassigned(BookingRoomReassignedEvent),
+    BookingDatesChanged(BookingDatesChangedEvent),
     BookingCheckedIn(BookingCheckedInEvent),
     BookingCheckedOut(BookingCheckedOutEvent),
     BookingCancelled(BookingCancelledEvent),
+    BookingMarkedNoShow(BookingMarkedNoShowEvent),
+    BookingWalked(BookingWalkedEvent),
+    RoomHousekeepingStatusChanged(RoomHousekeepingStatusChangedEvent),
+    GuestProfileCreated(GuestProfileEvent),
+    GuestProfileUpdated(GuestProfileEvent),
+    WaitlistJoined(WaitlistJoinedEvent),
+    WaitlistOffered(WaitlistOfferedEvent),
+    WaitlistLeft(WaitlistLeftEvent),
+    FolioEntryPosted(FolioEntryPostedEvent),
+    InvoiceIssued(InvoiceIssuedEvent),
+    PaymentAuthorized(PaymentAuthorizedEvent),
+    PaymentCaptured(PaymentCapturedEvent),
+    PaymentVoided(PaymentVoidedEvent),
+    PaymentDeclined(PaymentDeclinedEvent),
+    LoyaltyPointsEarned(LoyaltyPointsEarnedEvent),
+    LoyaltyPointsRedeemed(LoyaltyPointsRedeemedEvent),
+    LoyaltyPointsReversed(LoyaltyPointsReversedEvent),
+}
+
+impl Event {
+    /// The type of stream the event belongs to. Stream IDs are unique within a stream type:
+    /// booking streams use the booking ID, housekeeping streams use the hotel ID, guest and waitlist
+    /// streams use the guest and waitlist entry IDs, and loyalty streams use the guest ID.
+    pub fn stream_type(&self) -> &'static str {
+        match self {
+            Event::BookingCreated(_)
+            | Event::BookingHeld(_)
+            | Event::BookingHoldConfirmed(_)
+            | Event::BookingHoldExpired(_)
+            | Event::BookingRoomPreassigned(_)
+            | Event::BookingRoomReassigned(_)
+            | Event::BookingDatesChanged(_)
+            | Event::BookingCheckedIn(_)
+            | Event::BookingCheckedOut(_)
+            | Event::BookingCancelled(_)
+            | Event::BookingMarkedNoShow(_)
+            | Event::BookingWalked(_)
+            | Event::FolioEntryPosted(_)
+            | Event::InvoiceIssued(_)
+            | Event::PaymentAuthorized(_)
+            | Event::PaymentCaptured(_)
+            | Event::PaymentVoided(_)
+            | Event::PaymentDeclined(_) => "booking",
+            Event::RoomHousekeepingStatusChanged(_) => "housekeeping",
+            Event::GuestProfileCreated(_) | Event::GuestProfileUpdated(_) => "guest",
+            Event::WaitlistJoined(_) | Event::WaitlistOffered(_) | Event::WaitlistLeft(_) => {
+                "waitlist"
+            }
+            Event::LoyaltyPointsEarned(_)
+            | Event::LoyaltyPointsRedeemed(_)
+            | Event::LoyaltyPointsReversed(_) => "loyalty",
+        }
+    }
+
+    /// The booking whose room the event releases (for some or all of its nights), if any. A
+    /// checkout releases the nights left of the stay when the guest leaves early; when they leave
+    /// on the last day, there are none and nothing is offered.
+    pub fn released_booking_id(&self) -> Option<i64> {
+        match self {
+            Event::BookingCancelled(e) => Some(e.booking_id),
+            Event::BookingDatesChanged(e) => Some(e.booking_id),
+            Event::BookingHoldExpired(e) => Some(e.booking_id),
+            Event::BookingMarkedNoShow(e) => Some(e.booking_id),
+            Event::BookingWalked(e) => Some(e.booking_id),
+            Event::BookingCheckedOut(e) if e.departure_date.is_some() => Some(e.booking_id),
+            _ => None,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -18,6 +95,84 @@ pub struct BookingCreatedEvent {
     pub guest_name: String,
     pub start_time: NaiveDate,
     pub end_time: NaiveDate,
+    #[serde(default)]
+    pub preferences: RoomPreferences,
+    /// The group booking this booking is part of, if any
+    #[serde(default)]
+    pub group_id: Option<i64>,
+    #[serde(default = "default_adults")]
+    pub adults: i32,
+    #[serde(default)]
+    pub children: i32,
+    /// The profile of the guest, if the booking is linked to one
+    #[serde(default)]
+    pub guest_id: Option<i64>,
+    /// The price of the stay at the time of booking
+    #[serde(default)]
+    pub quote: Option<PriceQuote>,
+    /// The room type the booking was sold as; missing for events recorded before it was tracked
+    #[serde(default)]
+    pub room_type: Option<String>,
+}
+
+/// Emitted when a guest puts a tentative hold on a room, before entering their details
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookingHeldEvent {
+    pub booking_id: i64,
+    pub hotel_id: i64,
+    pub start_time: NaiveDate,
+    pub end_time: NaiveDate,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default = "default_adults")]
+    pub adults: i32,
+    #[serde(default)]
+    pub children: i32,
+    /// The price of the stay at the time of the hold
+    #[serde(default)]
+    pub quote: Option<PriceQuote>,
+    /// The room type the room is held in; missing for events recorded before it was tracked
+    #[serde(default)]
+    pub room_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookingHoldConfirmedEvent {
+    pub booking_id: i64,
+    pub guest_name: String,
+    #[serde(default)]
+    pub preferences: RoomPreferences,
+    #[serde(default)]
+    pub guest_id: Option<i64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookingHoldExpiredEvent {
+    pub booking_id: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookingRoomPreassignedEvent {
+    pub booking_id: i64,
+    pub room_number: i32,
+    pub pinned: bool,
+}
+
+/// Emitted when re-packing moves an unpinned pre-assignment to another room
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookingRoomReassignedEvent {
+    pub booking_id: i64,
+    pub from_room: i32,
+    pub to_room: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookingDatesChangedEvent {
+    pub booking_id: i64,
+    pub start_time: NaiveDate,
+    pub end_time: NaiveDate,
+    /// The price of the new stay, if the booking was priced
+    #[serde(default)]
+    pub quote: Option<PriceQuote>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -29,9 +184,161 @@ pub struct BookingCheckedInEvent {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BookingCheckedOutEvent {
     pub booking_id: i64,
+    /// The day the guest actually left; missing for events recorded before it was tracked
+    #[serde(default)]
+    pub departure_date: Option<NaiveDate>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BookingCancelledEvent {
     pub booking_id: i64,
-}
\ No newline at end of file
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookingMarkedNoShowEvent {
+    pub booking_id: i64,
+}
+
+/// Emitted when a guest is relocated to another hotel, because the hotel is overbooked
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BookingWalkedEvent {
+    pub booking_id: i64,
+    /// Where the guest was sent
+    #[serde(default)]
+    pub relocated_to: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RoomHousekeepingStatusChangedEvent {
+    pub hotel_id: i64,
+    pub room_number: i32,
+    pub status: HousekeepingStatus,
+}
+
+/// The full state of a guest profile, emitted when it's created and on every update
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuestProfileEvent {
+    pub guest_id: i64,
+    pub name: String,
+    pub email: Option<String>,
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub preferences: RoomPreferences,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitlistJoinedEvent {
+    pub entry_id: i64,
+    pub hotel_id: i64,
+    pub guest_name: String,
+    pub email: Option<String>,
+    pub start_time: NaiveDate,
+    pub end_time: NaiveDate,
+    pub adults: i32,
+    pub children: i32,
+}
+
+/// Emitted when a room frees up for a waitlist entry, and a held booking is created for it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitlistOfferedEvent {
+    pub entry_id: i64,
+    pub booking_id: i64,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WaitlistLeftEvent {
+    pub entry_id: i64,
+}
+
+/// A charge, payment or adjustment on a booking's folio. Charges have positive amounts, credits
+/// negative ones.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FolioEntryPostedEvent {
+    pub entry_id: i64,
+    pub booking_id: i64,
+    pub kind: FolioEntryKind,
+    pub description: String,
+    pub amount: i64,
+    pub date: NaiveDate,
+    pub posted_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InvoiceIssuedEvent {
+    pub invoice: Invoice,
+}
+
+/// A deposit was reserved on the guest's card
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentAuthorizedEvent {
+    pub booking_id: i64,
+    pub authorization_id: String,
+    pub amount: i64,
+    pub currency: String,
+    pub authorized_at: DateTime<Utc>,
+}
+
+/// (Part of) a deposit was charged, closing the authorization. The payment is credited on the
+/// booking's folio as the given entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentCapturedEvent {
+    pub booking_id: i64,
+    pub authorization_id: String,
+    /// The payment provider's ID for the charge
+    pub transaction_id: String,
+    pub amount: i64,
+    pub entry_id: i64,
+    pub captured_at: DateTime<Utc>,
+}
+
+/// A deposit was released without charging it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentVoidedEvent {
+    pub booking_id: i64,
+    pub authorization_id: String,
+}
+
+/// The payment provider refused to authorize or capture a payment. Only recorded for the audit
+/// trail.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PaymentDeclinedEvent {
+    pub booking_id: i64,
+    pub reason: String,
+}
+
+/// A guest earned loyalty points for a completed stay
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoyaltyPointsEarnedEvent {
+    pub guest_id: i64,
+    pub booking_id: i64,
+    pub points: i64,
+    pub earned_at: DateTime<Utc>,
+}
+
+/// A guest paid for (part of) a booking with loyalty points. Their value is credited on the
+/// booking's folio as the given entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoyaltyPointsRedeemedEvent {
+    pub guest_id: i64,
+    pub booking_id: i64,
+    pub points: i64,
+    /// The value of the points, in minor units of the hotel's base currency
+    pub amount: i64,
+    pub entry_id: i64,
+    pub redeemed_at: DateTime<Utc>,
+}
+
+/// The points a booking earned or used were taken back (or given back) because it was cancelled.
+/// `points` and `amount` undo the booking's earlier transactions, so they're negative for points
+/// that were earned, and positive for points that were redeemed. The credit for redeemed points is
+/// charged back on the folio as the given entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoyaltyPointsReversedEvent {
+    pub guest_id: i64,
+    pub booking_id: i64,
+    pub points: i64,
+    pub amount: i64,
+    pub entry_id: Option<i64>,
+    pub reversed_at: DateTime<Utc>,
+}
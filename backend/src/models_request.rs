@@ -1,4 +1,9 @@
-use chrono::NaiveDate;
+use crate::models::{
+    CancellationPolicy, FolioEntryKind, GroupRoomConstraint, HousekeepingStatus,
+    LengthOfStayDiscount, PromoDiscount, RoomPreferences, SeasonalRate, TaxCharge,
+    default_adults,
+};
+use chrono::{NaiveDate, NaiveTime, Weekday};
 use serde::{Deserialize, Serialize};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -6,4 +11,249 @@ pub struct CreateBookingRequest {
     pub guest_name: String,
     pub start_time: NaiveDate,
     pub end_time: NaiveDate,
-}
\ No newline at end of file
+    /// Optional room to pre-assign to the booking
+    #[serde(default)]
+    pub room_number: Option<i32>,
+    #[serde(default)]
+    pub pin_room: bool,
+    #[serde(default)]
+    pub preferences: RoomPreferences,
+    #[serde(default = "default_adults")]
+    pub adults: i32,
+    #[serde(default)]
+    pub children: i32,
+    /// Optional profile of a returning guest
+    #[serde(default)]
+    pub guest_id: Option<i64>,
+    /// The room type to price the stay in; defaults to the type of the requested room, if any
+    #[serde(default)]
+    pub room_type: Option<String>,
+    /// A card tokenized by the payment provider, on which the price of the stay is authorized as
+    /// a deposit
+    #[serde(default)]
+    pub payment_token: Option<String>,
+    #[serde(default)]
+    pub promo_code: Option<String>,
+    /// The guest's currency, to show the price in as well; the stay is charged in the hotel's
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PreassignRoomRequest {
+    pub room_number: i32,
+    #[serde(default)]
+    pub pinned: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateHousekeepingStatusRequest {
+    pub status: HousekeepingStatus,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateGroupBookingRequest {
+    pub guest_name: String,
+    pub start_time: NaiveDate,
+    pub end_time: NaiveDate,
+    pub number_of_rooms: i32,
+    #[serde(default)]
+    pub constraint: GroupRoomConstraint,
+    /// The number of adults staying in each of the rooms
+    #[serde(default = "default_adults")]
+    pub adults_per_room: i32,
+    #[serde(default)]
+    pub children_per_room: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ModifyBookingRequest {
+    pub start_time: NaiveDate,
+    pub end_time: NaiveDate,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateHoldRequest {
+    pub start_time: NaiveDate,
+    pub end_time: NaiveDate,
+    /// How long the room is held for; defaults to 15 minutes
+    #[serde(default)]
+    pub hold_minutes: Option<i64>,
+    #[serde(default = "default_adults")]
+    pub adults: i32,
+    #[serde(default)]
+    pub children: i32,
+    #[serde(default)]
+    pub room_type: Option<String>,
+    /// The guest's currency, as in `CreateBookingRequest`
+    #[serde(default)]
+    pub currency: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConfirmHoldRequest {
+    pub guest_name: String,
+    #[serde(default)]
+    pub preferences: RoomPreferences,
+    #[serde(default)]
+    pub guest_id: Option<i64>,
+    /// A card for the deposit, as in `CreateBookingRequest`
+    #[serde(default)]
+    pub payment_token: Option<String>,
+}
+
+/// Creates or updates a guest profile
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GuestProfileRequest {
+    pub name: String,
+    #[serde(default)]
+    pub email: Option<String>,
+    #[serde(default)]
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub preferences: RoomPreferences,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JoinWaitlistRequest {
+    pub guest_name: String,
+    /// Where to notify the guest once a room frees up
+    #[serde(default)]
+    pub email: Option<String>,
+    pub start_time: NaiveDate,
+    pub end_time: NaiveDate,
+    #[serde(default = "default_adults")]
+    pub adults: i32,
+    #[serde(default)]
+    pub children: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WalkGuestRequest {
+    /// The hotel the guest was relocated to
+    #[serde(default)]
+    pub relocated_to: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateStayRestrictionRequest {
+    pub start_date: NaiveDate,
+    /// Exclusive
+    pub end_date: NaiveDate,
+    /// Days of the week on which the restriction applies; every day if empty
+    #[serde(default)]
+    pub days_of_week: Vec<Weekday>,
+    #[serde(default)]
+    pub min_stay: Option<i32>,
+    #[serde(default)]
+    pub closed_to_arrival: bool,
+    #[serde(default)]
+    pub closed_to_departure: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreatePromoCodeRequest {
+    pub code: String,
+    /// Valid at every hotel if not given
+    #[serde(default)]
+    pub hotel_id: Option<i64>,
+    pub discount: PromoDiscount,
+    #[serde(default)]
+    pub valid_from: Option<NaiveDate>,
+    #[serde(default)]
+    pub valid_until: Option<NaiveDate>,
+    /// Unlimited if not given
+    #[serde(default)]
+    pub max_uses: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateHotelRequest {
+    pub name: String,
+    pub room_count: i32,
+    /// ISO 4217 code of the currency the hotel's stays are priced and charged in
+    pub base_currency: String,
+    /// IANA time zone, e.g. "Europe/Warsaw"
+    pub time_zone: String,
+    /// Defaults to 15:00
+    #[serde(default)]
+    pub check_in_time: Option<NaiveTime>,
+    /// Defaults to 11:00
+    #[serde(default)]
+    pub check_out_time: Option<NaiveTime>,
+    /// Defaults to the end of the arrival day
+    #[serde(default)]
+    pub no_show_cutoff: Option<NaiveTime>,
+}
+
+/// Fields which are left out are kept as they are
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateHotelRequest {
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub room_count: Option<i32>,
+    #[serde(default)]
+    pub time_zone: Option<String>,
+    #[serde(default)]
+    pub check_in_time: Option<NaiveTime>,
+    #[serde(default)]
+    pub check_out_time: Option<NaiveTime>,
+}
+
+/// Fields which are left out are kept as they are
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateRoomRequest {
+    #[serde(default)]
+    pub max_occupancy: Option<i32>,
+    #[serde(default)]
+    pub room_type: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateExchangeRateRequest {
+    /// In millionths of the target currency per unit of the source currency
+    pub rate: i64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateTaxRuleRequest {
+    pub name: String,
+    pub charge: TaxCharge,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RatePlanRequest {
+    pub name: String,
+    /// Must be the hotel's base currency, which is the default
+    #[serde(default)]
+    pub currency: Option<String>,
+    /// In minor units of the currency
+    pub base_rate: i64,
+    #[serde(default)]
+    pub weekend_surcharge_percent: i32,
+    #[serde(default)]
+    pub seasons: Vec<SeasonalRate>,
+    #[serde(default)]
+    pub length_of_stay_discounts: Vec<LengthOfStayDiscount>,
+    #[serde(default)]
+    pub cancellation_policy: Option<CancellationPolicy>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostFolioEntryRequest {
+    /// Extra, payment or adjustment; room charges, taxes and cancellation fees are posted
+    /// automatically
+    pub kind: FolioEntryKind,
+    pub description: String,
+    /// In minor units. Extras and payments are positive; adjustments are negative for credits.
+    pub amount: i64,
+    /// The date the charge is for; defaults to today
+    #[serde(default)]
+    pub date: Option<NaiveDate>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RedeemLoyaltyPointsRequest {
+    pub points: i64,
+}
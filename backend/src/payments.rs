@@ -0,0 +1,378 @@
+use crate::db::{get_and_lock_open_authorizations, get_next_folio_entry_id};
+use crate::error::AppError;
+use crate::event_processor::EventProcessor;
+use crate::models::PaymentAuthorization;
+use crate::models_events::{
+    Event, PaymentAuthorizedEvent, PaymentCapturedEvent, PaymentVoidedEvent,
+};
+use chrono::Utc;
+use futures::future::BoxFuture;
+use sqlx::{Postgres, Transaction};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tracing::warn;
+
+#[derive(Debug)]
+pub enum PaymentError {
+    Declined { reason: String },
+    UnknownAuthorization { authorization_id: String },
+    Internal(anyhow::Error),
+}
+
+impl PaymentError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PaymentError::Declined { .. } => "PAYMENT_DECLINED",
+            PaymentError::UnknownAuthorization { .. } => "INVALID_AUTHORIZATION",
+            PaymentError::Internal(_) => "PAYMENT_ERROR",
+        }
+    }
+}
+
+impl fmt::Display for PaymentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PaymentError::Declined { reason } => write!(f, "The payment was declined: {}", reason),
+            PaymentError::UnknownAuthorization { authorization_id } => write!(
+                f,
+                "Authorization {} doesn't exist or is already closed",
+                authorization_id
+            ),
+            PaymentError::Internal(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<anyhow::Error> for PaymentError {
+    fn from(err: anyhow::Error) -> Self {
+        PaymentError::Internal(err)
+    }
+}
+
+impl From<PaymentError> for AppError {
+    fn from(err: PaymentError) -> Self {
+        match err {
+            PaymentError::Internal(e) => AppError::Internal(e),
+            e => AppError::bad_request(e.to_string(), e.code()),
+        }
+    }
+}
+
+/// Takes payments from guests' cards. Card details are tokenized by the provider on the client,
+/// so the backend only ever sees payment tokens.
+pub trait PaymentProvider: Send + Sync {
+    /// Reserves an amount (in minor units) on the card, returning the ID of the authorization.
+    fn authorize<'a>(
+        &'a self,
+        payment_token: &'a str,
+        amount: i64,
+        currency: &'a str,
+    ) -> BoxFuture<'a, Result<String, PaymentError>>;
+
+    /// Charges up to the authorized amount, which closes the authorization. Returns the ID of
+    /// the transaction.
+    fn capture<'a>(
+        &'a self,
+        authorization_id: &'a str,
+        amount: i64,
+    ) -> BoxFuture<'a, Result<String, PaymentError>>;
+
+    /// Releases an authorization without charging anything.
+    fn void<'a>(&'a self, authorization_id: &'a str) -> BoxFuture<'a, Result<(), PaymentError>>;
+}
+
+/// A payment token which the mock provider always declines
+pub const MOCK_DECLINED_TOKEN: &str = "tok_declined";
+
+/// Keeps authorizations in memory, for development and tests. Every token except
+/// `MOCK_DECLINED_TOKEN` is accepted.
+#[derive(Default)]
+pub struct MockPaymentProvider {
+    open_authorizations: Mutex<HashMap<String, i64>>,
+    next_id: AtomicU64,
+}
+
+impl MockPaymentProvider {
+    fn next_id(&self, prefix: &str) -> String {
+        format!(
+            "{}_{}",
+            prefix,
+            self.next_id.fetch_add(1, Ordering::Relaxed) + 1
+        )
+    }
+
+    fn close_authorization(&self, authorization_id: &str) -> Result<i64, PaymentError> {
+        self.open_authorizations
+            .lock()
+            .unwrap()
+            .remove(authorization_id)
+            .ok_or_else(|| PaymentError::UnknownAuthorization {
+                authorization_id: authorization_id.to_string(),
+            })
+    }
+}
+
+impl PaymentProvider for MockPaymentProvider {
+    fn authorize<'a>(
+        &'a self,
+        payment_token: &'a str,
+        amount: i64,
+        _currency: &'a str,
+    ) -> BoxFuture<'a, Result<String, PaymentError>> {
+        Box::pin(async move {
+            if payment_token == MOCK_DECLINED_TOKEN {
+                return Err(PaymentError::Declined {
+                    reason: "card declined".to_string(),
+                });
+            }
+            if amount <= 0 {
+                return Err(PaymentError::Declined {
+                    reason: "invalid amount".to_string(),
+                });
+            }
+
+            let authorization_id = self.next_id("mock_auth");
+            self.open_authorizations
+                .lock()
+                .unwrap()
+                .insert(authorization_id.clone(), amount);
+            Ok(authorization_id)
+        })
+    }
+
+    fn capture<'a>(
+        &'a self,
+        authorization_id: &'a str,
+        amount: i64,
+    ) -> BoxFuture<'a, Result<String, PaymentError>> {
+        Box::pin(async move {
+            let authorized = self
+                .open_authorizations
+                .lock()
+                .unwrap()
+                .get(authorization_id)
+                .copied();
+            if authorized.is_some_and(|authorized| amount <= 0 || amount > authorized) {
+                return Err(PaymentError::Declined {
+                    reason: "amount exceeds the authorization".to_string(),
+                });
+            }
+
+            self.close_authorization(authorization_id)?;
+            Ok(self.next_id("mock_txn"))
+        })
+    }
+
+    fn void<'a>(&'a self, authorization_id: &'a str) -> BoxFuture<'a, Result<(), PaymentError>> {
+        Box::pin(async move {
+            self.close_authorization(authorization_id)?;
+            Ok(())
+        })
+    }
+}
+
+/// Decides how much of each open deposit to capture to collect the given amount, taking the
+/// deposits in order. Deposits which aren't needed get 0, meaning they're voided.
+pub fn plan_captures(open_authorizations: &[PaymentAuthorization], amount: i64) -> Vec<i64> {
+    let mut remaining = amount.max(0);
+    open_authorizations
+        .iter()
+        .map(|authorization| {
+            let capture = remaining.min(authorization.amount);
+            remaining -= capture;
+            capture
+        })
+        .collect()
+}
+
+/// What the guest still owes (or is owed, if negative) once the open deposits have been used to
+/// settle the given balance.
+pub fn balance_after_settlement(open_authorizations: &[PaymentAuthorization], balance: i64) -> i64 {
+    balance
+        - plan_captures(open_authorizations, balance)
+            .iter()
+            .sum::<i64>()
+}
+
+/// Authorizes a deposit for a booking, recording it on the booking's stream.
+pub async fn authorize_deposit(
+    provider: &dyn PaymentProvider,
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+    payment_token: &str,
+    amount: i64,
+    currency: &str,
+) -> Result<String, PaymentError> {
+    let authorization_id = provider.authorize(payment_token, amount, currency).await?;
+
+    let event = Event::PaymentAuthorized(PaymentAuthorizedEvent {
+        booking_id,
+        authorization_id: authorization_id.clone(),
+        amount,
+        currency: currency.to_string(),
+        authorized_at: Utc::now(),
+    });
+    event_processor
+        .process_event_with_tx(tx, booking_id, event)
+        .await?;
+
+    Ok(authorization_id)
+}
+
+/// Closes the open deposits of a booking, capturing up to `amount` from them (or everything, if
+/// `amount` is `None`) and voiding the rest. Captured payments are credited on the folio. Returns
+/// the amount captured.
+///
+/// Cards are charged right away, so this has to be the last step of an operation which can fail.
+/// Once a card has been charged, failures no longer fail the operation (which would roll back the
+/// capture), and the deposits concerned are left open instead.
+pub async fn settle_deposits(
+    provider: &dyn PaymentProvider,
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    booking_id: i64,
+    amount: Option<i64>,
+) -> Result<i64, PaymentError> {
+    let open_authorizations = get_and_lock_open_authorizations(tx, booking_id).await?;
+    let amount = amount.unwrap_or_else(|| open_authorizations.iter().map(|a| a.amount).sum());
+    let captures = plan_captures(&open_authorizations, amount);
+
+    let mut captured = 0;
+    for (authorization, capture) in open_authorizations.iter().zip(captures) {
+        let authorization_id = authorization.authorization_id.clone();
+
+        let event = if capture > 0 {
+            let transaction_id = match provider.capture(&authorization_id, capture).await {
+                Ok(transaction_id) => transaction_id,
+                Err(e) if captured > 0 => {
+                    warn!(
+                        "Failed to capture deposit {} of booking {}: {}",
+                        authorization_id, booking_id, e
+                    );
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            captured += capture;
+            Event::PaymentCaptured(PaymentCapturedEvent {
+                booking_id,
+                authorization_id,
+                transaction_id,
+                amount: capture,
+                entry_id: get_next_folio_entry_id(tx).await?,
+                captured_at: Utc::now(),
+            })
+        } else {
+            match provider.void(&authorization_id).await {
+                // Nothing is held on the card anymore (e.g. the authorization has expired)
+                Ok(()) | Err(PaymentError::UnknownAuthorization { .. }) => {}
+                Err(e) if captured > 0 => {
+                    warn!(
+                        "Failed to void deposit {} of booking {}: {}",
+                        authorization_id, booking_id, e
+                    );
+                    continue;
+                }
+                Err(e) => return Err(e),
+            }
+            Event::PaymentVoided(PaymentVoidedEvent {
+                booking_id,
+                authorization_id,
+            })
+        };
+        event_processor
+            .process_event_with_tx(tx, booking_id, event)
+            .await?;
+    }
+
+    Ok(captured)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::AuthorizationStatus;
+
+    fn authorization(id: &str, amount: i64) -> PaymentAuthorization {
+        PaymentAuthorization {
+            authorization_id: id.to_string(),
+            booking_id: 1,
+            amount,
+            currency: "EUR".to_string(),
+            status: AuthorizationStatus::Authorized,
+            captured_amount: 0,
+            authorized_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_plan_captures_takes_deposits_in_order() {
+        let open = vec![authorization("a", 10000), authorization("b", 5000)];
+
+        assert_eq!(plan_captures(&open, 12000), vec![10000, 2000]);
+        assert_eq!(plan_captures(&open, 8000), vec![8000, 0]);
+        assert_eq!(plan_captures(&open, 20000), vec![10000, 5000]);
+        // Nothing is captured if the guest doesn't owe anything
+        assert_eq!(plan_captures(&open, -500), vec![0, 0]);
+    }
+
+    #[test]
+    fn test_balance_after_settlement() {
+        let open = vec![authorization("a", 10000), authorization("b", 5000)];
+
+        assert_eq!(balance_after_settlement(&open, 12000), 0);
+        // The deposits don't cover the whole balance
+        assert_eq!(balance_after_settlement(&open, 18000), 3000);
+        assert_eq!(balance_after_settlement(&[], 18000), 18000);
+        // Credits are left on the folio
+        assert_eq!(balance_after_settlement(&open, -500), -500);
+    }
+
+    #[tokio::test]
+    async fn test_mock_provider_captures_authorized_amount() {
+        let provider = MockPaymentProvider::default();
+
+        let authorization_id = provider.authorize("tok_visa", 10000, "EUR").await.unwrap();
+        assert!(provider.capture(&authorization_id, 7500).await.is_ok());
+
+        // Capturing closes the authorization
+        assert!(matches!(
+            provider.capture(&authorization_id, 2500).await,
+            Err(PaymentError::UnknownAuthorization { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_mock_provider_declines() {
+        let provider = MockPaymentProvider::default();
+
+        assert!(matches!(
+            provider.authorize(MOCK_DECLINED_TOKEN, 10000, "EUR").await,
+            Err(PaymentError::Declined { .. })
+        ));
+
+        let authorization_id = provider.authorize("tok_visa", 10000, "EUR").await.unwrap();
+        assert!(matches!(
+            provider.capture(&authorization_id, 10001).await,
+            Err(PaymentError::Declined { .. })
+        ));
+        // A declined capture leaves the authorization open
+        assert!(provider.capture(&authorization_id, 10000).await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_mock_provider_voids_authorization() {
+        let provider = MockPaymentProvider::default();
+
+        let authorization_id = provider.authorize("tok_visa", 10000, "EUR").await.unwrap();
+        assert!(provider.void(&authorization_id).await.is_ok());
+
+        assert!(matches!(
+            provider.capture(&authorization_id, 10000).await,
+            Err(PaymentError::UnknownAuthorization { .. })
+        ));
+    }
+}
@@ -0,0 +1,443 @@
+use crate::db::{get_rate_plan, get_tax_rules};
+use crate::models::{
+    AppliedPromo, Booking, CancellationPenalty, CancellationPolicy, NightlyPrice, PriceQuote,
+    PromoDiscount, RatePlan,
+};
+use crate::taxes::tax_nights;
+use anyhow::Result;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use sqlx::PgConnection;
+
+/// The given percentage of an amount, rounded half away from zero to whole minor units.
+fn percent_of(amount: i64, percent: i32) -> i64 {
+    let scaled = amount * i64::from(percent);
+    (scaled + 50 * scaled.signum()) / 100
+}
+
+/// The rate for the night starting on the given date: the rate of the season the night falls into
+/// (the first one listed, if seasons overlap) or the base rate, adjusted on weekend nights.
+fn nightly_rate(plan: &RatePlan, date: NaiveDate) -> i64 {
+    let rate = plan
+        .seasons
+        .iter()
+        .find(|season| season.start_date <= date && date < season.end_date)
+        .map_or(plan.base_rate, |season| season.nightly_rate);
+
+    match date.weekday() {
+        Weekday::Fri | Weekday::Sat => rate + percent_of(rate, plan.weekend_surcharge_percent),
+        _ => rate,
+    }
+}
+
+/// Prices a stay using a rate plan. If several length-of-stay discounts apply, the largest one is
+/// given.
+pub fn price_stay(plan: &RatePlan, start: NaiveDate, end: NaiveDate) -> PriceQuote {
+    let nights: Vec<NightlyPrice> = start
+        .iter_days()
+        .take_while(|date| *date < end)
+        .map(|date| NightlyPrice {
+            date,
+            amount: nightly_rate(plan, date),
+        })
+        .collect();
+
+    let subtotal = nights.iter().map(|night| night.amount).sum();
+    let discount_percent = plan
+        .length_of_stay_discounts
+        .iter()
+        .filter(|discount| discount.min_nights as usize <= nights.len())
+        .map(|discount| discount.percent)
+        .max()
+        .unwrap_or(0);
+    let discount = percent_of(subtotal, discount_percent);
+
+    PriceQuote {
+        rate_plan_id: plan.id,
+        room_type: plan.room_type.clone(),
+        currency: plan.currency.clone(),
+        nights,
+        subtotal,
+        discount,
+        total: subtotal - discount,
+        cancellation_policy: plan.cancellation_policy.clone(),
+        taxes: vec![],
+        promo: None,
+        converted: None,
+    }
+}
+
+/// Takes a promo code's discount off a quote, up to the price of the room. Returns false if the
+/// discount doesn't apply to the quote, because it is fixed in another currency.
+pub fn apply_promo(quote: &mut PriceQuote, code: &str, discount: &PromoDiscount) -> bool {
+    let amount = match discount {
+        PromoDiscount::Percentage(percent) => percent_of(quote.total, *percent),
+        PromoDiscount::Fixed { amount, currency } if *currency == quote.currency => *amount,
+        PromoDiscount::Fixed { .. } => return false,
+    }
+    .min(quote.total);
+
+    quote.total -= amount;
+    quote.promo = Some(AppliedPromo {
+        code: code.to_string(),
+        discount: discount.clone(),
+        amount,
+    });
+    true
+}
+
+/// The nightly prices of a quote with its discounts spread over the nights in proportion to their
+/// prices, so that they add up to the quote's total. Any remainder goes to the last night.
+pub fn discounted_nights(quote: &PriceQuote) -> Vec<NightlyPrice> {
+    let mut nights = quote.nights.clone();
+    let discount = quote.subtotal - quote.total;
+    if discount == 0 || quote.subtotal == 0 {
+        return nights;
+    }
+
+    let mut remaining_discount = discount;
+    for night in &mut nights {
+        let share = night.amount * discount / quote.subtotal;
+        night.amount -= share;
+        remaining_discount -= share;
+    }
+    if let Some(last) = nights.last_mut() {
+        last.amount -= remaining_discount;
+    }
+
+    nights
+}
+
+/// The last day on which a stay arriving on the given date can be cancelled for free.
+pub fn free_cancellation_deadline(policy: &CancellationPolicy, arrival: NaiveDate) -> NaiveDate {
+    arrival - Duration::days(i64::from(policy.free_until_days_before))
+}
+
+/// The penalty for cancelling a booking on the given day, under the cancellation policy it was
+/// quoted with. Unpriced bookings, and bookings quoted without a policy, are cancelled for free.
+pub fn cancellation_fee(booking: &Booking, today: NaiveDate) -> i64 {
+    let Some(quote) = &booking.quote else {
+        return 0;
+    };
+    let Some(policy) = &quote.cancellation_policy else {
+        return 0;
+    };
+    if today <= free_cancellation_deadline(policy, booking.start_time) {
+        return 0;
+    }
+
+    penalty(quote, policy)
+}
+
+/// The penalty for not turning up, which is that of the booking's cancellation policy even if the
+/// policy allows cancelling on the arrival date for free. Bookings without a policy pay nothing.
+pub fn no_show_fee(booking: &Booking) -> i64 {
+    booking
+        .quote
+        .as_ref()
+        .and_then(|quote| Some(penalty(quote, quote.cancellation_policy.as_ref()?)))
+        .unwrap_or(0)
+}
+
+fn penalty(quote: &PriceQuote, policy: &CancellationPolicy) -> i64 {
+    match policy.penalty {
+        CancellationPenalty::Percentage(percent) => percent_of(quote.total, percent),
+        CancellationPenalty::Nights(nights) => discounted_nights(quote)
+            .iter()
+            .take(usize::try_from(nights).unwrap_or(0))
+            .map(|night| night.amount)
+            .sum(),
+    }
+}
+
+/// Prices a stay in a room type of a hotel, using the hotel's current rate plan for it and adding
+/// the hotel's taxes for the given number of guests. A promo code's discount is taken off before
+/// taxes, if it applies. Returns `None` if the hotel doesn't have a rate plan for the room type.
+pub async fn quote_stay(
+    conn: &mut PgConnection,
+    hotel_id: i64,
+    room_type: &str,
+    start: NaiveDate,
+    end: NaiveDate,
+    guests: i32,
+    promo: Option<(&str, &PromoDiscount)>,
+) -> Result<Option<PriceQuote>> {
+    let Some(plan) = get_rate_plan(&mut *conn, hotel_id, room_type).await? else {
+        return Ok(None);
+    };
+    let rules = get_tax_rules(&mut *conn, hotel_id).await?;
+
+    let mut quote = price_stay(&plan, start, end);
+    if let Some((code, discount)) = promo {
+        apply_promo(&mut quote, code, discount);
+    }
+    // Taxes are levied on what the guest pays for the room, after discounts
+    quote.taxes = tax_nights(&rules, &discounted_nights(&quote), guests);
+    Ok(Some(quote))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{BookingStatus, LengthOfStayDiscount, RoomPreferences, SeasonalRate};
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn plan(base_rate: i64) -> RatePlan {
+        RatePlan {
+            id: 1,
+            hotel_id: 1,
+            room_type: "standard".to_string(),
+            name: "Flexible".to_string(),
+            currency: "EUR".to_string(),
+            base_rate,
+            weekend_surcharge_percent: 0,
+            seasons: vec![],
+            length_of_stay_discounts: vec![],
+            cancellation_policy: None,
+        }
+    }
+
+    fn amounts(quote: &PriceQuote) -> Vec<i64> {
+        quote.nights.iter().map(|night| night.amount).collect()
+    }
+
+    #[test]
+    fn test_prices_each_night_at_base_rate() {
+        // Monday Jan 1 to Thursday Jan 4
+        let quote = price_stay(&plan(10000), date(1, 1), date(1, 4));
+
+        assert_eq!(amounts(&quote), vec![10000, 10000, 10000]);
+        assert_eq!(quote.subtotal, 30000);
+        assert_eq!(quote.discount, 0);
+        assert_eq!(quote.total, 30000);
+        assert_eq!(quote.currency, "EUR");
+    }
+
+    #[test]
+    fn test_seasonal_rates_override_base_rate() {
+        let plan = RatePlan {
+            seasons: vec![SeasonalRate {
+                start_date: date(7, 2),
+                end_date: date(7, 4),
+                nightly_rate: 15000,
+            }],
+            ..plan(10000)
+        };
+
+        // Monday Jul 1 to Thursday Jul 4
+        let quote = price_stay(&plan, date(7, 1), date(7, 4));
+
+        assert_eq!(amounts(&quote), vec![10000, 15000, 15000]);
+        assert_eq!(quote.total, 40000);
+    }
+
+    #[test]
+    fn test_weekend_surcharge_is_rounded_to_minor_units() {
+        let plan = RatePlan {
+            weekend_surcharge_percent: 15,
+            ..plan(9999)
+        };
+
+        // Thursday Jan 4 to Sunday Jan 7: 9999 * 1.15 = 11498.85
+        let quote = price_stay(&plan, date(1, 4), date(1, 7));
+
+        assert_eq!(amounts(&quote), vec![9999, 11499, 11499]);
+        assert_eq!(quote.total, 32997);
+    }
+
+    #[test]
+    fn test_largest_applicable_length_of_stay_discount_is_given() {
+        let plan = RatePlan {
+            length_of_stay_discounts: vec![
+                LengthOfStayDiscount {
+                    min_nights: 3,
+                    percent: 5,
+                },
+                LengthOfStayDiscount {
+                    min_nights: 7,
+                    percent: 10,
+                },
+                LengthOfStayDiscount {
+                    min_nights: 14,
+                    percent: 20,
+                },
+            ],
+            ..plan(10000)
+        };
+
+        let week = price_stay(&plan, date(1, 1), date(1, 8));
+        assert_eq!(week.subtotal, 70000);
+        assert_eq!(week.discount, 7000);
+        assert_eq!(week.total, 63000);
+
+        let two_nights = price_stay(&plan, date(1, 1), date(1, 3));
+        assert_eq!(two_nights.discount, 0);
+    }
+
+    #[test]
+    fn test_discounted_nights_add_up_to_total() {
+        let plan = RatePlan {
+            weekend_surcharge_percent: 20,
+            length_of_stay_discounts: vec![LengthOfStayDiscount {
+                min_nights: 3,
+                percent: 7,
+            }],
+            ..plan(9999)
+        };
+
+        // Thursday Jan 4 to Sunday Jan 7
+        let quote = price_stay(&plan, date(1, 4), date(1, 7));
+        let nights = discounted_nights(&quote);
+
+        assert_eq!(nights.iter().map(|n| n.amount).sum::<i64>(), quote.total);
+        assert!(nights[0].amount < nights[1].amount);
+    }
+
+    fn booking(plan: &RatePlan, start: NaiveDate, end: NaiveDate) -> Booking {
+        Booking {
+            id: 1,
+            hotel_id: 1,
+            room_number: None,
+            room_pinned: false,
+            guest_name: "Jane Doe".to_string(),
+            start_time: start,
+            end_time: end,
+            status: BookingStatus::Confirmed,
+            preferences: RoomPreferences::default(),
+            hold_expires_at: None,
+            group_id: None,
+            adults: 1,
+            children: 0,
+            guest_id: None,
+            quote: Some(price_stay(plan, start, end)),
+            room_type: None,
+        }
+    }
+
+    #[test]
+    fn test_cancellation_is_free_until_deadline() {
+        let plan = RatePlan {
+            cancellation_policy: Some(CancellationPolicy {
+                free_until_days_before: 7,
+                penalty: CancellationPenalty::Percentage(50),
+            }),
+            ..plan(10000)
+        };
+        let booking = booking(&plan, date(3, 11), date(3, 14));
+
+        assert_eq!(cancellation_fee(&booking, date(3, 1)), 0);
+        assert_eq!(cancellation_fee(&booking, date(3, 4)), 0);
+        assert_eq!(cancellation_fee(&booking, date(3, 5)), 15000);
+        assert_eq!(cancellation_fee(&booking, date(3, 11)), 15000);
+    }
+
+    #[test]
+    fn test_no_shows_pay_the_penalty_even_if_arrival_day_is_free() {
+        let plan = RatePlan {
+            cancellation_policy: Some(CancellationPolicy {
+                free_until_days_before: 0,
+                penalty: CancellationPenalty::Nights(1),
+            }),
+            ..plan(10000)
+        };
+        let booking = booking(&plan, date(3, 11), date(3, 14));
+
+        assert_eq!(cancellation_fee(&booking, date(3, 11)), 0);
+        assert_eq!(no_show_fee(&booking), 10000);
+
+        let booking = Booking {
+            quote: None,
+            ..booking
+        };
+        assert_eq!(no_show_fee(&booking), 0);
+    }
+
+    #[test]
+    fn test_nights_penalty_charges_first_discounted_nights() {
+        let plan = RatePlan {
+            weekend_surcharge_percent: 50,
+            length_of_stay_discounts: vec![LengthOfStayDiscount {
+                min_nights: 3,
+                percent: 10,
+            }],
+            cancellation_policy: Some(CancellationPolicy {
+                free_until_days_before: 1,
+                penalty: CancellationPenalty::Nights(2),
+            }),
+            ..plan(10000)
+        };
+        // Friday Jan 5 to Monday Jan 8: 15000, 15000 and 10000, less 10%
+        let booking = booking(&plan, date(1, 5), date(1, 8));
+
+        assert_eq!(cancellation_fee(&booking, date(1, 5)), 27000);
+
+        // Bookings made without a policy are free to cancel
+        let booking = Booking {
+            quote: Some(price_stay(
+                &RatePlan {
+                    cancellation_policy: None,
+                    ..plan
+                },
+                date(1, 5),
+                date(1, 8),
+            )),
+            ..booking
+        };
+        assert_eq!(cancellation_fee(&booking, date(1, 5)), 0);
+    }
+
+    #[test]
+    fn test_promo_discount_is_spread_over_nights() {
+        let plan = RatePlan {
+            length_of_stay_discounts: vec![LengthOfStayDiscount {
+                min_nights: 2,
+                percent: 10,
+            }],
+            ..plan(10000)
+        };
+        let mut quote = price_stay(&plan, date(1, 1), date(1, 3));
+
+        // 15% off what's left after the length of stay discount
+        assert!(apply_promo(
+            &mut quote,
+            "SPRING",
+            &PromoDiscount::Percentage(15)
+        ));
+
+        assert_eq!(quote.discount, 2000);
+        assert_eq!(quote.promo.as_ref().unwrap().amount, 2700);
+        assert_eq!(quote.total, 15300);
+        let nights = discounted_nights(&quote);
+        assert_eq!(amounts(&PriceQuote { nights, ..quote }), vec![7650, 7650]);
+    }
+
+    #[test]
+    fn test_fixed_promo_discount_needs_same_currency() {
+        let mut quote = price_stay(&plan(10000), date(1, 1), date(1, 2));
+
+        let usd = PromoDiscount::Fixed {
+            amount: 2500,
+            currency: "USD".to_string(),
+        };
+        assert!(!apply_promo(&mut quote, "WELCOME", &usd));
+        assert_eq!(quote.total, 10000);
+        assert!(quote.promo.is_none());
+
+        // The discount can't exceed the price of the room
+        let eur = PromoDiscount::Fixed {
+            amount: 25000,
+            currency: "EUR".to_string(),
+        };
+        assert!(apply_promo(&mut quote, "WELCOME", &eur));
+        assert_eq!(quote.total, 0);
+    }
+
+    #[test]
+    fn test_percent_of_rounds_half_away_from_zero() {
+        assert_eq!(percent_of(150, 1), 2);
+        assert_eq!(percent_of(149, 1), 1);
+        assert_eq!(percent_of(150, -1), -2);
+    }
+}
@@ -1,15 +1,18 @@
-use crate::models::BookingStatus;
+use crate::models::{
+    AuthorizationStatus, BookingStatus, FolioEntryKind, LoyaltyTransactionKind, WaitlistStatus,
+};
 use crate::models_events::Event;
 use anyhow::Result;
-use sqlx::{Postgres, Transaction};
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, Transaction, types::Json};
 
-pub async fn handle_booking_event(tx: &mut Transaction<'_, Postgres>, event: &Event) -> Result<()> {
+pub async fn handle_event(tx: &mut Transaction<'_, Postgres>, event: &Event) -> Result<()> {
     match event {
         Event::BookingCreated(booking_event) => {
             // Insert booking into projections table
             sqlx::query(
-                "INSERT INTO bookings (id, hotel_id, room_number, guest_name, start_time, end_time, status) 
-                 VALUES ($1, $2, $3, $4, $5, $6, $7)"
+                "INSERT INTO bookings (id, hotel_id, room_number, guest_name, start_time, end_time, status, preferences, group_id, adults, children, guest_id, quote, room_type) 
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)"
             )
             .bind(booking_event.booking_id)
             .bind(booking_event.hotel_id)
@@ -18,11 +21,104 @@ pub async fn handle_booking_event(tx: &mut Transaction<'_, Postgres>, event: &Ev
             .bind(booking_event.start_time)
             .bind(booking_event.end_time)
             .bind(BookingStatus::Confirmed.to_string())
+            .bind(Json(&booking_event.preferences))
+            .bind(booking_event.group_id)
+            .bind(booking_event.adults)
+            .bind(booking_event.children)
+            .bind(booking_event.guest_id)
+            .bind(booking_event.quote.as_ref().map(Json))
+            .bind(&booking_event.room_type)
             .execute(&mut **tx)
             .await?;
             
             Ok(())
         }
+        Event::BookingHeld(hold_event) => {
+            // Insert a held booking; the guest's details are filled in on confirmation
+            sqlx::query(
+                "INSERT INTO bookings (id, hotel_id, room_number, guest_name, start_time, end_time, status, hold_expires_at, adults, children, quote, room_type) 
+                 VALUES ($1, $2, NULL, '', $3, $4, $5, $6, $7, $8, $9, $10)"
+            )
+            .bind(hold_event.booking_id)
+            .bind(hold_event.hotel_id)
+            .bind(hold_event.start_time)
+            .bind(hold_event.end_time)
+            .bind(BookingStatus::Held.to_string())
+            .bind(hold_event.expires_at)
+            .bind(hold_event.adults)
+            .bind(hold_event.children)
+            .bind(hold_event.quote.as_ref().map(Json))
+            .bind(&hold_event.room_type)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::BookingHoldConfirmed(confirm_event) => {
+            sqlx::query(
+                "UPDATE bookings SET status = $1, guest_name = $2, preferences = $3, guest_id = $4, hold_expires_at = NULL WHERE id = $5"
+            )
+            .bind(BookingStatus::Confirmed.to_string())
+            .bind(&confirm_event.guest_name)
+            .bind(Json(&confirm_event.preferences))
+            .bind(confirm_event.guest_id)
+            .bind(confirm_event.booking_id)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::BookingHoldExpired(expire_event) => {
+            // Update booking status to expired, releasing any pre-assigned room
+            sqlx::query(
+                "UPDATE bookings SET status = $1, room_number = NULL, room_pinned = FALSE WHERE id = $2"
+            )
+            .bind(BookingStatus::Expired.to_string())
+            .bind(expire_event.booking_id)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::BookingRoomPreassigned(preassign_event) => {
+            // Pre-assign the room, without changing the booking status
+            sqlx::query(
+                "UPDATE bookings SET room_number = $1, room_pinned = $2 WHERE id = $3"
+            )
+            .bind(preassign_event.room_number)
+            .bind(preassign_event.pinned)
+            .bind(preassign_event.booking_id)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::BookingRoomReassigned(reassign_event) => {
+            // Move the pre-assignment to another room
+            sqlx::query(
+                "UPDATE bookings SET room_number = $1 WHERE id = $2"
+            )
+            .bind(reassign_event.to_room)
+            .bind(reassign_event.booking_id)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::BookingDatesChanged(dates_event) => {
+            // Move or resize the stay, re-pricing it if it was priced
+            sqlx::query(
+                "UPDATE bookings SET start_time = $1, end_time = $2, quote = COALESCE($3, quote) WHERE id = $4"
+            )
+            .bind(dates_event.start_time)
+            .bind(dates_event.end_time)
+            .bind(dates_event.quote.as_ref().map(Json))
+            .bind(dates_event.booking_id)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
         Event::BookingCheckedIn(checkin_event) => {
             // Update booking status to checked_in and assign room
             sqlx::query(
@@ -38,12 +134,20 @@ pub async fn handle_booking_event(tx: &mut Transaction<'_, Postgres>, event: &Ev
         }
         Event::BookingCheckedOut(checkout_event) => {
             // Update booking status to checked_out and free up the room
+            // On early checkout, the stay is shortened (to at least one night), so that the
+            // remaining nights become available again
             sqlx::query(
-                "UPDATE bookings SET status = $1, room_number = $2 WHERE id = $3"
+                "UPDATE bookings SET status = $1, room_number = $2, room_pinned = FALSE,
+                 end_time = CASE
+                     WHEN $4::date IS NULL THEN end_time
+                     ELSE LEAST(end_time, GREATEST($4::date, start_time + 1))
+                 END
+                 WHERE id = $3"
             )
             .bind(BookingStatus::CheckedOut.to_string())
             .bind(None::<i32>) // Clear room assignment
             .bind(checkout_event.booking_id)
+            .bind(checkout_event.departure_date)
             .execute(&mut **tx)
             .await?;
             
@@ -59,7 +163,298 @@ pub async fn handle_booking_event(tx: &mut Transaction<'_, Postgres>, event: &Ev
             .execute(&mut **tx)
             .await?;
             
+            Ok(())
+        }
+        Event::BookingMarkedNoShow(no_show_event) => {
+            // Update booking status to no-show, releasing any pre-assigned room
+            sqlx::query(
+                "UPDATE bookings SET status = $1, room_number = NULL, room_pinned = FALSE WHERE id = $2"
+            )
+            .bind(BookingStatus::NoShow.to_string())
+            .bind(no_show_event.booking_id)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::BookingWalked(walk_event) => {
+            // Update booking status to walked, releasing any pre-assigned room
+            sqlx::query(
+                "UPDATE bookings SET status = $1, room_number = NULL, room_pinned = FALSE WHERE id = $2"
+            )
+            .bind(BookingStatus::Walked.to_string())
+            .bind(walk_event.booking_id)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::RoomHousekeepingStatusChanged(housekeeping_event) => {
+            // Rooms without configured features don't have a row yet, so upsert
+            sqlx::query(
+                "INSERT INTO rooms (hotel_id, room_number, housekeeping_status) VALUES ($1, $2, $3)
+                 ON CONFLICT (hotel_id, room_number) DO UPDATE SET housekeeping_status = EXCLUDED.housekeeping_status"
+            )
+            .bind(housekeeping_event.hotel_id)
+            .bind(housekeeping_event.room_number)
+            .bind(housekeeping_event.status.to_string())
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::GuestProfileCreated(guest_event) | Event::GuestProfileUpdated(guest_event) => {
+            // Both events carry the full profile
+            sqlx::query(
+                "INSERT INTO guests (id, name, email, phone, preferences) VALUES ($1, $2, $3, $4, $5)
+                 ON CONFLICT (id) DO UPDATE SET name = EXCLUDED.name, email = EXCLUDED.email, phone = EXCLUDED.phone, preferences = EXCLUDED.preferences"
+            )
+            .bind(guest_event.guest_id)
+            .bind(&guest_event.name)
+            .bind(&guest_event.email)
+            .bind(&guest_event.phone)
+            .bind(Json(&guest_event.preferences))
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::WaitlistJoined(waitlist_event) => {
+            sqlx::query(
+                "INSERT INTO waitlist_entries (id, hotel_id, guest_name, email, start_time, end_time, adults, children, status)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9)"
+            )
+            .bind(waitlist_event.entry_id)
+            .bind(waitlist_event.hotel_id)
+            .bind(&waitlist_event.guest_name)
+            .bind(&waitlist_event.email)
+            .bind(waitlist_event.start_time)
+            .bind(waitlist_event.end_time)
+            .bind(waitlist_event.adults)
+            .bind(waitlist_event.children)
+            .bind(WaitlistStatus::Waiting.to_string())
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::WaitlistOffered(offer_event) => {
+            sqlx::query(
+                "UPDATE waitlist_entries SET status = $1, booking_id = $2 WHERE id = $3"
+            )
+            .bind(WaitlistStatus::Offered.to_string())
+            .bind(offer_event.booking_id)
+            .bind(offer_event.entry_id)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::WaitlistLeft(leave_event) => {
+            sqlx::query(
+                "UPDATE waitlist_entries SET status = $1 WHERE id = $2"
+            )
+            .bind(WaitlistStatus::Cancelled.to_string())
+            .bind(leave_event.entry_id)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::FolioEntryPosted(entry_event) => {
+            sqlx::query(
+                "INSERT INTO folio_entries (id, booking_id, kind, description, amount, date, posted_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)"
+            )
+            .bind(entry_event.entry_id)
+            .bind(entry_event.booking_id)
+            .bind(entry_event.kind.to_string())
+            .bind(&entry_event.description)
+            .bind(entry_event.amount)
+            .bind(entry_event.date)
+            .bind(entry_event.posted_at)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::InvoiceIssued(invoice_event) => {
+            let invoice = &invoice_event.invoice;
+            sqlx::query(
+                "INSERT INTO invoices (hotel_id, invoice_number, booking_id, issued_at, document)
+                 VALUES ($1, $2, $3, $4, $5)"
+            )
+            .bind(invoice.hotel_id)
+            .bind(invoice.invoice_number)
+            .bind(invoice.booking_id)
+            .bind(invoice.issued_at)
+            .bind(Json(invoice))
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::PaymentAuthorized(payment_event) => {
+            sqlx::query(
+                "INSERT INTO payment_authorizations (authorization_id, booking_id, amount, currency, status, authorized_at)
+                 VALUES ($1, $2, $3, $4, $5, $6)"
+            )
+            .bind(&payment_event.authorization_id)
+            .bind(payment_event.booking_id)
+            .bind(payment_event.amount)
+            .bind(&payment_event.currency)
+            .bind(AuthorizationStatus::Authorized.to_string())
+            .bind(payment_event.authorized_at)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::PaymentCaptured(payment_event) => {
+            sqlx::query(
+                "UPDATE payment_authorizations SET status = $1, captured_amount = $2 WHERE authorization_id = $3"
+            )
+            .bind(AuthorizationStatus::Captured.to_string())
+            .bind(payment_event.amount)
+            .bind(&payment_event.authorization_id)
+            .execute(&mut **tx)
+            .await?;
+
+            // Credit the payment on the folio
+            sqlx::query(
+                "INSERT INTO folio_entries (id, booking_id, kind, description, amount, date, posted_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)"
+            )
+            .bind(payment_event.entry_id)
+            .bind(payment_event.booking_id)
+            .bind(FolioEntryKind::Payment.to_string())
+            .bind(format!("Card payment ({})", payment_event.transaction_id))
+            .bind(-payment_event.amount)
+            .bind(payment_event.captured_at.date_naive())
+            .bind(payment_event.captured_at)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::PaymentVoided(payment_event) => {
+            sqlx::query(
+                "UPDATE payment_authorizations SET status = $1 WHERE authorization_id = $2"
+            )
+            .bind(AuthorizationStatus::Voided.to_string())
+            .bind(&payment_event.authorization_id)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::PaymentDeclined(_) => {
+            // Only kept in the event log, for the audit trail
+            Ok(())
+        }
+        Event::LoyaltyPointsEarned(loyalty_event) => {
+            record_loyalty_transaction(
+                tx,
+                loyalty_event.guest_id,
+                loyalty_event.booking_id,
+                LoyaltyTransactionKind::Earned,
+                loyalty_event.points,
+                0,
+                loyalty_event.earned_at,
+            )
+            .await
+        }
+        Event::LoyaltyPointsRedeemed(loyalty_event) => {
+            record_loyalty_transaction(
+                tx,
+                loyalty_event.guest_id,
+                loyalty_event.booking_id,
+                LoyaltyTransactionKind::Redeemed,
+                -loyalty_event.points,
+                loyalty_event.amount,
+                loyalty_event.redeemed_at,
+            )
+            .await?;
+
+            // Credit the value of the points on the folio
+            sqlx::query(
+                "INSERT INTO folio_entries (id, booking_id, kind, description, amount, date, posted_at)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)"
+            )
+            .bind(loyalty_event.entry_id)
+            .bind(loyalty_event.booking_id)
+            .bind(FolioEntryKind::Payment.to_string())
+            .bind(format!("Loyalty points ({})", loyalty_event.points))
+            .bind(-loyalty_event.amount)
+            .bind(loyalty_event.redeemed_at.date_naive())
+            .bind(loyalty_event.redeemed_at)
+            .execute(&mut **tx)
+            .await?;
+
+            Ok(())
+        }
+        Event::LoyaltyPointsReversed(loyalty_event) => {
+            record_loyalty_transaction(
+                tx,
+                loyalty_event.guest_id,
+                loyalty_event.booking_id,
+                LoyaltyTransactionKind::Reversed,
+                loyalty_event.points,
+                loyalty_event.amount,
+                loyalty_event.reversed_at,
+            )
+            .await?;
+
+            // Take back the credit for the points which were given back
+            if let Some(entry_id) = loyalty_event.entry_id {
+                sqlx::query(
+                    "INSERT INTO folio_entries (id, booking_id, kind, description, amount, date, posted_at)
+                     VALUES ($1, $2, $3, $4, $5, $6, $7)"
+                )
+                .bind(entry_id)
+                .bind(loyalty_event.booking_id)
+                .bind(FolioEntryKind::Adjustment.to_string())
+                .bind("Loyalty points refunded")
+                .bind(-loyalty_event.amount)
+                .bind(loyalty_event.reversed_at.date_naive())
+                .bind(loyalty_event.reversed_at)
+                .execute(&mut **tx)
+                .await?;
+            }
+
             Ok(())
         }
     }
+}
+
+/// Adds a transaction to a guest's loyalty ledger, and applies it to their balance
+async fn record_loyalty_transaction(
+    tx: &mut Transaction<'_, Postgres>,
+    guest_id: i64,
+    booking_id: i64,
+    kind: LoyaltyTransactionKind,
+    points: i64,
+    amount: i64,
+    created_at: DateTime<Utc>,
+) -> Result<()> {
+    sqlx::query(
+        "INSERT INTO loyalty_transactions (guest_id, booking_id, kind, points, amount, created_at)
+         VALUES ($1, $2, $3, $4, $5, $6)"
+    )
+    .bind(guest_id)
+    .bind(booking_id)
+    .bind(kind.to_string())
+    .bind(points)
+    .bind(amount)
+    .bind(created_at)
+    .execute(&mut **tx)
+    .await?;
+
+    sqlx::query("UPDATE guests SET loyalty_points = loyalty_points + $1 WHERE id = $2")
+        .bind(points)
+        .bind(guest_id)
+        .execute(&mut **tx)
+        .await?;
+
+    Ok(())
 }
\ No newline at end of file
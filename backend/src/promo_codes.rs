@@ -0,0 +1,185 @@
+use crate::db::{get_promo_code, increment_promo_code_usage};
+use crate::error::AppError;
+use crate::models::PromoCode;
+use chrono::NaiveDate;
+use sqlx::PgConnection;
+use std::fmt;
+
+#[derive(Debug)]
+pub enum PromoCodeError {
+    NotFound,
+    WrongHotel,
+    OutsideValidity,
+    UsedUp,
+    /// The stay isn't priced, or is priced in another currency than the code's fixed discount
+    NotApplicable,
+    Internal(anyhow::Error),
+}
+
+impl PromoCodeError {
+    pub fn code(&self) -> &'static str {
+        match self {
+            PromoCodeError::NotFound => "PROMO_CODE_NOT_FOUND",
+            PromoCodeError::WrongHotel | PromoCodeError::OutsideValidity => "PROMO_CODE_NOT_VALID",
+            PromoCodeError::UsedUp => "PROMO_CODE_USED_UP",
+            PromoCodeError::NotApplicable => "PROMO_CODE_NOT_APPLICABLE",
+            PromoCodeError::Internal(_) => "INTERNAL_ERROR",
+        }
+    }
+}
+
+impl fmt::Display for PromoCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PromoCodeError::NotFound => write!(f, "The promo code doesn't exist"),
+            PromoCodeError::WrongHotel => write!(f, "The promo code isn't valid at this hotel"),
+            PromoCodeError::OutsideValidity => {
+                write!(
+                    f,
+                    "The promo code isn't valid for stays arriving on this date"
+                )
+            }
+            PromoCodeError::UsedUp => write!(f, "The promo code has been used up"),
+            PromoCodeError::NotApplicable => {
+                write!(f, "The promo code doesn't apply to the price of this stay")
+            }
+            PromoCodeError::Internal(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<anyhow::Error> for PromoCodeError {
+    fn from(err: anyhow::Error) -> Self {
+        PromoCodeError::Internal(err)
+    }
+}
+
+impl From<PromoCodeError> for AppError {
+    fn from(err: PromoCodeError) -> Self {
+        match err {
+            PromoCodeError::Internal(e) => AppError::Internal(e),
+            e => AppError::bad_request(e.to_string(), e.code()),
+        }
+    }
+}
+
+/// Promo codes are matched case-insensitively, and stored in upper case.
+pub fn normalize_promo_code(code: &str) -> String {
+    code.trim().to_uppercase()
+}
+
+/// Checks whether a promo code can still be used for a stay at a hotel.
+pub fn check_promo_code(
+    promo: &PromoCode,
+    hotel_id: i64,
+    arrival: NaiveDate,
+) -> Result<(), PromoCodeError> {
+    if promo.hotel_id.is_some_and(|id| id != hotel_id) {
+        return Err(PromoCodeError::WrongHotel);
+    }
+    if promo.valid_from.is_some_and(|from| arrival < from)
+        || promo.valid_until.is_some_and(|until| arrival > until)
+    {
+        return Err(PromoCodeError::OutsideValidity);
+    }
+    if promo
+        .max_uses
+        .is_some_and(|max_uses| promo.used_count >= max_uses)
+    {
+        return Err(PromoCodeError::UsedUp);
+    }
+
+    Ok(())
+}
+
+/// Uses up one redemption of a promo code for a stay at a hotel. The usage is counted with a single
+/// conditional update, so that concurrent bookings can't exceed the code's limit; it's undone if
+/// the transaction is rolled back.
+pub async fn redeem_promo_code(
+    conn: &mut PgConnection,
+    code: &str,
+    hotel_id: i64,
+    arrival: NaiveDate,
+) -> Result<PromoCode, PromoCodeError> {
+    let code = normalize_promo_code(code);
+    if let Some(promo) = increment_promo_code_usage(&mut *conn, &code, hotel_id, arrival).await? {
+        return Ok(promo);
+    }
+
+    // Find out why the code couldn't be redeemed
+    let promo = get_promo_code(&mut *conn, &code)
+        .await?
+        .ok_or(PromoCodeError::NotFound)?;
+    check_promo_code(&promo, hotel_id, arrival)?;
+    // The code was used up by a concurrent booking in the meantime
+    Err(PromoCodeError::UsedUp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::PromoDiscount;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 6, day).unwrap()
+    }
+
+    fn promo() -> PromoCode {
+        PromoCode {
+            code: "SUMMER".to_string(),
+            hotel_id: Some(1),
+            discount: PromoDiscount::Percentage(10),
+            valid_from: Some(date(1)),
+            valid_until: Some(date(30)),
+            max_uses: Some(2),
+            used_count: 0,
+        }
+    }
+
+    #[test]
+    fn test_promo_code_is_checked_against_hotel_and_dates() {
+        assert!(check_promo_code(&promo(), 1, date(1)).is_ok());
+        assert!(check_promo_code(&promo(), 1, date(30)).is_ok());
+        assert!(matches!(
+            check_promo_code(&promo(), 2, date(10)),
+            Err(PromoCodeError::WrongHotel)
+        ));
+        assert!(matches!(
+            check_promo_code(&promo(), 1, date(1).pred_opt().unwrap()),
+            Err(PromoCodeError::OutsideValidity)
+        ));
+
+        // Codes without a scope are valid at every hotel, at any time
+        let unrestricted = PromoCode {
+            hotel_id: None,
+            valid_from: None,
+            valid_until: None,
+            ..promo()
+        };
+        assert!(check_promo_code(&unrestricted, 2, date(1).pred_opt().unwrap()).is_ok());
+    }
+
+    #[test]
+    fn test_promo_code_usage_limit() {
+        let used_up = PromoCode {
+            used_count: 2,
+            ..promo()
+        };
+        assert!(matches!(
+            check_promo_code(&used_up, 1, date(10)),
+            Err(PromoCodeError::UsedUp)
+        ));
+
+        let unlimited = PromoCode {
+            max_uses: None,
+            used_count: 1000,
+            ..promo()
+        };
+        assert!(check_promo_code(&unlimited, 1, date(10)).is_ok());
+    }
+
+    #[test]
+    fn test_promo_codes_are_case_insensitive() {
+        assert_eq!(normalize_promo_code(" summer24 "), "SUMMER24");
+    }
+}
@@ -0,0 +1,213 @@
+use crate::error::AppError;
+use crate::models::StayRestriction;
+use chrono::{Datelike, NaiveDate};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RestrictionViolation {
+    MinStayNotMet { min_stay: i32 },
+    ClosedToArrival { date: NaiveDate },
+    ClosedToDeparture { date: NaiveDate },
+}
+
+impl RestrictionViolation {
+    pub fn code(&self) -> &'static str {
+        match self {
+            RestrictionViolation::MinStayNotMet { .. } => "MIN_STAY_NOT_MET",
+            RestrictionViolation::ClosedToArrival { .. } => "CLOSED_TO_ARRIVAL",
+            RestrictionViolation::ClosedToDeparture { .. } => "CLOSED_TO_DEPARTURE",
+        }
+    }
+}
+
+impl fmt::Display for RestrictionViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RestrictionViolation::MinStayNotMet { min_stay } => write!(
+                f,
+                "Stays arriving on the requested date must be at least {} nights long",
+                min_stay
+            ),
+            RestrictionViolation::ClosedToArrival { date } => {
+                write!(f, "The hotel is closed to arrivals on {}", date)
+            }
+            RestrictionViolation::ClosedToDeparture { date } => {
+                write!(f, "The hotel is closed to departures on {}", date)
+            }
+        }
+    }
+}
+
+impl From<RestrictionViolation> for AppError {
+    fn from(violation: RestrictionViolation) -> Self {
+        AppError::bad_request(violation.to_string(), violation.code())
+    }
+}
+
+impl StayRestriction {
+    /// Whether the restriction is in effect on the given date
+    pub fn applies_to(&self, date: NaiveDate) -> bool {
+        self.start_date <= date
+            && date < self.end_date
+            && (self.days_of_week.is_empty() || self.days_of_week.contains(&date.weekday()))
+    }
+}
+
+/// Checks a stay against the restrictions of a hotel. The minimum stay and arrival rules are those
+/// in effect on the arrival date, the departure rules those in effect on the departure date. If
+/// several restrictions set a minimum stay, the longest one applies.
+pub fn check_stay_restrictions(
+    restrictions: &[StayRestriction],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Result<(), RestrictionViolation> {
+    let on_arrival: Vec<&StayRestriction> = restrictions
+        .iter()
+        .filter(|r| r.applies_to(start))
+        .collect();
+
+    if on_arrival.iter().any(|r| r.closed_to_arrival) {
+        return Err(RestrictionViolation::ClosedToArrival { date: start });
+    }
+
+    check_departure_restrictions(restrictions, end)?;
+
+    let nights = (end - start).num_days();
+    if let Some(min_stay) = on_arrival.iter().filter_map(|r| r.min_stay).max()
+        && nights < i64::from(min_stay)
+    {
+        return Err(RestrictionViolation::MinStayNotMet { min_stay });
+    }
+
+    Ok(())
+}
+
+/// Checks only the departure rules of a hotel, for stays whose arrival has already happened
+pub fn check_departure_restrictions(
+    restrictions: &[StayRestriction],
+    end: NaiveDate,
+) -> Result<(), RestrictionViolation> {
+    if restrictions
+        .iter()
+        .any(|r| r.closed_to_departure && r.applies_to(end))
+    {
+        return Err(RestrictionViolation::ClosedToDeparture { date: end });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Days, Weekday};
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, month, day).unwrap()
+    }
+
+    fn restriction(start: NaiveDate, end: NaiveDate) -> StayRestriction {
+        StayRestriction {
+            id: 1,
+            start_date: start,
+            end_date: end,
+            days_of_week: vec![],
+            min_stay: None,
+            closed_to_arrival: false,
+            closed_to_departure: false,
+        }
+    }
+
+    #[test]
+    fn test_min_stay_over_new_year() {
+        let new_year = NaiveDate::from_ymd_opt(2025, 1, 1).unwrap();
+        let restrictions = vec![StayRestriction {
+            min_stay: Some(3),
+            ..restriction(date(12, 30), new_year)
+        }];
+
+        assert_eq!(
+            check_stay_restrictions(&restrictions, date(12, 31), new_year + Days::new(1)),
+            Err(RestrictionViolation::MinStayNotMet { min_stay: 3 })
+        );
+        assert!(
+            check_stay_restrictions(&restrictions, date(12, 30), new_year + Days::new(2)).is_ok()
+        );
+        // Only arrivals during the restricted period have to stay longer
+        assert!(check_stay_restrictions(&restrictions, date(12, 29), date(12, 31)).is_ok());
+        assert!(check_stay_restrictions(&restrictions, new_year, new_year + Days::new(1)).is_ok());
+    }
+
+    #[test]
+    fn test_longest_min_stay_applies() {
+        let restrictions = vec![
+            StayRestriction {
+                min_stay: Some(2),
+                ..restriction(date(7, 1), date(9, 1))
+            },
+            StayRestriction {
+                min_stay: Some(5),
+                ..restriction(date(8, 1), date(8, 15))
+            },
+        ];
+
+        assert!(check_stay_restrictions(&restrictions, date(7, 10), date(7, 12)).is_ok());
+        assert_eq!(
+            check_stay_restrictions(&restrictions, date(8, 10), date(8, 12)),
+            Err(RestrictionViolation::MinStayNotMet { min_stay: 5 })
+        );
+    }
+
+    #[test]
+    fn test_closed_to_arrival_on_saturdays() {
+        let restrictions = vec![StayRestriction {
+            days_of_week: vec![Weekday::Sat],
+            closed_to_arrival: true,
+            ..restriction(date(1, 1), date(12, 31))
+        }];
+
+        // June 1st, 2024 is a Saturday
+        assert_eq!(
+            check_stay_restrictions(&restrictions, date(6, 1), date(6, 3)),
+            Err(RestrictionViolation::ClosedToArrival { date: date(6, 1) })
+        );
+        // Staying over a Saturday is fine
+        assert!(check_stay_restrictions(&restrictions, date(5, 31), date(6, 3)).is_ok());
+    }
+
+    #[test]
+    fn test_closed_to_departure() {
+        let restrictions = vec![StayRestriction {
+            closed_to_departure: true,
+            ..restriction(date(6, 5), date(6, 6))
+        }];
+
+        assert_eq!(
+            check_stay_restrictions(&restrictions, date(6, 1), date(6, 5)),
+            Err(RestrictionViolation::ClosedToDeparture { date: date(6, 5) })
+        );
+        // Arriving on the closed date is fine
+        assert!(check_stay_restrictions(&restrictions, date(6, 5), date(6, 7)).is_ok());
+    }
+
+    #[test]
+    fn test_departure_rules_ignore_arrival_rules() {
+        let restrictions = vec![
+            StayRestriction {
+                closed_to_arrival: true,
+                min_stay: Some(7),
+                ..restriction(date(6, 1), date(6, 2))
+            },
+            StayRestriction {
+                closed_to_departure: true,
+                ..restriction(date(6, 5), date(6, 6))
+            },
+        ];
+
+        assert_eq!(
+            check_departure_restrictions(&restrictions, date(6, 5)),
+            Err(RestrictionViolation::ClosedToDeparture { date: date(6, 5) })
+        );
+        assert!(check_departure_restrictions(&restrictions, date(6, 3)).is_ok());
+    }
+}
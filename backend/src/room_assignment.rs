@@ -1,113 +1,726 @@
-use crate::models::Booking;
+use crate::models::{
+    Booking, BookingStatus, GroupRoomConstraint, HousekeepingStatus, NightAvailability,
+    OverbookedNight, Room, RoomConnection, RoomConnectionKind, RoomPreferences,
+};
+use crate::models_events::BookingRoomReassignedEvent;
 use chrono::NaiveDate;
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 
+/// Checks whether a new booking fits into the hotel. Up to `overbooking_allowance` bookings beyond
+/// the number of rooms are accepted for a night; these don't get a (physical) room assigned.
 pub fn can_accommodate_booking(
     hotel_room_count: i32,
+    overbooking_allowance: i32,
     existing_bookings: Vec<Booking>,
     new_start: NaiveDate,
     new_end: NaiveDate,
 ) -> bool {
-    // Create a list of all bookings including the new one (with a dummy ID)
-    let mut all_bookings = existing_bookings;
-    all_bookings.push(Booking {
-        id: -1,                     // dummy ID for the new booking
-        hotel_id: 0,                // doesn't matter for this algorithm
-        room_number: None,          // unassigned
-        guest_name: "".to_string(), // doesn't matter
-        start_time: new_start,
-        end_time: new_end,
-        status: crate::models::BookingStatus::Confirmed,
-    });
+    accommodates(
+        &[],
+        hotel_room_count + overbooking_allowance,
+        existing_bookings,
+        new_booking(new_start, new_end, None, 1, None),
+    )
+}
+
+/// Checks whether a new booking for a party of `guest_count` in a room of `room_type` fits into
+/// the hotel, like `can_accommodate_booking`, but also making sure that every booking (the new one
+/// included) gets a room of its type large enough for its party. Overbooked stays may get any
+/// (virtual) room.
+pub fn can_accommodate_party(
+    rooms: &[Room],
+    overbooking_allowance: i32,
+    existing_bookings: Vec<Booking>,
+    new_start: NaiveDate,
+    new_end: NaiveDate,
+    guest_count: i32,
+    room_type: Option<&str>,
+) -> bool {
+    accommodates(
+        rooms,
+        rooms.len() as i32 + overbooking_allowance,
+        existing_bookings,
+        new_booking(new_start, new_end, None, guest_count, room_type),
+    )
+}
+
+fn accommodates(
+    rooms: &[Room],
+    room_count: i32,
+    existing_bookings: Vec<Booking>,
+    new_booking: Booking,
+) -> bool {
+    // Create a list of all bookings taking up rooms, including the new one (with a dummy ID)
+    let mut all_bookings: Vec<Booking> = existing_bookings
+        .into_iter()
+        .filter(|b| b.status.holds_inventory())
+        .collect();
+    all_bookings.push(new_booking);
 
     // Sort bookings by start time
-    all_bookings.sort_by(|a, b| a.start_time.cmp(&b.start_time));
+    all_bookings.sort_by_key(|b| b.start_time);
+
+    // Try to assign rooms using a greedy algorithm, keeping every existing room assignment in place
+    // Overbooked stays are assigned to virtual rooms after the physical ones
+    assign_rooms_greedy(&all_bookings, rooms, room_count, |b| {
+        b.room_number.is_some()
+    })
+    .is_some()
+}
+
+/// Checks whether bookings which have already been accepted all fit into the given number of
+/// rooms, e.g. before the hotel's capacity is reduced.
+pub fn can_accommodate_bookings(
+    hotel_room_count: i32,
+    overbooking_allowance: i32,
+    bookings: Vec<Booking>,
+) -> bool {
+    let mut bookings: Vec<Booking> = bookings
+        .into_iter()
+        .filter(|b| b.status.holds_inventory())
+        .collect();
+
+    // Rooms are assigned to all bookings together, so checking any one of them as if it were new
+    // checks them all
+    let Some(last) = bookings.pop() else {
+        return true;
+    };
+    can_accommodate_booking(
+        hotel_room_count,
+        overbooking_allowance,
+        bookings,
+        last.start_time,
+        last.end_time,
+    )
+}
+
+/// Checks whether bookings which have already been accepted all still get a room of their type
+/// large enough for their party, e.g. before the rooms of a hotel are changed.
+pub fn can_accommodate_parties(
+    rooms: &[Room],
+    overbooking_allowance: i32,
+    bookings: Vec<Booking>,
+) -> bool {
+    let mut bookings: Vec<Booking> = bookings
+        .into_iter()
+        .filter(|b| b.status.holds_inventory())
+        .collect();
+
+    let Some(last) = bookings.pop() else {
+        return true;
+    };
+    accommodates(
+        rooms,
+        rooms.len() as i32 + overbooking_allowance,
+        bookings,
+        last,
+    )
+}
+
+/// Lists the nights from `from` (inclusive) to `to` (exclusive) for which more rooms are booked
+/// than the hotel has, so that some guests will have to be walked.
+pub fn overbooked_nights(
+    hotel_room_count: i32,
+    bookings: &[Booking],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<OverbookedNight> {
+    from.iter_days()
+        .take_while(|&date| date < to)
+        .filter_map(|date| {
+            let booked_rooms = bookings
+                .iter()
+                .filter(|b| b.status.holds_inventory())
+                .filter(|b| b.start_time <= date && b.end_time > date)
+                .count() as i32;
+            (booked_rooms > hotel_room_count).then_some(OverbookedNight {
+                date,
+                booked_rooms,
+                rooms: hotel_room_count,
+            })
+        })
+        .collect()
+}
+
+/// Counts the free rooms for each night from `from` (inclusive) to `to` (exclusive). A booking
+/// occupies a room on the nights from its start date up to, but excluding, its end date.
+pub fn nightly_availability(
+    hotel_room_count: i32,
+    bookings: &[Booking],
+    from: NaiveDate,
+    to: NaiveDate,
+) -> Vec<NightAvailability> {
+    from.iter_days()
+        .take_while(|&date| date < to)
+        .map(|date| {
+            let occupied = bookings
+                .iter()
+                .filter(|b| b.start_time <= date && b.end_time > date)
+                .count() as i32;
+            NightAvailability {
+                date,
+                free_rooms: (hotel_room_count - occupied).max(0),
+            }
+        })
+        .collect()
+}
+
+/// Checks whether a new booking for a party of `guest_count` in a room of `room_type` (optionally
+/// with a requested room) fits into the hotel, moving unpinned room pre-assignments if that's the
+/// only way to make room for it.
+///
+/// Returns `None` if the booking can't be accommodated even after re-packing. Otherwise, returns
+/// the reassignment events that have to be processed, which is empty if nothing had to be moved.
+/// Pinned pre-assignments and rooms of checked-in guests are never moved, and pre-assignments are
+/// only moved to rooms of their type which fit their party. Only pre-assignments overlapping the new booking are
+/// considered movable, so `existing_bookings` must contain every booking overlapping the stays of
+/// those (not only the ones overlapping the new booking).
+pub fn repack_preassignments(
+    rooms: &[Room],
+    existing_bookings: Vec<Booking>,
+    new_start: NaiveDate,
+    new_end: NaiveDate,
+    requested_room: Option<i32>,
+    guest_count: i32,
+    room_type: Option<&str>,
+) -> Option<Vec<BookingRoomReassignedEvent>> {
+    let room_count = rooms.len() as i32;
+    let mut all_bookings = existing_bookings;
+    all_bookings.push(new_booking(
+        new_start,
+        new_end,
+        requested_room,
+        guest_count,
+        room_type,
+    ));
+    all_bookings.sort_by_key(|b| b.start_time);
+
+    // First, check if the booking fits without moving anything
+    if assign_rooms_greedy(&all_bookings, rooms, room_count, |b| {
+        b.room_number.is_some()
+    })
+    .is_some()
+    {
+        return Some(vec![]);
+    }
+
+    let is_movable = |b: &Booking| {
+        b.status == BookingStatus::Confirmed
+            && b.room_number.is_some()
+            && !b.room_pinned
+            && b.start_time < new_end
+            && b.end_time > new_start
+    };
+
+    let assignments = assign_rooms_greedy(&all_bookings, rooms, room_count, |b| {
+        b.room_number.is_some() && !is_movable(b)
+    })?;
+
+    let reassignments = all_bookings
+        .iter()
+        .zip(assignments)
+        .filter(|(booking, _)| is_movable(booking))
+        .filter_map(|(booking, assigned_room)| {
+            let from_room = booking.room_number?;
+            let to_room = assigned_room?;
+            (from_room != to_room).then_some(BookingRoomReassignedEvent {
+                booking_id: booking.id,
+                from_room,
+                to_room,
+            })
+        })
+        .collect();
+
+    Some(reassignments)
+}
 
-    // Try to assign rooms using a greedy algorithm
-    assign_rooms_greedy(&all_bookings, hotel_room_count).is_some()
+fn new_booking(
+    start: NaiveDate,
+    end: NaiveDate,
+    room_number: Option<i32>,
+    guest_count: i32,
+    room_type: Option<&str>,
+) -> Booking {
+    Booking {
+        id: -1,      // dummy ID for the new booking
+        hotel_id: 0, // doesn't matter for this algorithm
+        room_number, // unassigned, unless a specific room is requested
+        room_pinned: room_number.is_some(),
+        guest_name: "".to_string(), // doesn't matter
+        start_time: start,
+        end_time: end,
+        status: BookingStatus::Confirmed,
+        preferences: RoomPreferences::default(),
+        hold_expires_at: None,
+        group_id: None,
+        adults: guest_count,
+        children: 0,
+        guest_id: None,
+        quote: None,
+        room_type: room_type.map(str::to_string),
+    }
 }
 
-fn assign_rooms_greedy(bookings: &[Booking], room_count: i32) -> Option<Vec<Option<i32>>> {
+/// Assigns rooms to bookings (which must be sorted by start time) using a greedy algorithm.
+/// Bookings for which `is_fixed` holds keep their current room (if it's a valid room number).
+/// The remaining bookings get their current room if it's free, of their type and fits their
+/// party, or the smallest such available room otherwise, so that larger rooms are kept for larger
+/// parties. Rooms beyond the given `rooms` (up to `room_count`) fit any booking.
+fn assign_rooms_greedy(
+    bookings: &[Booking],
+    rooms: &[Room],
+    room_count: i32,
+    is_fixed: impl Fn(&Booking) -> bool,
+) -> Option<Vec<Option<i32>>> {
+    let max_occupancy = |room_idx: usize| {
+        rooms
+            .get(room_idx)
+            .map_or(i32::MAX, |room| room.max_occupancy)
+    };
+
     let mut assignments = vec![None; bookings.len()];
 
+    // Stays of fixed bookings, per room
+    let mut fixed_stays: Vec<Vec<(NaiveDate, NaiveDate)>> = vec![vec![]; room_count as usize];
+
+    for (booking_idx, booking) in bookings.iter().enumerate() {
+        let Some(room_number) = booking.room_number.filter(|_| is_fixed(booking)) else {
+            continue;
+        };
+        if room_number < 1 || room_number > room_count {
+            continue;
+        }
+
+        // Two fixed bookings can't share a room at the same time
+        let stays = &mut fixed_stays[(room_number - 1) as usize];
+        if stays
+            .iter()
+            .any(|&(start, end)| start < booking.end_time && end > booking.start_time)
+        {
+            return None;
+        }
+        stays.push((booking.start_time, booking.end_time));
+        assignments[booking_idx] = Some(room_number);
+    }
+
     // Track which rooms are occupied at any given time
     // Each room tracks when it becomes free
     let mut room_free_times: Vec<Option<NaiveDate>> = vec![None; room_count as usize];
 
     for (booking_idx, booking) in bookings.iter().enumerate() {
-        let mut assigned = false;
-
-        // Try to find an available room
-        for room_idx in 0..room_count as usize {
-            // Room is available if it's never been used or is free before this booking starts
-            if room_free_times[room_idx].is_none()
-                || room_free_times[room_idx].unwrap() <= booking.start_time
-            {
+        if assignments[booking_idx].is_some() {
+            continue;
+        }
+
+        // Room is available if it's of the booking's type and fits the party, has never been used
+        // or is free before this booking starts, and no fixed booking occupies it during the stay
+        let is_available = |room_idx: usize| {
+            max_occupancy(room_idx) >= booking.guest_count()
+                && rooms
+                    .get(room_idx)
+                    .is_none_or(|room| room.is_of_type(booking.room_type.as_deref()))
+                && room_free_times[room_idx].is_none_or(|free_time| free_time <= booking.start_time)
+                && fixed_stays[room_idx]
+                    .iter()
+                    .all(|&(start, end)| start >= booking.end_time || end <= booking.start_time)
+        };
+
+        // Prefer the current room of the booking, then the smallest available room
+        let current_room_idx = booking
+            .room_number
+            .filter(|&room_number| room_number >= 1 && room_number <= room_count)
+            .map(|room_number| (room_number - 1) as usize);
+        let room_idx = current_room_idx
+            .filter(|&room_idx| is_available(room_idx))
+            .or_else(|| {
+                (0..room_count as usize)
+                    .filter(|&room_idx| is_available(room_idx))
+                    .min_by_key(|&room_idx| (max_occupancy(room_idx), room_idx))
+            });
+
+        match room_idx {
+            Some(room_idx) => {
                 // Assign this room to the booking
                 assignments[booking_idx] = Some(room_idx as i32 + 1); // rooms are 1-indexed
                 room_free_times[room_idx] = Some(booking.end_time);
-                assigned = true;
-                break;
             }
-        }
-
-        if !assigned {
             // Could not assign a room - hotel is overbooked
-            return None;
+            None => return None,
         }
     }
 
     Some(assignments)
 }
 
-/// Assigns a room to a specific booking during checkin.
-/// Assumes all existing bookings with room assignments are currently active/checked-in.
-/// Simply finds the first available room number without considering date ranges.
-pub fn assign_room_for_checkin(
-    hotel_room_count: i32,
+/// Why rooms can't be allocated to a group booking
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupAllocationError {
+    /// The hotel doesn't have enough rooms available for the whole stay
+    NotEnoughRooms { requested: usize, available: usize },
+    /// There are enough rooms available, but not enough of them are next to each other
+    NotEnoughConnectedRooms {
+        requested: usize,
+        largest_block: usize,
+    },
+    /// Enough connected rooms are free, but giving them to the group would leave no rooms for
+    /// other bookings which don't have a room assigned yet
+    ConnectedRoomsNeededElsewhere { requested: usize },
+}
+
+impl std::fmt::Display for GroupAllocationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GroupAllocationError::NotEnoughRooms {
+                requested,
+                available,
+            } => write!(
+                f,
+                "Requested {} rooms, but only {} are available for the requested dates",
+                requested, available
+            ),
+            GroupAllocationError::NotEnoughConnectedRooms {
+                requested,
+                largest_block,
+            } => write!(
+                f,
+                "Requested {} rooms next to each other, but the largest block of such rooms available for the requested dates has {} rooms",
+                requested, largest_block
+            ),
+            GroupAllocationError::ConnectedRoomsNeededElsewhere { requested } => write!(
+                f,
+                "{} rooms next to each other are free, but they are needed to accommodate other bookings",
+                requested
+            ),
+        }
+    }
+}
+
+/// Allocates rooms to a group booking, one for each of the group's `parties` (given by their
+/// number of guests), so that they satisfy the group's constraint. Each party gets a room which
+/// fits it, of any type. Rooms with assigned bookings (pre-assigned or checked-in) during the stay are never
+/// used, and the remaining bookings must still fit into the hotel.
+pub fn allocate_group_rooms(
+    hotel_rooms: &[Room],
     existing_bookings: Vec<Booking>,
-    _checkin_booking: &Booking,
-) -> Option<i32> {
-    // Track which rooms are currently occupied by existing bookings
-    let mut occupied_rooms = vec![false; hotel_room_count as usize];
-
-    // Mark rooms that are occupied by existing bookings
-    for booking in &existing_bookings {
-        if let Some(room_number) = booking.room_number {
-            let room_idx = (room_number - 1) as usize; // Convert from 1-indexed to 0-indexed
-            if room_idx < hotel_room_count as usize {
-                occupied_rooms[room_idx] = true;
+    start: NaiveDate,
+    end: NaiveDate,
+    parties: &[i32],
+    constraint: GroupRoomConstraint,
+    connections: &[RoomConnection],
+) -> Result<Vec<i32>, GroupAllocationError> {
+    let number_of_rooms = parties.len();
+
+    // First, check if there's enough capacity for the group, with rooms in any location
+    let Some(rooms) = assign_group_greedy(hotel_rooms, &existing_bookings, start, end, parties)
+    else {
+        let available = (0..number_of_rooms)
+            .rev()
+            .find(|&n| {
+                assign_group_greedy(hotel_rooms, &existing_bookings, start, end, &parties[..n])
+                    .is_some()
+            })
+            .unwrap_or(0);
+        return Err(GroupAllocationError::NotEnoughRooms {
+            requested: number_of_rooms,
+            available,
+        });
+    };
+
+    let accepted_kinds: &[RoomConnectionKind] = match constraint {
+        GroupRoomConstraint::None => return Ok(rooms),
+        GroupRoomConstraint::Adjacent => {
+            &[RoomConnectionKind::Adjacent, RoomConnectionKind::Connecting]
+        }
+        GroupRoomConstraint::Connecting => &[RoomConnectionKind::Connecting],
+    };
+
+    // Rooms which fit any of the group's parties, and aren't assigned to any booking during the
+    // stay
+    let largest_party = parties.iter().copied().max().unwrap_or(0);
+    let free_rooms: BTreeSet<i32> = hotel_rooms
+        .iter()
+        .filter(|room| room.fits(largest_party))
+        .map(|room| room.room_number)
+        .filter(|&room_number| {
+            !existing_bookings.iter().any(|b| {
+                b.room_number == Some(room_number) && b.start_time < end && b.end_time > start
+            })
+        })
+        .collect();
+
+    let mut neighbours: BTreeMap<i32, BTreeSet<i32>> = BTreeMap::new();
+    for connection in connections {
+        if accepted_kinds.contains(&connection.kind)
+            && free_rooms.contains(&connection.room_a)
+            && free_rooms.contains(&connection.room_b)
+        {
+            neighbours
+                .entry(connection.room_a)
+                .or_default()
+                .insert(connection.room_b);
+            neighbours
+                .entry(connection.room_b)
+                .or_default()
+                .insert(connection.room_a);
+        }
+    }
+
+    // Go through blocks of connected free rooms; within a large enough block, the first rooms
+    // reached by a breadth-first search from any room are connected as well
+    let mut largest_block = 0;
+    let mut visited = BTreeSet::new();
+    for &room_number in &free_rooms {
+        if visited.contains(&room_number) {
+            continue;
+        }
+
+        let block = connected_rooms(room_number, &neighbours, usize::MAX);
+        visited.extend(block.iter().copied());
+        largest_block = largest_block.max(block.len());
+        if block.len() < number_of_rooms {
+            continue;
+        }
+
+        for &first_room in &block {
+            let mut candidate = connected_rooms(first_room, &neighbours, number_of_rooms);
+            if fits_with_group_rooms(hotel_rooms, &existing_bookings, start, end, &candidate) {
+                candidate.sort();
+                return Ok(candidate);
             }
         }
     }
 
-    // Find the first available room
-    for room_idx in 0..hotel_room_count as usize {
-        if !occupied_rooms[room_idx] {
-            return Some(room_idx as i32 + 1); // Convert back to 1-indexed
+    if largest_block < number_of_rooms {
+        Err(GroupAllocationError::NotEnoughConnectedRooms {
+            requested: number_of_rooms,
+            largest_block,
+        })
+    } else {
+        Err(GroupAllocationError::ConnectedRoomsNeededElsewhere {
+            requested: number_of_rooms,
+        })
+    }
+}
+
+/// Returns up to `limit` rooms reachable from `first_room`, in breadth-first order.
+fn connected_rooms(
+    first_room: i32,
+    neighbours: &BTreeMap<i32, BTreeSet<i32>>,
+    limit: usize,
+) -> Vec<i32> {
+    let mut rooms = vec![first_room];
+    let mut queue = VecDeque::from([first_room]);
+
+    while let Some(room_number) = queue.pop_front() {
+        for &neighbour in neighbours.get(&room_number).into_iter().flatten() {
+            if rooms.len() >= limit {
+                return rooms;
+            }
+            if !rooms.contains(&neighbour) {
+                rooms.push(neighbour);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+
+    rooms
+}
+
+/// Adds an unassigned booking for the stay for each of the parties, and returns the rooms they
+/// get assigned, if all bookings fit.
+fn assign_group_greedy(
+    hotel_rooms: &[Room],
+    existing_bookings: &[Booking],
+    start: NaiveDate,
+    end: NaiveDate,
+    parties: &[i32],
+) -> Option<Vec<i32>> {
+    let mut all_bookings = existing_bookings.to_vec();
+    all_bookings.extend(
+        parties
+            .iter()
+            .map(|&guest_count| new_booking(start, end, None, guest_count, None)),
+    );
+    all_bookings.sort_by_key(|b| b.start_time);
+
+    let assignments =
+        assign_rooms_greedy(&all_bookings, hotel_rooms, hotel_rooms.len() as i32, |b| {
+            b.room_number.is_some()
+        })?;
+
+    // Existing bookings have real IDs, so the dummy ID identifies the group's bookings
+    all_bookings
+        .iter()
+        .zip(assignments)
+        .filter(|(booking, _)| booking.id == -1)
+        .map(|(_, room_number)| room_number)
+        .collect()
+}
+
+/// Checks whether all bookings still fit if the group gets the given rooms, which must fit the
+/// group's parties.
+fn fits_with_group_rooms(
+    hotel_rooms: &[Room],
+    existing_bookings: &[Booking],
+    start: NaiveDate,
+    end: NaiveDate,
+    rooms: &[i32],
+) -> bool {
+    let mut all_bookings = existing_bookings.to_vec();
+    all_bookings.extend(
+        rooms
+            .iter()
+            .map(|&room_number| new_booking(start, end, Some(room_number), 1, None)),
+    );
+    all_bookings.sort_by_key(|b| b.start_time);
+
+    assign_rooms_greedy(&all_bookings, hotel_rooms, hotel_rooms.len() as i32, |b| {
+        b.room_number.is_some()
+    })
+    .is_some()
+}
+
+/// Chooses a room for a guest at check-in, among the rooms that are free.
+pub trait RoomAssignmentStrategy: Send + Sync {
+    /// Returns the room number of the chosen room, or `None` if `free_rooms` is empty.
+    fn choose_room(&self, free_rooms: &[&Room], booking: &Booking) -> Option<i32>;
+}
+
+/// Picks the free room with the lowest number, ignoring guest preferences.
+pub struct FirstFitStrategy;
+
+impl RoomAssignmentStrategy for FirstFitStrategy {
+    fn choose_room(&self, free_rooms: &[&Room], _booking: &Booking) -> Option<i32> {
+        free_rooms.iter().map(|room| room.room_number).min()
+    }
+}
+
+/// Picks the free room which best satisfies the guest's preferences. Ties are resolved in favor
+/// of the lowest room number.
+pub struct PreferenceScoringStrategy;
+
+impl PreferenceScoringStrategy {
+    fn score(room: &Room, preferences: &RoomPreferences, max_floor: i32) -> i32 {
+        let mut score = 0;
+
+        if preferences.accessible {
+            // Accessibility is a need rather than a wish, so it outweighs all other preferences
+            if room.accessible {
+                score += 100;
+            }
+        } else if room.accessible {
+            // Keep accessible rooms for the guests who need them
+            score -= 1;
+        }
+        if preferences.high_floor && max_floor > 0 {
+            score += 10 * room.floor / max_floor;
         }
+        if preferences.quiet && room.quiet {
+            score += 10;
+        }
+        if preferences.near_elevator && room.near_elevator {
+            score += 10;
+        }
+
+        score
+    }
+}
+
+impl RoomAssignmentStrategy for PreferenceScoringStrategy {
+    fn choose_room(&self, free_rooms: &[&Room], booking: &Booking) -> Option<i32> {
+        let max_floor = free_rooms.iter().map(|room| room.floor).max()?;
+
+        free_rooms
+            .iter()
+            .min_by_key(|room| {
+                (
+                    -Self::score(room, &booking.preferences, max_floor),
+                    room.room_number,
+                )
+            })
+            .map(|room| room.room_number)
+    }
+}
+
+/// Assigns a room to a specific booking during checkin.
+/// Assumes all existing bookings with room assignments are currently active/checked-in.
+/// Uses the room pre-assigned to the booking if it's still free, otherwise lets the strategy
+/// choose among the free rooms of the booking's type, without considering date ranges. Rooms which are further along
+/// in housekeeping are preferred (inspected ones first), and rooms which haven't been cleaned
+/// yet are skipped entirely if `refuse_dirty_rooms` is set.
+pub fn assign_room_for_checkin(
+    rooms: &[Room],
+    existing_bookings: Vec<Booking>,
+    checkin_booking: &Booking,
+    strategy: &dyn RoomAssignmentStrategy,
+    refuse_dirty_rooms: bool,
+) -> Option<i32> {
+    // Find rooms of the booking's type that fit the party and aren't occupied by existing bookings
+    let free_rooms: Vec<&Room> = rooms
+        .iter()
+        .filter(|room| room.is_of_type(checkin_booking.room_type.as_deref()))
+        .filter(|room| room.fits(checkin_booking.guest_count()))
+        .filter(|room| {
+            !existing_bookings
+                .iter()
+                .any(|booking| booking.room_number == Some(room.room_number))
+        })
+        .filter(|room| !refuse_dirty_rooms || room.housekeeping_status >= HousekeepingStatus::Clean)
+        .collect();
+
+    // Use the pre-assigned room, if there is one and it's free
+    if let Some(room_number) = checkin_booking.room_number
+        && free_rooms
+            .iter()
+            .any(|room| room.room_number == room_number)
+    {
+        return Some(room_number);
     }
 
-    // No available rooms
-    None
+    // Let the strategy choose only among the rooms in the best housekeeping state available
+    let best_status = free_rooms
+        .iter()
+        .map(|room| room.housekeeping_status)
+        .max()?;
+    let ready_rooms: Vec<&Room> = free_rooms
+        .into_iter()
+        .filter(|room| room.housekeeping_status == best_status)
+        .collect();
+
+    strategy.choose_room(&ready_rooms, checkin_booking)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::models::BookingStatus;
+    use crate::models::{BookingStatus, DEFAULT_ROOM_TYPE};
+    use chrono::Datelike;
 
     fn fake_booking(id: i64, start_day: u32, end_day: u32) -> Booking {
         Booking {
             id,
             hotel_id: 1,
             room_number: None,
+            room_pinned: false,
             guest_name: format!("Guest {}", id),
             start_time: NaiveDate::from_ymd_opt(2024, 1, start_day).unwrap(),
             end_time: NaiveDate::from_ymd_opt(2024, 1, end_day).unwrap(),
             status: BookingStatus::Confirmed,
+            preferences: RoomPreferences::default(),
+            hold_expires_at: None,
+            group_id: None,
+            adults: 1,
+            children: 0,
+            guest_id: None,
+            quote: None,
+            room_type: None,
         }
     }
 
+    fn rooms(count: i32) -> Vec<Room> {
+        (1..=count).map(Room::with_defaults).collect()
+    }
+
     fn request_booking(start_day: u32, end_day: u32) -> (NaiveDate, NaiveDate) {
         (
             NaiveDate::from_ymd_opt(2024, 1, start_day).unwrap(),
@@ -120,7 +733,7 @@ mod tests {
         let existing_bookings = vec![];
         let (start, end) = request_booking(1, 3);
 
-        assert!(can_accommodate_booking(1, existing_bookings, start, end));
+        assert!(can_accommodate_booking(1, 0, existing_bookings, start, end));
     }
 
     #[test]
@@ -128,7 +741,35 @@ mod tests {
         let existing_bookings = vec![fake_booking(1, 1, 3)];
         let (start, end) = request_booking(4, 6);
 
-        assert!(can_accommodate_booking(1, existing_bookings, start, end));
+        assert!(can_accommodate_booking(1, 0, existing_bookings, start, end));
+    }
+
+    #[test]
+    fn test_existing_bookings_fit_into_fewer_rooms() {
+        // At most two stays overlap, on the nights of the 3rd and 4th
+        let bookings = vec![
+            fake_booking(1, 1, 5),
+            fake_booking(2, 3, 6),
+            fake_booking(3, 6, 8),
+        ];
+
+        assert!(can_accommodate_bookings(2, 0, bookings.clone()));
+        assert!(!can_accommodate_bookings(1, 0, bookings.clone()));
+        // Unless the hotel may be overbooked
+        assert!(can_accommodate_bookings(1, 1, bookings));
+        assert!(can_accommodate_bookings(1, 0, vec![]));
+    }
+
+    #[test]
+    fn test_cancelled_bookings_dont_need_rooms() {
+        let mut cancelled = fake_booking(2, 1, 5);
+        cancelled.status = BookingStatus::Cancelled;
+
+        assert!(can_accommodate_bookings(
+            1,
+            0,
+            vec![fake_booking(1, 1, 5), cancelled]
+        ));
     }
 
     #[test]
@@ -136,7 +777,13 @@ mod tests {
         let existing_bookings = vec![fake_booking(1, 1, 5)];
         let (start, end) = request_booking(3, 7);
 
-        assert!(!can_accommodate_booking(1, existing_bookings, start, end));
+        assert!(!can_accommodate_booking(
+            1,
+            0,
+            existing_bookings,
+            start,
+            end
+        ));
     }
 
     #[test]
@@ -144,7 +791,7 @@ mod tests {
         let existing_bookings = vec![fake_booking(1, 1, 5)];
         let (start, end) = request_booking(3, 7);
 
-        assert!(can_accommodate_booking(2, existing_bookings, start, end));
+        assert!(can_accommodate_booking(2, 0, existing_bookings, start, end));
     }
 
     #[test]
@@ -161,13 +808,20 @@ mod tests {
         let (start, end) = request_booking(7, 9);
         assert!(can_accommodate_booking(
             3,
+            0,
             existing_bookings.clone(),
             start,
             end
         ));
 
         // Should NOT fit - all rooms occupied during Jan 7-9
-        assert!(!can_accommodate_booking(2, existing_bookings, start, end));
+        assert!(!can_accommodate_booking(
+            2,
+            0,
+            existing_bookings,
+            start,
+            end
+        ));
     }
 
     #[test]
@@ -180,7 +834,7 @@ mod tests {
 
         // New booking Jan 10-15 should work (consecutive)
         let (start, end) = request_booking(10, 15);
-        assert!(can_accommodate_booking(1, existing_bookings, start, end));
+        assert!(can_accommodate_booking(1, 0, existing_bookings, start, end));
     }
 
     #[test]
@@ -194,7 +848,13 @@ mod tests {
 
         // Any booking overlapping with Jan 3-8 should fail
         let (start, end) = request_booking(5, 7);
-        assert!(!can_accommodate_booking(3, existing_bookings, start, end));
+        assert!(!can_accommodate_booking(
+            3,
+            0,
+            existing_bookings,
+            start,
+            end
+        ));
     }
 
     #[test]
@@ -217,6 +877,7 @@ mod tests {
         let (start, end) = request_booking(6, 8);
         assert!(can_accommodate_booking(
             3,
+            0,
             existing_bookings.clone(),
             start,
             end
@@ -237,6 +898,7 @@ mod tests {
         let (start, end) = request_booking(8, 10);
         assert!(can_accommodate_booking(
             2,
+            0,
             existing_bookings_2room,
             start,
             end
@@ -253,6 +915,7 @@ mod tests {
         let (start, end) = request_booking(5, 7);
         assert!(!can_accommodate_booking(
             1,
+            0,
             existing_bookings.clone(),
             start,
             end
@@ -261,6 +924,7 @@ mod tests {
         let (start, end) = request_booking(15, 17);
         assert!(!can_accommodate_booking(
             1,
+            0,
             existing_bookings.clone(),
             start,
             end
@@ -268,7 +932,7 @@ mod tests {
 
         // But should work with 2 rooms
         let (start, end) = request_booking(10, 15);
-        assert!(can_accommodate_booking(2, existing_bookings, start, end));
+        assert!(can_accommodate_booking(2, 0, existing_bookings, start, end));
     }
 
     #[test]
@@ -281,7 +945,7 @@ mod tests {
         let start = NaiveDate::from_ymd_opt(2024, 1, 5).unwrap(); // checkin Jan 5
         let end = NaiveDate::from_ymd_opt(2024, 1, 8).unwrap();
 
-        assert!(can_accommodate_booking(1, existing_bookings, start, end));
+        assert!(can_accommodate_booking(1, 0, existing_bookings, start, end));
     }
 
     fn fake_booking_with_room(
@@ -294,10 +958,19 @@ mod tests {
             id,
             hotel_id: 1,
             room_number,
+            room_pinned: false,
             guest_name: format!("Guest {}", id),
             start_time: NaiveDate::from_ymd_opt(2024, 1, start_day).unwrap(),
             end_time: NaiveDate::from_ymd_opt(2024, 1, end_day).unwrap(),
             status: BookingStatus::CheckedIn,
+            preferences: RoomPreferences::default(),
+            hold_expires_at: None,
+            group_id: None,
+            adults: 1,
+            children: 0,
+            guest_id: None,
+            quote: None,
+            room_type: None,
         }
     }
 
@@ -307,7 +980,13 @@ mod tests {
         let existing_bookings = vec![];
         let checkin_booking = fake_booking(1, 1, 3);
 
-        let assigned_room = assign_room_for_checkin(2, existing_bookings, &checkin_booking);
+        let assigned_room = assign_room_for_checkin(
+            &rooms(2),
+            existing_bookings,
+            &checkin_booking,
+            &FirstFitStrategy,
+            false,
+        );
 
         assert_eq!(assigned_room, Some(1)); // Should get room 1
     }
@@ -323,7 +1002,13 @@ mod tests {
         // New checkin (dates don't matter since we only look at occupied rooms)
         let checkin_booking = fake_booking(3, 6, 8);
 
-        let assigned_room = assign_room_for_checkin(3, existing_bookings, &checkin_booking);
+        let assigned_room = assign_room_for_checkin(
+            &rooms(3),
+            existing_bookings,
+            &checkin_booking,
+            &FirstFitStrategy,
+            false,
+        );
 
         // Should get room 3 (first available room after rooms 1 and 2)
         assert_eq!(assigned_room, Some(3));
@@ -339,7 +1024,13 @@ mod tests {
         // New checkin
         let checkin_booking = fake_booking(2, 5, 8);
 
-        let assigned_room = assign_room_for_checkin(2, existing_bookings, &checkin_booking);
+        let assigned_room = assign_room_for_checkin(
+            &rooms(2),
+            existing_bookings,
+            &checkin_booking,
+            &FirstFitStrategy,
+            false,
+        );
 
         // Should get room 1 (room 2 is occupied)
         assert_eq!(assigned_room, Some(1));
@@ -357,7 +1048,13 @@ mod tests {
         // New checkin for Jan 6-9 (after room 2 is free, overlaps with room 3)
         let checkin_booking = fake_booking(4, 6, 9);
 
-        let assigned_room = assign_room_for_checkin(3, existing_bookings, &checkin_booking);
+        let assigned_room = assign_room_for_checkin(
+            &rooms(3),
+            existing_bookings,
+            &checkin_booking,
+            &FirstFitStrategy,
+            false,
+        );
 
         // Should get room 1 (room 2 is free after Jan 5, room 3 occupied until Jan 8)
         assert_eq!(assigned_room, Some(1));
@@ -374,7 +1071,13 @@ mod tests {
         // New checkin overlaps with both existing bookings
         let checkin_booking = fake_booking(3, 5, 8);
 
-        let assigned_room = assign_room_for_checkin(2, existing_bookings, &checkin_booking);
+        let assigned_room = assign_room_for_checkin(
+            &rooms(2),
+            existing_bookings,
+            &checkin_booking,
+            &FirstFitStrategy,
+            false,
+        );
 
         assert_eq!(assigned_room, None); // No room available
     }
@@ -391,7 +1094,13 @@ mod tests {
         // New checkin for Jan 8-12
         let checkin_booking = fake_booking(4, 8, 12);
 
-        let assigned_room = assign_room_for_checkin(3, existing_bookings, &checkin_booking);
+        let assigned_room = assign_room_for_checkin(
+            &rooms(3),
+            existing_bookings,
+            &checkin_booking,
+            &FirstFitStrategy,
+            false,
+        );
 
         // Should get room 3 (rooms 1 and 2 are occupied, bookings without rooms are ignored)
         assert_eq!(assigned_room, Some(3));
@@ -408,9 +1117,997 @@ mod tests {
         // Add a new booking that doesn't overlap
         let checkin_booking = fake_booking(3, 5, 7);
 
-        let assigned_room = assign_room_for_checkin(3, existing_bookings, &checkin_booking);
+        let assigned_room = assign_room_for_checkin(
+            &rooms(3),
+            existing_bookings,
+            &checkin_booking,
+            &FirstFitStrategy,
+            false,
+        );
 
         // Should get room 2 (first available room - rooms 1 and 3 are occupied)
         assert_eq!(assigned_room, Some(2));
     }
+
+    fn preassigned_booking(
+        id: i64,
+        start_day: u32,
+        end_day: u32,
+        room_number: i32,
+        room_pinned: bool,
+    ) -> Booking {
+        Booking {
+            room_number: Some(room_number),
+            room_pinned,
+            ..fake_booking(id, start_day, end_day)
+        }
+    }
+
+    fn reassignments(events: Vec<BookingRoomReassignedEvent>) -> Vec<(i64, i32, i32)> {
+        events
+            .into_iter()
+            .map(|e| (e.booking_id, e.from_room, e.to_room))
+            .collect()
+    }
+
+    #[test]
+    fn test_can_accommodate_with_overbooking_allowance() {
+        let existing_bookings = vec![fake_booking(1, 1, 5), fake_booking(2, 2, 4)];
+
+        let (start, end) = request_booking(3, 6);
+        assert!(!can_accommodate_booking(
+            2,
+            0,
+            existing_bookings.clone(),
+            start,
+            end
+        ));
+        assert!(can_accommodate_booking(2, 1, existing_bookings, start, end));
+    }
+
+    #[test]
+    fn test_overbooking_allowance_is_per_night() {
+        // Both nights of Jan 2-4 are already overbooked by one room
+        let existing_bookings = vec![
+            fake_booking(1, 1, 5),
+            fake_booking(2, 2, 4),
+            fake_booking(3, 2, 4),
+        ];
+
+        let (start, end) = request_booking(3, 4);
+        assert!(!can_accommodate_booking(
+            2,
+            1,
+            existing_bookings.clone(),
+            start,
+            end
+        ));
+
+        let (start, end) = request_booking(4, 6);
+        assert!(can_accommodate_booking(2, 1, existing_bookings, start, end));
+    }
+
+    #[test]
+    fn test_overbooked_nights() {
+        let bookings = vec![
+            fake_booking(1, 1, 5),
+            fake_booking(2, 2, 4),
+            fake_booking(3, 3, 5),
+            Booking {
+                status: BookingStatus::Cancelled,
+                ..fake_booking(4, 1, 5)
+            },
+        ];
+        let (from, to) = request_booking(1, 6);
+
+        let nights = overbooked_nights(2, &bookings, from, to);
+
+        assert_eq!(
+            nights,
+            vec![OverbookedNight {
+                date: NaiveDate::from_ymd_opt(2024, 1, 3).unwrap(),
+                booked_rooms: 3,
+                rooms: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn test_can_accommodate_counts_held_bookings() {
+        let existing_bookings = vec![Booking {
+            status: BookingStatus::Held,
+            ..fake_booking(1, 1, 3)
+        }];
+
+        let (start, end) = request_booking(2, 4);
+        assert!(!can_accommodate_booking(
+            1,
+            0,
+            existing_bookings,
+            start,
+            end
+        ));
+    }
+
+    #[test]
+    fn test_can_accommodate_ignores_expired_holds() {
+        let existing_bookings = vec![Booking {
+            status: BookingStatus::Expired,
+            ..fake_booking(1, 1, 3)
+        }];
+
+        let (start, end) = request_booking(2, 4);
+        assert!(can_accommodate_booking(1, 0, existing_bookings, start, end));
+    }
+
+    #[test]
+    fn test_can_accommodate_respects_pinned_preassignments() {
+        // Room 2 is pinned for Jan 1-5, room 1 is taken by a long stay
+        let existing_bookings = vec![
+            preassigned_booking(1, 1, 5, 2, true),
+            preassigned_booking(2, 1, 10, 1, true),
+        ];
+
+        let (start, end) = request_booking(4, 6);
+        assert!(!can_accommodate_booking(
+            2,
+            0,
+            existing_bookings,
+            start,
+            end
+        ));
+    }
+
+    #[test]
+    fn test_repack_no_reassignments_when_booking_fits() {
+        let existing_bookings = vec![preassigned_booking(1, 1, 5, 1, false)];
+        let (start, end) = request_booking(3, 7);
+
+        let result = repack_preassignments(&rooms(2), existing_bookings, start, end, None, 1, None);
+
+        assert_eq!(result.map(reassignments), Some(vec![]));
+    }
+
+    #[test]
+    fn test_repack_moves_unpinned_preassignment() {
+        // Room 1: booking 1 on Jan 1-3 (unpinned), room 2: booking 2 on Jan 4-8 (pinned)
+        // A request for room 1 on Jan 2-4 is only possible if booking 1 moves to room 2
+        let existing_bookings = vec![
+            preassigned_booking(1, 1, 3, 1, false),
+            preassigned_booking(2, 4, 8, 2, true),
+        ];
+        let (start, end) = request_booking(2, 4);
+
+        let result =
+            repack_preassignments(&rooms(2), existing_bookings, start, end, Some(1), 1, None);
+
+        assert_eq!(result.map(reassignments), Some(vec![(1, 1, 2)]));
+    }
+
+    #[test]
+    fn test_repack_makes_room_for_unassigned_booking() {
+        // Room 1 is free on Jan 1-3 and room 2 on Jan 5-9, but not across the whole of Jan 1-9
+        let existing_bookings = vec![
+            preassigned_booking(1, 5, 9, 1, false),
+            preassigned_booking(2, 1, 3, 2, false),
+        ];
+        let (start, end) = request_booking(1, 9);
+
+        let result = repack_preassignments(&rooms(2), existing_bookings, start, end, None, 1, None);
+
+        assert_eq!(result.map(reassignments), Some(vec![(1, 1, 2)]));
+    }
+
+    #[test]
+    fn test_repack_never_moves_pinned_preassignments() {
+        let existing_bookings = vec![
+            preassigned_booking(1, 5, 9, 1, true),
+            preassigned_booking(2, 1, 3, 2, true),
+        ];
+        let (start, end) = request_booking(1, 9);
+
+        let result = repack_preassignments(&rooms(2), existing_bookings, start, end, None, 1, None);
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_repack_never_moves_checked_in_guests() {
+        let existing_bookings = vec![fake_booking_with_room(1, 1, 5, Some(1))];
+        let (start, end) = request_booking(2, 4);
+
+        let result =
+            repack_preassignments(&rooms(2), existing_bookings, start, end, Some(1), 1, None);
+
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_repack_keeps_room_of_extended_stay() {
+        // A guest checked into room 1 on Jan 1 extends their stay from Jan 5 to Jan 8, while
+        // room 1 is pinned to someone else from Jan 6. Room 2 is free, but the guest keeps
+        // their room, so the extension isn't possible.
+        let existing_bookings = vec![preassigned_booking(2, 6, 9, 1, true)];
+        let (start, end) = request_booking(1, 8);
+
+        let result =
+            repack_preassignments(&rooms(2), existing_bookings, start, end, Some(1), 1, None);
+        assert!(result.is_none());
+
+        // Unless the other guest can be moved
+        let existing_bookings = vec![preassigned_booking(2, 6, 9, 1, false)];
+
+        let result =
+            repack_preassignments(&rooms(2), existing_bookings, start, end, Some(1), 1, None);
+        assert_eq!(result.map(reassignments), Some(vec![(2, 1, 2)]));
+    }
+
+    #[test]
+    fn test_can_accommodate_party_needs_a_free_room_which_fits() {
+        // Only room 2 fits four guests, and it's needed by the family staying on Jan 1-5
+        let hotel_rooms = vec![room_with_occupancy(1, 2), room_with_occupancy(2, 4)];
+        let existing_bookings = vec![Booking {
+            adults: 4,
+            ..fake_booking(1, 1, 5)
+        }];
+
+        let (start, end) = request_booking(2, 4);
+        assert!(!can_accommodate_party(
+            &hotel_rooms,
+            0,
+            existing_bookings.clone(),
+            start,
+            end,
+            4,
+            None
+        ));
+        assert!(can_accommodate_party(
+            &hotel_rooms,
+            0,
+            existing_bookings.clone(),
+            start,
+            end,
+            2,
+            None
+        ));
+
+        let (start, end) = request_booking(5, 8);
+        assert!(can_accommodate_party(
+            &hotel_rooms,
+            0,
+            existing_bookings,
+            start,
+            end,
+            4,
+            None
+        ));
+    }
+
+    #[test]
+    fn test_existing_parties_need_rooms_which_fit() {
+        // Two families of four overlap on Jan 3-4
+        let bookings = vec![
+            Booking {
+                adults: 4,
+                ..fake_booking(1, 1, 5)
+            },
+            Booking {
+                adults: 4,
+                ..fake_booking(2, 3, 6)
+            },
+        ];
+
+        let hotel_rooms = vec![room_with_occupancy(1, 4), room_with_occupancy(2, 4)];
+        assert!(can_accommodate_parties(&hotel_rooms, 0, bookings.clone()));
+
+        let hotel_rooms = vec![room_with_occupancy(1, 4), room_with_occupancy(2, 2)];
+        assert!(!can_accommodate_parties(&hotel_rooms, 0, bookings.clone()));
+        // Unless one of them may be overbooked
+        assert!(can_accommodate_parties(&hotel_rooms, 1, bookings));
+        assert!(can_accommodate_parties(&hotel_rooms, 0, vec![]));
+    }
+
+    #[test]
+    fn test_repack_only_moves_preassignments_to_rooms_which_fit() {
+        // Room 1 is requested for Jan 2-4, and only room 2 is left for the guests pre-assigned to
+        // it
+        let hotel_rooms = vec![room_with_occupancy(1, 4), room_with_occupancy(2, 2)];
+        let (start, end) = request_booking(2, 4);
+
+        let existing_bookings = vec![Booking {
+            adults: 3,
+            ..preassigned_booking(1, 1, 5, 1, false)
+        }];
+        let result = repack_preassignments(
+            &hotel_rooms,
+            existing_bookings,
+            start,
+            end,
+            Some(1),
+            1,
+            None,
+        );
+        assert!(result.is_none());
+
+        let existing_bookings = vec![Booking {
+            adults: 2,
+            ..preassigned_booking(1, 1, 5, 1, false)
+        }];
+        let result = repack_preassignments(
+            &hotel_rooms,
+            existing_bookings,
+            start,
+            end,
+            Some(1),
+            1,
+            None,
+        );
+        assert_eq!(result.map(reassignments), Some(vec![(1, 1, 2)]));
+    }
+
+    #[test]
+    fn test_assign_room_for_checkin_uses_preassigned_room() {
+        let existing_bookings = vec![fake_booking_with_room(1, 1, 5, Some(1))];
+        let checkin_booking = preassigned_booking(2, 3, 6, 3, false);
+
+        let assigned_room = assign_room_for_checkin(
+            &rooms(3),
+            existing_bookings,
+            &checkin_booking,
+            &FirstFitStrategy,
+            false,
+        );
+
+        assert_eq!(assigned_room, Some(3));
+    }
+
+    #[test]
+    fn test_assign_room_for_checkin_preassigned_room_occupied() {
+        let existing_bookings = vec![fake_booking_with_room(1, 1, 5, Some(1))];
+        let checkin_booking = preassigned_booking(2, 3, 6, 1, false);
+
+        let assigned_room = assign_room_for_checkin(
+            &rooms(3),
+            existing_bookings,
+            &checkin_booking,
+            &FirstFitStrategy,
+            false,
+        );
+
+        assert_eq!(assigned_room, Some(2));
+    }
+
+    fn room(
+        room_number: i32,
+        floor: i32,
+        quiet: bool,
+        near_elevator: bool,
+        accessible: bool,
+    ) -> Room {
+        Room {
+            room_number,
+            floor,
+            quiet,
+            near_elevator,
+            accessible,
+            housekeeping_status: HousekeepingStatus::Inspected,
+            max_occupancy: 2,
+            room_type: DEFAULT_ROOM_TYPE.to_string(),
+        }
+    }
+
+    fn booking_with_preferences(id: i64, preferences: RoomPreferences) -> Booking {
+        Booking {
+            preferences,
+            ..fake_booking(id, 1, 3)
+        }
+    }
+
+    #[test]
+    fn test_scoring_prefers_high_floor() {
+        let rooms = vec![
+            room(1, 1, false, false, false),
+            room(2, 3, false, false, false),
+            room(3, 2, false, false, false),
+        ];
+        let checkin_booking = booking_with_preferences(
+            1,
+            RoomPreferences {
+                high_floor: true,
+                ..Default::default()
+            },
+        );
+
+        let assigned_room = assign_room_for_checkin(
+            &rooms,
+            vec![],
+            &checkin_booking,
+            &PreferenceScoringStrategy,
+            false,
+        );
+
+        assert_eq!(assigned_room, Some(2));
+    }
+
+    #[test]
+    fn test_scoring_maximizes_number_of_satisfied_preferences() {
+        let rooms = vec![
+            room(1, 1, true, false, false),
+            room(2, 1, false, true, false),
+            room(3, 1, true, true, false),
+        ];
+        let checkin_booking = booking_with_preferences(
+            1,
+            RoomPreferences {
+                quiet: true,
+                near_elevator: true,
+                ..Default::default()
+            },
+        );
+
+        let assigned_room = assign_room_for_checkin(
+            &rooms,
+            vec![],
+            &checkin_booking,
+            &PreferenceScoringStrategy,
+            false,
+        );
+
+        assert_eq!(assigned_room, Some(3));
+    }
+
+    #[test]
+    fn test_scoring_accessibility_outweighs_other_preferences() {
+        let rooms = vec![
+            room(1, 5, true, true, false),
+            room(2, 1, false, false, true),
+        ];
+        let checkin_booking = booking_with_preferences(
+            1,
+            RoomPreferences {
+                high_floor: true,
+                quiet: true,
+                near_elevator: true,
+                accessible: true,
+            },
+        );
+
+        let assigned_room = assign_room_for_checkin(
+            &rooms,
+            vec![],
+            &checkin_booking,
+            &PreferenceScoringStrategy,
+            false,
+        );
+
+        assert_eq!(assigned_room, Some(2));
+    }
+
+    #[test]
+    fn test_scoring_keeps_accessible_rooms_free() {
+        let rooms = vec![
+            room(1, 1, false, false, true),
+            room(2, 1, false, false, false),
+        ];
+        let checkin_booking = fake_booking(1, 1, 3);
+
+        let scored_room = assign_room_for_checkin(
+            &rooms,
+            vec![],
+            &checkin_booking,
+            &PreferenceScoringStrategy,
+            false,
+        );
+        let first_fit_room =
+            assign_room_for_checkin(&rooms, vec![], &checkin_booking, &FirstFitStrategy, false);
+
+        assert_eq!(scored_room, Some(2));
+        assert_eq!(first_fit_room, Some(1));
+    }
+
+    #[test]
+    fn test_scoring_only_considers_free_rooms() {
+        let rooms = vec![
+            room(1, 1, false, false, false),
+            room(2, 9, false, false, false),
+        ];
+        let existing_bookings = vec![fake_booking_with_room(1, 1, 5, Some(2))];
+        let checkin_booking = booking_with_preferences(
+            2,
+            RoomPreferences {
+                high_floor: true,
+                ..Default::default()
+            },
+        );
+
+        let assigned_room = assign_room_for_checkin(
+            &rooms,
+            existing_bookings,
+            &checkin_booking,
+            &PreferenceScoringStrategy,
+            false,
+        );
+
+        assert_eq!(assigned_room, Some(1));
+    }
+
+    #[test]
+    fn test_scoring_keeps_preassigned_room() {
+        let rooms = vec![
+            room(1, 1, false, false, false),
+            room(2, 9, false, false, false),
+        ];
+        let checkin_booking = Booking {
+            room_number: Some(1),
+            ..booking_with_preferences(
+                1,
+                RoomPreferences {
+                    high_floor: true,
+                    ..Default::default()
+                },
+            )
+        };
+
+        let assigned_room = assign_room_for_checkin(
+            &rooms,
+            vec![],
+            &checkin_booking,
+            &PreferenceScoringStrategy,
+            false,
+        );
+
+        assert_eq!(assigned_room, Some(1));
+    }
+
+    fn room_with_status(room_number: i32, housekeeping_status: HousekeepingStatus) -> Room {
+        Room {
+            housekeeping_status,
+            ..Room::with_defaults(room_number)
+        }
+    }
+
+    fn room_with_occupancy(room_number: i32, max_occupancy: i32) -> Room {
+        Room {
+            max_occupancy,
+            ..Room::with_defaults(room_number)
+        }
+    }
+
+    fn room_of_type(room_number: i32, room_type: &str) -> Room {
+        Room {
+            room_type: room_type.to_string(),
+            ..Room::with_defaults(room_number)
+        }
+    }
+
+    #[test]
+    fn test_bookings_only_get_rooms_of_their_type() {
+        // The only suite is taken from Jan 1 to Jan 5
+        let hotel_rooms = vec![room_of_type(1, DEFAULT_ROOM_TYPE), room_of_type(2, "suite")];
+        let existing_bookings = vec![Booking {
+            room_type: Some("suite".to_string()),
+            ..fake_booking(1, 1, 5)
+        }];
+
+        let (start, end) = request_booking(2, 4);
+        let fits = |room_type| {
+            can_accommodate_party(
+                &hotel_rooms,
+                0,
+                existing_bookings.clone(),
+                start,
+                end,
+                1,
+                room_type,
+            )
+        };
+        assert!(!fits(Some("suite")));
+        assert!(fits(Some(DEFAULT_ROOM_TYPE)));
+        // Bookings without a type may get any room
+        assert!(fits(None));
+
+        // At check-in, a suite guest isn't given a standard room either
+        let checkin_booking = Booking {
+            room_type: Some("suite".to_string()),
+            ..fake_booking(2, 2, 4)
+        };
+        let occupied = vec![Booking {
+            room_number: Some(2),
+            ..existing_bookings[0].clone()
+        }];
+        let assigned_room = assign_room_for_checkin(
+            &hotel_rooms,
+            occupied,
+            &checkin_booking,
+            &FirstFitStrategy,
+            false,
+        );
+        assert_eq!(assigned_room, None);
+    }
+
+    #[test]
+    fn test_repack_only_moves_preassignments_to_rooms_of_their_type() {
+        // The suite guest pre-assigned to room 2 can only move to the other suite
+        let hotel_rooms = vec![
+            room_of_type(1, DEFAULT_ROOM_TYPE),
+            room_of_type(2, "suite"),
+            room_of_type(3, "suite"),
+        ];
+        let existing_bookings = vec![Booking {
+            room_number: Some(2),
+            room_type: Some("suite".to_string()),
+            ..fake_booking(1, 1, 5)
+        }];
+        let (start, end) = request_booking(2, 4);
+
+        let result = repack_preassignments(
+            &hotel_rooms,
+            existing_bookings.clone(),
+            start,
+            end,
+            Some(2),
+            1,
+            Some("suite"),
+        );
+        assert_eq!(result.unwrap()[0].to_room, 3);
+
+        let hotel_rooms = vec![room_of_type(1, DEFAULT_ROOM_TYPE), room_of_type(2, "suite")];
+        let result = repack_preassignments(
+            &hotel_rooms,
+            existing_bookings,
+            start,
+            end,
+            Some(2),
+            1,
+            Some("suite"),
+        );
+        assert!(result.is_none());
+    }
+
+    #[test]
+    fn test_assign_room_for_checkin_skips_rooms_too_small_for_party() {
+        let rooms = vec![
+            room_with_occupancy(1, 2),
+            room_with_occupancy(2, 3),
+            room_with_occupancy(3, 4),
+        ];
+        let checkin_booking = Booking {
+            adults: 2,
+            children: 2,
+            ..fake_booking(1, 1, 3)
+        };
+
+        let assigned_room =
+            assign_room_for_checkin(&rooms, vec![], &checkin_booking, &FirstFitStrategy, false);
+
+        assert_eq!(assigned_room, Some(3));
+    }
+
+    #[test]
+    fn test_assign_room_for_checkin_ignores_preassigned_room_too_small_for_party() {
+        let rooms = vec![room_with_occupancy(1, 4), room_with_occupancy(2, 2)];
+        let checkin_booking = Booking {
+            adults: 3,
+            ..preassigned_booking(1, 1, 3, 2, false)
+        };
+
+        let assigned_room =
+            assign_room_for_checkin(&rooms, vec![], &checkin_booking, &FirstFitStrategy, false);
+
+        assert_eq!(assigned_room, Some(1));
+    }
+
+    #[test]
+    fn test_assign_room_for_checkin_prefers_inspected_rooms() {
+        let rooms = vec![
+            room_with_status(1, HousekeepingStatus::Dirty),
+            room_with_status(2, HousekeepingStatus::Clean),
+            room_with_status(3, HousekeepingStatus::Inspected),
+        ];
+        let checkin_booking = fake_booking(1, 1, 3);
+
+        let assigned_room =
+            assign_room_for_checkin(&rooms, vec![], &checkin_booking, &FirstFitStrategy, false);
+
+        assert_eq!(assigned_room, Some(3));
+    }
+
+    #[test]
+    fn test_assign_room_for_checkin_falls_back_to_dirty_rooms() {
+        let rooms = vec![
+            room_with_status(1, HousekeepingStatus::Dirty),
+            room_with_status(2, HousekeepingStatus::Cleaning),
+        ];
+        let checkin_booking = fake_booking(1, 1, 3);
+
+        let assigned_room =
+            assign_room_for_checkin(&rooms, vec![], &checkin_booking, &FirstFitStrategy, false);
+
+        assert_eq!(assigned_room, Some(2));
+    }
+
+    #[test]
+    fn test_assign_room_for_checkin_can_refuse_dirty_rooms() {
+        let rooms = vec![
+            room_with_status(1, HousekeepingStatus::Dirty),
+            room_with_status(2, HousekeepingStatus::Cleaning),
+        ];
+        let checkin_booking = Booking {
+            room_number: Some(1),
+            ..fake_booking(1, 1, 3)
+        };
+
+        let assigned_room =
+            assign_room_for_checkin(&rooms, vec![], &checkin_booking, &FirstFitStrategy, true);
+
+        assert_eq!(assigned_room, None);
+    }
+
+    fn connection(room_a: i32, room_b: i32, kind: RoomConnectionKind) -> RoomConnection {
+        RoomConnection {
+            room_a,
+            room_b,
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_allocate_group_rooms_without_constraint() {
+        let existing_bookings = vec![fake_booking_with_room(1, 1, 5, Some(1))];
+        let (start, end) = request_booking(2, 4);
+
+        let rooms = allocate_group_rooms(
+            &rooms(4),
+            existing_bookings,
+            start,
+            end,
+            &[1; 2],
+            GroupRoomConstraint::None,
+            &[],
+        );
+
+        assert_eq!(rooms, Ok(vec![2, 3]));
+    }
+
+    #[test]
+    fn test_allocate_group_rooms_not_enough_rooms() {
+        let existing_bookings = vec![fake_booking(1, 1, 5), fake_booking(2, 3, 8)];
+        let (start, end) = request_booking(4, 6);
+
+        let rooms = allocate_group_rooms(
+            &rooms(4),
+            existing_bookings,
+            start,
+            end,
+            &[1; 3],
+            GroupRoomConstraint::None,
+            &[],
+        );
+
+        assert_eq!(
+            rooms,
+            Err(GroupAllocationError::NotEnoughRooms {
+                requested: 3,
+                available: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_allocate_group_rooms_is_all_or_nothing() {
+        // Rooms 1 and 2 are each free for part of the stay only, so just room 3 could be given to
+        // the group, which isn't enough for any of it to be booked
+        let existing_bookings = vec![
+            fake_booking_with_room(1, 1, 5, Some(1)),
+            fake_booking_with_room(2, 5, 8, Some(2)),
+        ];
+        let (start, end) = request_booking(3, 7);
+
+        let rooms = allocate_group_rooms(
+            &rooms(3),
+            existing_bookings,
+            start,
+            end,
+            &[1; 2],
+            GroupRoomConstraint::None,
+            &[],
+        );
+
+        assert_eq!(
+            rooms,
+            Err(GroupAllocationError::NotEnoughRooms {
+                requested: 2,
+                available: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_allocate_group_rooms_adjacent() {
+        // Rooms 1-2-3 are next to each other, but room 1 is taken
+        let connections = vec![
+            connection(1, 2, RoomConnectionKind::Adjacent),
+            connection(2, 3, RoomConnectionKind::Connecting),
+        ];
+        let existing_bookings = vec![fake_booking_with_room(1, 1, 5, Some(1))];
+        let (start, end) = request_booking(2, 4);
+
+        let rooms = allocate_group_rooms(
+            &rooms(4),
+            existing_bookings,
+            start,
+            end,
+            &[1; 2],
+            GroupRoomConstraint::Adjacent,
+            &connections,
+        );
+
+        assert_eq!(rooms, Ok(vec![2, 3]));
+    }
+
+    #[test]
+    fn test_allocate_group_rooms_connecting_ignores_adjacent_rooms() {
+        let connections = vec![
+            connection(1, 2, RoomConnectionKind::Adjacent),
+            connection(3, 4, RoomConnectionKind::Connecting),
+        ];
+        let (start, end) = request_booking(2, 4);
+
+        let rooms = allocate_group_rooms(
+            &rooms(4),
+            vec![],
+            start,
+            end,
+            &[1; 2],
+            GroupRoomConstraint::Connecting,
+            &connections,
+        );
+
+        assert_eq!(rooms, Ok(vec![3, 4]));
+    }
+
+    #[test]
+    fn test_allocate_group_rooms_not_enough_connected_rooms() {
+        let connections = vec![
+            connection(1, 2, RoomConnectionKind::Adjacent),
+            connection(2, 3, RoomConnectionKind::Adjacent),
+            connection(4, 5, RoomConnectionKind::Adjacent),
+        ];
+        let existing_bookings = vec![preassigned_booking(1, 1, 5, 2, true)];
+        let (start, end) = request_booking(2, 4);
+
+        let rooms = allocate_group_rooms(
+            &rooms(5),
+            existing_bookings,
+            start,
+            end,
+            &[1; 3],
+            GroupRoomConstraint::Adjacent,
+            &connections,
+        );
+
+        assert_eq!(
+            rooms,
+            Err(GroupAllocationError::NotEnoughConnectedRooms {
+                requested: 3,
+                largest_block: 2
+            })
+        );
+    }
+
+    #[test]
+    fn test_allocate_group_rooms_connected_rooms_needed_elsewhere() {
+        // The unassigned booking for Jan 1-10 can only go to room 1 or 2, as room 3 is
+        // pre-assigned on Jan 8-9
+        let connections = vec![connection(1, 2, RoomConnectionKind::Adjacent)];
+        let existing_bookings = vec![
+            fake_booking(1, 1, 10),
+            preassigned_booking(2, 8, 9, 3, true),
+        ];
+        let (start, end) = request_booking(5, 7);
+
+        let rooms = allocate_group_rooms(
+            &rooms(3),
+            existing_bookings,
+            start,
+            end,
+            &[1; 2],
+            GroupRoomConstraint::Adjacent,
+            &connections,
+        );
+
+        assert_eq!(
+            rooms,
+            Err(GroupAllocationError::ConnectedRoomsNeededElsewhere { requested: 2 })
+        );
+    }
+
+    #[test]
+    fn test_allocate_group_rooms_only_uses_rooms_which_fit() {
+        let hotel_rooms = vec![
+            room_with_occupancy(1, 2),
+            room_with_occupancy(2, 4),
+            room_with_occupancy(3, 2),
+            room_with_occupancy(4, 4),
+        ];
+        let (start, end) = request_booking(2, 4);
+
+        let rooms = allocate_group_rooms(
+            &hotel_rooms,
+            vec![],
+            start,
+            end,
+            &[3, 3],
+            GroupRoomConstraint::None,
+            &[],
+        );
+        assert_eq!(rooms, Ok(vec![2, 4]));
+
+        let rooms = allocate_group_rooms(
+            &hotel_rooms,
+            vec![],
+            start,
+            end,
+            &[3, 3, 3],
+            GroupRoomConstraint::None,
+            &[],
+        );
+        assert_eq!(
+            rooms,
+            Err(GroupAllocationError::NotEnoughRooms {
+                requested: 3,
+                available: 2
+            })
+        );
+
+        // Rooms 1 and 2 are next to each other, but room 1 is too small for the group
+        let connections = vec![
+            connection(1, 2, RoomConnectionKind::Adjacent),
+            connection(3, 4, RoomConnectionKind::Adjacent),
+        ];
+        let rooms = allocate_group_rooms(
+            &hotel_rooms,
+            vec![],
+            start,
+            end,
+            &[3, 3],
+            GroupRoomConstraint::Adjacent,
+            &connections,
+        );
+        assert_eq!(
+            rooms,
+            Err(GroupAllocationError::NotEnoughConnectedRooms {
+                requested: 2,
+                largest_block: 1
+            })
+        );
+    }
+
+    #[test]
+    fn test_nightly_availability() {
+        let bookings = vec![
+            fake_booking(1, 1, 3),
+            fake_booking(2, 2, 4),
+            fake_booking_with_room(3, 2, 3, Some(3)),
+        ];
+        let (from, to) = request_booking(1, 5);
+
+        let free_rooms: Vec<_> = nightly_availability(3, &bookings, from, to)
+            .into_iter()
+            .map(|night| (night.date.day(), night.free_rooms))
+            .collect();
+
+        // The end date of a booking is its departure day, so the room is free that night
+        assert_eq!(free_rooms, vec![(1, 2), (2, 0), (3, 2), (4, 3)]);
+    }
+
+    #[test]
+    fn test_nightly_availability_never_negative() {
+        let bookings = vec![fake_booking(1, 1, 3), fake_booking(2, 1, 3)];
+        let (from, to) = request_booking(1, 2);
+
+        let availability = nightly_availability(1, &bookings, from, to);
+
+        assert_eq!(availability[0].free_rooms, 0);
+    }
 }
@@ -0,0 +1,102 @@
+use crate::models::{NightlyPrice, TaxCharge, TaxLine, TaxRule};
+
+/// Rates are in basis points, hundredths of a percent
+const BASIS_POINTS_PER_UNIT: i64 = 10_000;
+
+/// The given rate in basis points of an amount, rounded half away from zero to whole minor units.
+fn rate_of(amount: i64, basis_points: i64) -> i64 {
+    let scaled = amount * basis_points;
+    (scaled + BASIS_POINTS_PER_UNIT / 2 * scaled.signum()) / BASIS_POINTS_PER_UNIT
+}
+
+/// The amount of a tax on a night with the given (discounted) room price.
+pub fn tax_on_night(charge: TaxCharge, room_price: i64, guests: i32) -> i64 {
+    match charge {
+        TaxCharge::Rate(basis_points) => rate_of(room_price, basis_points),
+        TaxCharge::PerPersonPerNight(amount) => amount * i64::from(guests),
+    }
+}
+
+/// Applies a hotel's tax rules to each night of a stay. Taxes are rounded night by night, so that
+/// the folio, which is charged a night at a time, adds up to the quote. Taxes which come to
+/// nothing are left out.
+pub fn tax_nights(rules: &[TaxRule], nights: &[NightlyPrice], guests: i32) -> Vec<TaxLine> {
+    nights
+        .iter()
+        .flat_map(|night| {
+            rules.iter().map(move |rule| TaxLine {
+                tax_rule_id: rule.id,
+                name: rule.name.clone(),
+                date: night.date,
+                amount: tax_on_night(rule.charge, night.amount, guests),
+            })
+        })
+        .filter(|line| line.amount != 0)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Datelike, NaiveDate};
+
+    fn rule(id: i64, name: &str, charge: TaxCharge) -> TaxRule {
+        TaxRule {
+            id,
+            hotel_id: 1,
+            name: name.to_string(),
+            charge,
+        }
+    }
+
+    fn night(day: u32, amount: i64) -> NightlyPrice {
+        NightlyPrice {
+            date: NaiveDate::from_ymd_opt(2024, 1, day).unwrap(),
+            amount,
+        }
+    }
+
+    #[test]
+    fn test_rates_are_exact_in_basis_points() {
+        // 8.1% of 123.45 is 9.99945
+        assert_eq!(tax_on_night(TaxCharge::Rate(810), 12345, 2), 1000);
+        // 7.7% of 100.00 is exactly 7.70
+        assert_eq!(tax_on_night(TaxCharge::Rate(770), 10000, 2), 770);
+        assert_eq!(tax_on_night(TaxCharge::Rate(0), 10000, 2), 0);
+    }
+
+    #[test]
+    fn test_city_tax_is_charged_per_guest() {
+        assert_eq!(
+            tax_on_night(TaxCharge::PerPersonPerNight(350), 10000, 3),
+            1050
+        );
+    }
+
+    #[test]
+    fn test_taxes_are_itemized_per_night_and_rule() {
+        let rules = vec![
+            rule(1, "VAT 8.1%", TaxCharge::Rate(810)),
+            rule(2, "City tax", TaxCharge::PerPersonPerNight(250)),
+            rule(3, "Service fee", TaxCharge::Rate(1000)),
+        ];
+        // The second night is free, so only the city tax is charged for it
+        let nights = vec![night(1, 9999), night(2, 0)];
+
+        let lines = tax_nights(&rules, &nights, 2);
+
+        let items: Vec<(&str, u32, i64)> = lines
+            .iter()
+            .map(|l| (l.name.as_str(), l.date.day(), l.amount))
+            .collect();
+        assert_eq!(
+            items,
+            vec![
+                ("VAT 8.1%", 1, 810),
+                ("City tax", 1, 500),
+                ("Service fee", 1, 1000),
+                ("City tax", 2, 500),
+            ]
+        );
+    }
+}
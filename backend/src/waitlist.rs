@@ -0,0 +1,256 @@
+use crate::db::{
+    get_and_lock_hotel_by_id, get_and_lock_overlapping_bookings, get_and_lock_waiting_entries,
+    get_booking_by_id, get_hotel_rooms, get_next_booking_id,
+};
+use crate::event_processor::EventProcessor;
+use crate::hotel_time::hotel_today;
+use crate::models::{
+    Booking, BookingStatus, DEFAULT_ROOM_TYPE, Room, RoomPreferences, WaitlistEntry,
+};
+use crate::models_events::{BookingHeldEvent, Event, WaitlistOfferedEvent};
+use crate::pricing::quote_stay;
+use crate::room_assignment::can_accommodate_party;
+use anyhow::Result;
+use chrono::{Duration, Utc};
+use sqlx::{Postgres, Transaction};
+use tracing::info;
+
+/// How long guests on the waitlist have to confirm a room offered to them
+const WAITLIST_OFFER_HOLD: Duration = Duration::hours(12);
+
+/// Decides which waitlist entries can be offered a room, checking them in order. An entry which
+/// doesn't fit (or whose party doesn't fit any free room) doesn't block the entries after it.
+/// Every offered entry takes up a room, so `existing_bookings` must contain all bookings
+/// overlapping any of the entries' stays.
+pub fn plan_waitlist_offers<'a>(
+    rooms: &[Room],
+    existing_bookings: Vec<Booking>,
+    entries: &'a [WaitlistEntry],
+) -> Vec<&'a WaitlistEntry> {
+    let mut bookings = existing_bookings;
+    let mut offers = Vec::new();
+
+    for entry in entries {
+        // Overbooked rooms aren't offered, only rooms of the default type which are physically free
+        if can_accommodate_party(
+            rooms,
+            0,
+            bookings.clone(),
+            entry.start_time,
+            entry.end_time,
+            entry.adults + entry.children,
+            Some(DEFAULT_ROOM_TYPE),
+        ) {
+            bookings.push(held_booking(entry));
+            offers.push(entry);
+        }
+    }
+
+    offers
+}
+
+fn held_booking(entry: &WaitlistEntry) -> Booking {
+    Booking {
+        id: -1,
+        hotel_id: entry.hotel_id,
+        room_number: None,
+        room_pinned: false,
+        guest_name: entry.guest_name.clone(),
+        start_time: entry.start_time,
+        end_time: entry.end_time,
+        status: BookingStatus::Held,
+        preferences: RoomPreferences::default(),
+        hold_expires_at: None,
+        group_id: None,
+        adults: entry.adults,
+        children: entry.children,
+        guest_id: None,
+        quote: None,
+        room_type: Some(DEFAULT_ROOM_TYPE.to_string()),
+    }
+}
+
+/// Called when (some nights of) a booking's room are released. Offers the freed capacity of the
+/// booking's hotel to the guests on its waitlist, by creating held bookings for them. Archived
+/// hotels don't make offers.
+///
+/// The hotel is locked exclusively, so that concurrent releases make their offers one after the
+/// other. Callers must have locked it before locking any of its bookings, as the waitlist
+/// entries and the bookings are locked here.
+pub async fn offer_released_rooms(
+    event_processor: &EventProcessor,
+    tx: &mut Transaction<'_, Postgres>,
+    released_booking_id: i64,
+) -> Result<()> {
+    let Some(released_booking) = get_booking_by_id(&mut **tx, released_booking_id).await? else {
+        return Ok(());
+    };
+    let Some(hotel) = get_and_lock_hotel_by_id(tx, released_booking.hotel_id).await? else {
+        return Ok(());
+    };
+    if hotel.archived_at.is_some() {
+        return Ok(());
+    }
+
+    let entries = get_and_lock_waiting_entries(tx, hotel.id, hotel_today(&hotel)).await?;
+    let (Some(window_start), Some(window_end)) = (
+        entries.iter().map(|e| e.start_time).min(),
+        entries.iter().map(|e| e.end_time).max(),
+    ) else {
+        return Ok(());
+    };
+
+    let bookings =
+        get_and_lock_overlapping_bookings(tx, hotel.id, window_start, window_end).await?;
+    let rooms = get_hotel_rooms(&mut **tx, &hotel).await?;
+
+    for entry in plan_waitlist_offers(&rooms, bookings, &entries) {
+        let quote = quote_stay(
+            tx,
+            hotel.id,
+            DEFAULT_ROOM_TYPE,
+            entry.start_time,
+            entry.end_time,
+            entry.adults + entry.children,
+            None,
+        )
+        .await?;
+        let booking_id = get_next_booking_id(tx).await?;
+        let expires_at = Utc::now() + WAITLIST_OFFER_HOLD;
+
+        // These events don't release any rooms, so they can't trigger further offers
+        let held_event = Event::BookingHeld(BookingHeldEvent {
+            booking_id,
+            hotel_id: hotel.id,
+            start_time: entry.start_time,
+            end_time: entry.end_time,
+            expires_at,
+            adults: entry.adults,
+            children: entry.children,
+            quote,
+            room_type: Some(DEFAULT_ROOM_TYPE.to_string()),
+        });
+        event_processor
+            .append_event(tx, booking_id, &held_event)
+            .await?;
+
+        let offered_event = Event::WaitlistOffered(WaitlistOfferedEvent {
+            entry_id: entry.id,
+            booking_id,
+            expires_at,
+        });
+        event_processor
+            .append_event(tx, entry.id, &offered_event)
+            .await?;
+
+        // There's no mail delivery yet, so the notification is only logged
+        info!(
+            "Notifying {} ({}): a room is held for them as booking {} until {}",
+            entry.guest_name,
+            entry.email.as_deref().unwrap_or("no email"),
+            booking_id,
+            expires_at
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::WaitlistStatus;
+    use chrono::NaiveDate;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, day).unwrap()
+    }
+
+    fn entry(id: i64, start_day: u32, end_day: u32) -> WaitlistEntry {
+        WaitlistEntry {
+            id,
+            hotel_id: 1,
+            guest_name: format!("Guest {}", id),
+            email: None,
+            start_time: date(start_day),
+            end_time: date(end_day),
+            adults: 1,
+            children: 0,
+            status: WaitlistStatus::Waiting,
+            booking_id: None,
+        }
+    }
+
+    fn booking(id: i64, start_day: u32, end_day: u32) -> Booking {
+        Booking {
+            id,
+            ..held_booking(&entry(id, start_day, end_day))
+        }
+    }
+
+    fn rooms(count: i32) -> Vec<Room> {
+        (1..=count).map(Room::with_defaults).collect()
+    }
+
+    fn offered_ids(offers: Vec<&WaitlistEntry>) -> Vec<i64> {
+        offers.iter().map(|e| e.id).collect()
+    }
+
+    #[test]
+    fn test_offers_rooms_in_waitlist_order() {
+        // One room is free from Jan 1 to Jan 5
+        let existing_bookings = vec![booking(1, 1, 5)];
+        let entries = vec![entry(10, 2, 4), entry(11, 1, 3)];
+
+        let offers = plan_waitlist_offers(&rooms(2), existing_bookings, &entries);
+
+        assert_eq!(offered_ids(offers), vec![10]);
+    }
+
+    #[test]
+    fn test_entries_which_dont_fit_are_skipped() {
+        // One room is free from Jan 3 on
+        let existing_bookings = vec![booking(1, 1, 10), booking(2, 1, 3)];
+        let entries = vec![entry(10, 1, 4), entry(11, 3, 5), entry(12, 5, 7)];
+
+        let offers = plan_waitlist_offers(&rooms(2), existing_bookings, &entries);
+
+        assert_eq!(offered_ids(offers), vec![11, 12]);
+    }
+
+    #[test]
+    fn test_no_offers_when_hotel_is_full() {
+        let existing_bookings = vec![booking(1, 1, 10)];
+        let entries = vec![entry(10, 2, 4)];
+
+        let offers = plan_waitlist_offers(&rooms(1), existing_bookings, &entries);
+
+        assert!(offers.is_empty());
+    }
+
+    #[test]
+    fn test_parties_are_only_offered_rooms_which_fit_them() {
+        // Room 1 is taken, and room 2 only fits two guests
+        let hotel_rooms = vec![
+            Room {
+                max_occupancy: 4,
+                ..Room::with_defaults(1)
+            },
+            Room::with_defaults(2),
+        ];
+        let existing_bookings = vec![Booking {
+            room_number: Some(1),
+            ..booking(1, 1, 10)
+        }];
+        let family = WaitlistEntry {
+            adults: 2,
+            children: 2,
+            ..entry(10, 2, 4)
+        };
+        let entries = vec![family, entry(11, 2, 4)];
+
+        let offers = plan_waitlist_offers(&hotel_rooms, existing_bookings, &entries);
+
+        assert_eq!(offered_ids(offers), vec![11]);
+    }
+}
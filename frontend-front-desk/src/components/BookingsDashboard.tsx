@@ -29,7 +29,7 @@ export default function BookingsDashboard() {
     if (!hotelId) return
 
     try {
-      const response = await fetch(`http://localhost:3000/hotels/${hotelId}`)
+      const response = await fetch(`http://localhost:3000/hotels/${hotelId}?include_archived=true`)
       if (response.ok) {
         const data = await response.json()
         // Convert BigInt fields to regular numbers for safe JavaScript handling
@@ -47,7 +47,7 @@ export default function BookingsDashboard() {
     }
   }
 
-  const handleCheckin = async (bookingId: string) => {
+  const handleCheckin = async (bookingId: string, earlyCheckin = false) => {
     // If offline, show room selector for manual checkin
     if (isOffline) {
       const booking = bookings.find(b => b.id === bookingId)
@@ -59,7 +59,7 @@ export default function BookingsDashboard() {
 
     // Online checkin (automatic room assignment by backend)
     try {
-      const response = await fetch(`http://localhost:3000/bookings/${bookingId}/checkin?today=${today}`, {
+      const response = await fetch(`http://localhost:3000/bookings/${bookingId}/checkin?early_checkin=${earlyCheckin}`, {
         method: 'POST',
       })
 
@@ -68,6 +68,11 @@ export default function BookingsDashboard() {
         // Electric will automatically update the UI when the backend processes the change
       } else {
         const errorData = await response.json()
+        // It's before the hotel's check-in time; the guest can still be let in if the desk confirms it
+        if (errorData.code === 'EARLY_CHECKIN' && window.confirm(`${errorData.error}. Check in early?`)) {
+          await handleCheckin(bookingId, true)
+          return
+        }
         console.error(`Failed to check in: ${errorData.error || 'Unknown error'}`)
       }
     } catch (error) {
@@ -77,16 +82,29 @@ export default function BookingsDashboard() {
     }
   }
 
-  const handleCheckout = async (bookingId: string) => {
+  const handleCheckout = async (bookingId: string, overrideBalance = false, lateCheckout = false) => {
     try {
-      const response = await fetch(`http://localhost:3000/bookings/${bookingId}/checkout`, {
-        method: 'POST',
-      })
+      const response = await fetch(
+        `http://localhost:3000/bookings/${bookingId}/checkout?override_balance=${overrideBalance}&late_checkout=${lateCheckout}`,
+        {
+          method: 'POST',
+        }
+      )
 
       if (response.ok) {
         // Electric will automatically update the UI when the backend processes the change
       } else {
         const errorData = await response.json()
+        // The folio isn't settled; the guest can still be checked out if the desk confirms it
+        if (errorData.code === 'BALANCE_OUTSTANDING' && window.confirm(`${errorData.error}. Check out anyway?`)) {
+          await handleCheckout(bookingId, true, lateCheckout)
+          return
+        }
+        // It's past the hotel's check-out time
+        if (errorData.code === 'LATE_CHECKOUT' && window.confirm(`${errorData.error}. Check out late?`)) {
+          await handleCheckout(bookingId, overrideBalance, true)
+          return
+        }
         console.error(`Failed to check out: ${errorData.error || 'Unknown error'}`)
       }
     } catch (error) {
@@ -98,6 +116,15 @@ export default function BookingsDashboard() {
 
   const handleCancel = async (bookingId: string) => {
     try {
+      // Late cancellations are charged, so make sure the guest agrees to the fee first
+      const feeResponse = await fetch(`http://localhost:3000/bookings/${bookingId}/cancellation-fee`)
+      if (feeResponse.ok) {
+        const { cancellation_fee, currency } = await feeResponse.json()
+        if (cancellation_fee > 0 && !window.confirm(`Cancelling now costs ${(cancellation_fee / 100).toFixed(2)} ${currency}. Cancel anyway?`)) {
+          return
+        }
+      }
+
       const response = await fetch(`http://localhost:3000/bookings/${bookingId}/cancel`, {
         method: 'POST',
       })
@@ -93,7 +93,11 @@ export const OfflineEventsProvider: React.FC<OfflineEventsProviderProps> = ({ ch
         type: 'offline_checkin',
         booking_id: event.bookingId,
         room_number: event.roomNumber,
-        today: event.today
+        today: event.today,
+        // Arrival rules are checked against when the guest was let in, not when this syncs. The
+        // desk already let them in, so an early arrival is accepted rather than refused.
+        checked_in_at: new Date(event.timestamp).toISOString(),
+        early_checkin: true
       }
 
       const response = await fetch(`http://localhost:3000/client-events`, {
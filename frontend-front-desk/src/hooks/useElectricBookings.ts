@@ -31,7 +31,8 @@ export function useElectricBookings(hotelId: string, today: string) {
   const [cachedData, setCachedData] = useState<Booking[]>([])
 
   const { data, error, stream } = useShape<Booking>({
-    url: `http://localhost:3000/hotels/${hotelId}/bookings/shape?date=${today}`,
+    // The backend syncs the bookings for today at the hotel
+    url: `http://localhost:3000/hotels/${hotelId}/bookings/shape`,
   })
 
   // Cache key for this hotel
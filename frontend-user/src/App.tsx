@@ -6,14 +6,34 @@ interface Hotel {
   name: string
 }
 
+interface Quote {
+  currency: string
+  // In minor units of the currency
+  total: number
+  // Taxes and fees on each night, on top of the total
+  taxes: { amount: number }[]
+}
+
+const totalWithTaxes = (quote: Quote) =>
+  quote.total + quote.taxes.reduce((sum, tax) => sum + tax.amount, 0)
+
+interface Hold {
+  bookingId: number
+  expiresAt: string
+  quote: Quote | null
+}
+
 function App() {
   const [hotels, setHotels] = useState<Hotel[]>([])
   const [selectedHotel, setSelectedHotel] = useState<Hotel | null>(null)
   const [guestName, setGuestName] = useState('')
   const [startDate, setStartDate] = useState('')
   const [endDate, setEndDate] = useState('')
+  const [adults, setAdults] = useState(1)
+  const [children, setChildren] = useState(0)
   const [loading, setLoading] = useState(false)
   const [message, setMessage] = useState('')
+  const [hold, setHold] = useState<Hold | null>(null)
 
   const loadHotels = async () => {
     try {
@@ -28,23 +48,57 @@ function App() {
   }
 
 
-  const createBooking = async (e: React.FormEvent) => {
+  // Holds a room for the selected dates while the guest enters their details
+  const holdRoom = async (e: React.FormEvent) => {
     e.preventDefault()
-    if (!selectedHotel || !guestName || !startDate || !endDate) return
+    if (!selectedHotel || !startDate || !endDate) return
 
     setLoading(true)
     setMessage('')
 
     try {
-      const response = await fetch(`http://localhost:3000/hotels/${selectedHotel.id}/bookings`, {
+      const response = await fetch(`http://localhost:3000/hotels/${selectedHotel.id}/holds`, {
         method: 'POST',
         headers: {
           'Content-Type': 'application/json'
         },
         body: JSON.stringify({
-          guest_name: guestName,
           start_time: startDate,
-          end_time: endDate
+          end_time: endDate,
+          adults,
+          children
+        })
+      })
+
+      const data = await response.json()
+
+      if (response.ok) {
+        setHold({ bookingId: data.booking_id, expiresAt: data.expires_at, quote: data.quote })
+      } else {
+        setMessage(`Error: ${data.error} ${data.code ? `(${data.code})` : ''}`)
+      }
+    } catch (error) {
+      setMessage('Failed to hold a room. Please try again.')
+    } finally {
+      setLoading(false)
+    }
+  }
+
+  const confirmBooking = async (e: React.FormEvent) => {
+    e.preventDefault()
+    if (!hold || !guestName) return
+
+    setLoading(true)
+    setMessage('')
+
+    try {
+      const response = await fetch(`http://localhost:3000/bookings/${hold.bookingId}/confirm`, {
+        method: 'POST',
+        headers: {
+          'Content-Type': 'application/json'
+        },
+        body: JSON.stringify({
+          guest_name: guestName
         })
       })
 
@@ -52,14 +106,20 @@ function App() {
 
       if (response.ok) {
         setMessage(`Booking created successfully! Booking ID: ${data.booking_id}`)
+        setHold(null)
         setGuestName('')
         setStartDate('')
         setEndDate('')
+        setAdults(1)
+        setChildren(0)
       } else {
+        if (data.code === 'HOLD_EXPIRED') {
+          setHold(null)
+        }
         setMessage(`Error: ${data.error} ${data.code ? `(${data.code})` : ''}`)
       }
     } catch (error) {
-      setMessage('Failed to create booking. Please try again.')
+      setMessage('Failed to confirm booking. Please try again.')
     } finally {
       setLoading(false)
     }
@@ -93,44 +153,90 @@ function App() {
         <div className="booking-section">
           <h2>Book a Room at {selectedHotel.name}</h2>
 
-          <form onSubmit={createBooking} className="booking-form">
-            <div className="form-group">
-              <label htmlFor="guestName">Guest Name:</label>
-              <input
-                id="guestName"
-                type="text"
-                value={guestName}
-                onChange={(e) => setGuestName(e.target.value)}
-                required
-              />
-            </div>
-
-            <div className="form-group">
-              <label htmlFor="startDate">Check-in Date:</label>
-              <input
-                id="startDate"
-                type="date"
-                value={startDate}
-                onChange={(e) => setStartDate(e.target.value)}
-                required
-              />
-            </div>
-
-            <div className="form-group">
-              <label htmlFor="endDate">Check-out Date:</label>
-              <input
-                id="endDate"
-                type="date"
-                value={endDate}
-                onChange={(e) => setEndDate(e.target.value)}
-                required
-              />
-            </div>
-
-            <button type="submit" disabled={loading}>
-              {loading ? 'Creating Booking...' : 'Create Booking'}
-            </button>
-          </form>
+          {!hold ? (
+            <form onSubmit={holdRoom} className="booking-form">
+              <div className="form-group">
+                <label htmlFor="startDate">Check-in Date:</label>
+                <input
+                  id="startDate"
+                  type="date"
+                  value={startDate}
+                  onChange={(e) => setStartDate(e.target.value)}
+                  required
+                />
+              </div>
+
+              <div className="form-group">
+                <label htmlFor="endDate">Check-out Date:</label>
+                <input
+                  id="endDate"
+                  type="date"
+                  value={endDate}
+                  onChange={(e) => setEndDate(e.target.value)}
+                  required
+                />
+              </div>
+
+              <div className="form-group">
+                <label htmlFor="adults">Adults:</label>
+                <input
+                  id="adults"
+                  type="number"
+                  min={1}
+                  value={adults}
+                  onChange={(e) => setAdults(Number(e.target.value))}
+                  required
+                />
+              </div>
+
+              <div className="form-group">
+                <label htmlFor="children">Children:</label>
+                <input
+                  id="children"
+                  type="number"
+                  min={0}
+                  value={children}
+                  onChange={(e) => setChildren(Number(e.target.value))}
+                  required
+                />
+              </div>
+
+              <button type="submit" disabled={loading}>
+                {loading ? 'Holding Room...' : 'Hold Room'}
+              </button>
+            </form>
+          ) : (
+            <form onSubmit={confirmBooking} className="booking-form">
+              <p>
+                Room held from {startDate} to {endDate} until{' '}
+                {new Date(hold.expiresAt).toLocaleTimeString()}.
+              </p>
+              {hold.quote && (
+                <p>
+                  Total price, taxes included:{' '}
+                  {new Intl.NumberFormat(undefined, {
+                    style: 'currency',
+                    currency: hold.quote.currency,
+                  }).format(totalWithTaxes(hold.quote) / 100)}
+                </p>
+              )}
+
+              <div className="form-group">
+                <label htmlFor="guestName">Guest Name:</label>
+                <input
+                  id="guestName"
+                  type="text"
+                  value={guestName}
+                  onChange={(e) => setGuestName(e.target.value)}
+                  required
+                />
+              </div>
+
+              <button type="submit" disabled={loading}>
+                {loading ? 'Confirming Booking...' : 'Confirm Booking'}
+              </button>
+            </form>
+          )}
 
           {message && (
             <div className={`message ${message.includes('Error') ? 'error' : 'success'}`}>